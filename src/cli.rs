@@ -96,6 +96,10 @@ pub enum Commands {
         #[arg(long)]
         inspect: bool,
 
+        /// Enable inspector and pause on the first statement until a debugger attaches
+        #[arg(long)]
+        inspect_brk: bool,
+
         /// Inspector port
         #[arg(long, default_value = "9229")]
         inspect_port: u16,
@@ -104,6 +108,22 @@ pub enum Commands {
         #[arg(long)]
         enable_source_maps: bool,
 
+        /// Write a CPU profile (.cpuprofile) on exit
+        #[arg(long)]
+        cpu_prof: bool,
+
+        /// Directory for CPU profiles (defaults to the current directory)
+        #[arg(long, value_name = "DIR")]
+        cpu_prof_dir: Option<PathBuf>,
+
+        /// CPU profile sampling interval in microseconds
+        #[arg(long, value_name = "US", default_value = "1000")]
+        cpu_prof_interval: u32,
+
+        /// Write a heap snapshot to the current directory on a signal (e.g. SIGUSR2)
+        #[arg(long, value_name = "SIGNAL")]
+        heap_snapshot_signal: Option<String>,
+
         /// Check script without executing
         #[arg(long)]
         check: bool,
@@ -115,6 +135,10 @@ pub enum Commands {
         /// Watch mode for development
         #[arg(long)]
         watch: bool,
+
+        /// Terminate execution that runs longer than this many milliseconds
+        #[arg(long, value_name = "MS")]
+        timeout: Option<u64>,
     },
 
     /// Start an interactive REPL
@@ -455,10 +479,11 @@ impl Commands {
         match self {
             Commands::Run {
                 inspect,
+                inspect_brk,
                 inspect_port,
                 ..
             } => {
-                if *inspect {
+                if *inspect || *inspect_brk {
                     Some(*inspect_port)
                 } else {
                     None
@@ -468,6 +493,43 @@ impl Commands {
         }
     }
 
+    /// Check if execution should pause until a debugger attaches
+    pub fn inspect_brk(&self) -> bool {
+        matches!(self, Commands::Run { inspect_brk: true, .. })
+    }
+
+    /// Get the CPU profile directory and sampling interval (if profiling)
+    pub fn cpu_prof(&self) -> Option<(PathBuf, u32)> {
+        match self {
+            Commands::Run {
+                cpu_prof: true,
+                cpu_prof_dir,
+                cpu_prof_interval,
+                ..
+            } => Some((
+                cpu_prof_dir.clone().unwrap_or_else(|| PathBuf::from(".")),
+                *cpu_prof_interval,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Get the signal that writes a heap snapshot (if set)
+    pub fn heap_snapshot_signal(&self) -> Option<&str> {
+        match self {
+            Commands::Run { heap_snapshot_signal, .. } => heap_snapshot_signal.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Get the execution timeout in milliseconds (if set)
+    pub fn timeout_ms(&self) -> Option<u64> {
+        match self {
+            Commands::Run { timeout, .. } => *timeout,
+            _ => None,
+        }
+    }
+
     /// Check if watch mode is enabled
     pub fn watch_mode(&self) -> bool {
         match self {
@@ -587,6 +649,58 @@ mod tests {
         assert_eq!(cli.command.inspect_enabled(), Some(3000));
     }
 
+    #[test]
+    fn test_parse_inspect_brk() {
+        let cli = parse_args_from(strs(&["ferrum", "run", "script.js", "--inspect-brk"])).unwrap();
+
+        assert_eq!(cli.command.inspect_enabled(), Some(9229));
+        assert!(cli.command.inspect_brk());
+
+        let cli = parse_args_from(strs(&["ferrum", "run", "script.js", "--inspect"])).unwrap();
+        assert!(!cli.command.inspect_brk());
+    }
+
+    #[test]
+    fn test_parse_cpu_prof() {
+        let cli = parse_args_from(strs(&["ferrum", "run", "script.js"])).unwrap();
+        assert_eq!(cli.command.cpu_prof(), None);
+
+        let cli = parse_args_from(strs(&[
+            "ferrum",
+            "run",
+            "script.js",
+            "--cpu-prof",
+            "--cpu-prof-dir",
+            "profiles",
+            "--cpu-prof-interval",
+            "100",
+        ]))
+        .unwrap();
+
+        assert_eq!(cli.command.cpu_prof(), Some((PathBuf::from("profiles"), 100)));
+    }
+
+    #[test]
+    fn test_parse_heap_snapshot_signal() {
+        let cli = parse_args_from(strs(&[
+            "ferrum",
+            "run",
+            "script.js",
+            "--heap-snapshot-signal",
+            "SIGUSR2",
+        ]))
+        .unwrap();
+
+        assert_eq!(cli.command.heap_snapshot_signal(), Some("SIGUSR2"));
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        let cli = parse_args_from(strs(&["ferrum", "run", "script.js", "--timeout", "500"])).unwrap();
+
+        assert_eq!(cli.command.timeout_ms(), Some(500));
+    }
+
     #[test]
     fn test_parse_test_command() {
         let cli = parse_args_from(strs(&["ferrum", "test", "--allow-all"])).unwrap();
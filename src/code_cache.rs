@@ -0,0 +1,165 @@
+//! V8 Code Cache
+//!
+//! This module persists the code cache V8 produces for compiled modules and
+//! classic scripts, so later runs can skip parsing and compiling sources
+//! that did not change.
+//!
+//! # Architecture
+//!
+//! Every specifier maps to one file in the cache directory. Each entry
+//! starts with a header holding the V8 cached data version tag and a hash
+//! of the source it was produced from; entries whose header does not match
+//! the current V8 build or the current source are treated as missing and
+//! overwritten once the source has been compiled again. V8 performs its own
+//! checks as well: data it rejects on consumption is replaced the same way.
+
+use std::path::{Path, PathBuf};
+
+use v8;
+
+/// Magic bytes at the start of each cache entry
+const MAGIC: &[u8; 4] = b"FRCC";
+
+/// Size of the entry header (magic, version tag, source hash)
+const HEADER_LEN: usize = 4 + 4 + 8;
+
+/// On-disk cache of V8 code cache data
+#[derive(Debug, Clone)]
+pub struct CodeCache {
+    /// Directory holding the cache entries
+    dir: PathBuf,
+    /// Version tag of the V8 build producing and consuming the data
+    version_tag: u32,
+}
+
+impl CodeCache {
+    /// Create a code cache stored in a directory
+    ///
+    /// The directory is created when the first entry is written.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            version_tag: v8::script_compiler::cached_data_version_tag(),
+        }
+    }
+
+    /// Get the cache directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Get the cached code for a source
+    ///
+    /// # Arguments
+    /// * `specifier` - Resolved module specifier or script file name
+    /// * `source` - Current source code
+    ///
+    /// # Returns
+    /// The cached data, or `None` if there is no entry or it is stale
+    pub fn get(&self, specifier: &str, source: &str) -> Option<Vec<u8>> {
+        let entry = std::fs::read(self.entry_path(specifier)).ok()?;
+        if entry.len() <= HEADER_LEN || entry[..HEADER_LEN] != self.header(source) {
+            tracing::debug!("Ignoring stale code cache for {}", specifier);
+            return None;
+        }
+        Some(entry[HEADER_LEN..].to_vec())
+    }
+
+    /// Store the code cache of a source
+    ///
+    /// Caching is best effort: write failures are logged and ignored.
+    ///
+    /// # Arguments
+    /// * `specifier` - Resolved module specifier or script file name
+    /// * `source` - Source code the data was produced from
+    /// * `data` - Code cache produced by V8
+    pub fn set(&self, specifier: &str, source: &str, data: &[u8]) {
+        let mut entry = Vec::with_capacity(HEADER_LEN + data.len());
+        entry.extend_from_slice(&self.header(source));
+        entry.extend_from_slice(data);
+
+        if let Err(e) = self.write_entry(&self.entry_path(specifier), &entry) {
+            tracing::warn!("Failed to write code cache for {}: {}", specifier, e);
+        }
+    }
+
+    /// Remove all cache entries
+    pub fn clear(&self) -> std::io::Result<()> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Build the entry header for a source
+    fn header(&self, source: &str) -> [u8; HEADER_LEN] {
+        let mut header = [0; HEADER_LEN];
+        header[..4].copy_from_slice(MAGIC);
+        header[4..8].copy_from_slice(&self.version_tag.to_le_bytes());
+        header[8..].copy_from_slice(&fnv1a(source.as_bytes()).to_le_bytes());
+        header
+    }
+
+    /// Get the path of the entry for a specifier
+    fn entry_path(&self, specifier: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.bin", fnv1a(specifier.as_bytes())))
+    }
+
+    /// Write an entry atomically so concurrent runs never read a partial file
+    fn write_entry(&self, path: &Path, entry: &[u8]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+        std::fs::write(&tmp_path, entry)?;
+        std::fs::rename(&tmp_path, path)
+    }
+}
+
+/// 64-bit FNV-1a hash (stable across builds, unlike `DefaultHasher`)
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_cache(dir: &TempDir) -> CodeCache {
+        crate::runtime::init_v8_platform();
+        CodeCache::new(dir.path().join("code_cache"))
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let cache = test_cache(&dir);
+
+        assert!(cache.get("file:///main.js", "1 + 1").is_none());
+        cache.set("file:///main.js", "1 + 1", &[1, 2, 3]);
+        assert_eq!(cache.get("file:///main.js", "1 + 1"), Some(vec![1, 2, 3]));
+        assert!(cache.get("file:///other.js", "1 + 1").is_none());
+    }
+
+    #[test]
+    fn test_stale_and_invalid_entries_are_rejected() {
+        let dir = TempDir::new().unwrap();
+        let cache = test_cache(&dir);
+
+        cache.set("file:///main.js", "1 + 1", &[1, 2, 3]);
+        assert!(cache.get("file:///main.js", "2 + 2").is_none());
+
+        std::fs::write(cache.entry_path("file:///main.js"), b"garbage").unwrap();
+        assert!(cache.get("file:///main.js", "1 + 1").is_none());
+
+        cache.clear().unwrap();
+        assert!(!cache.dir().exists());
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
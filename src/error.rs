@@ -0,0 +1,243 @@
+//! JavaScript Errors
+//!
+//! This module converts exceptions thrown by JavaScript into [`JsError`],
+//! a plain Rust value carrying the exception message, its stack trace and
+//! the source location it was thrown from. Runtime errors embed it so
+//! embedders and the CLI can report exceptions with a code frame.
+
+use std::fmt;
+
+use v8;
+
+/// A JavaScript exception captured from V8
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsError {
+    /// Error name (e.g. `TypeError`), `None` for non-Error exceptions
+    pub name: Option<String>,
+    /// Error message
+    pub message: String,
+    /// Stack trace as reported by the `stack` property
+    pub stack: Option<String>,
+    /// Name of the script or module the exception was thrown from
+    pub file_name: Option<String>,
+    /// Line number (1-based)
+    pub line_number: Option<usize>,
+    /// Column number (1-based)
+    pub column_number: Option<usize>,
+    /// Source line the exception was thrown from
+    pub source_line: Option<String>,
+}
+
+impl JsError {
+    /// Create an error from a message only (no JavaScript location)
+    pub fn from_message(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Capture a JavaScript exception
+    ///
+    /// # Arguments
+    /// * `scope` - V8 handle scope with a context entered
+    /// * `exception` - The thrown value
+    ///
+    /// # Returns
+    /// The exception's name, message and stack (when it is an `Error`) and
+    /// the location it was thrown from
+    pub fn from_v8_exception(scope: &mut v8::HandleScope, exception: v8::Local<v8::Value>) -> Self {
+        // Reading properties may run getters; keep their exceptions contained
+        let scope = &mut v8::TryCatch::new(scope);
+
+        let mut error = Self::default();
+
+        match v8::Local::<v8::Object>::try_from(exception) {
+            Ok(object) => {
+                error.name = get_string_property(scope, object, "name");
+                error.stack = get_string_property(scope, object, "stack");
+                error.message = get_string_property(scope, object, "message")
+                    .unwrap_or_else(|| exception.to_rust_string_lossy(scope));
+            }
+            Err(_) => {
+                error.message = exception.to_rust_string_lossy(scope);
+            }
+        }
+
+        let message = v8::Exception::create_message(scope, exception);
+        error.file_name = message
+            .get_script_resource_name(scope)
+            .filter(|name| name.is_string())
+            .map(|name| name.to_rust_string_lossy(scope))
+            .filter(|name| !name.is_empty());
+        error.line_number = message.get_line_number(scope).filter(|line| *line > 0);
+        if error.line_number.is_some() {
+            error.column_number = Some(message.get_start_column() + 1);
+            error.source_line = message
+                .get_source_line(scope)
+                .map(|line| line.to_rust_string_lossy(scope));
+        }
+
+        error
+    }
+
+    /// Format the location as `file:line:column`
+    pub fn location(&self) -> Option<String> {
+        let file_name = self.file_name.as_deref().unwrap_or("<anonymous>");
+        let line = self.line_number?;
+        match self.column_number {
+            Some(column) => Some(format!("{}:{}:{}", file_name, line, column)),
+            None => Some(format!("{}:{}", file_name, line)),
+        }
+    }
+
+    /// Render the source line with a caret under the error column
+    ///
+    /// Returns `None` if the source line is unknown.
+    pub fn code_frame(&self) -> Option<String> {
+        let source_line = self.source_line.as_deref()?;
+        let line = self.line_number?;
+        let gutter = line.to_string();
+        let padding = " ".repeat(gutter.len());
+        let column = self.column_number.unwrap_or(1);
+
+        // Keep tabs so the caret lines up with the source
+        let indent: String = source_line
+            .chars()
+            .take(column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        Some(format!(
+            "{} | {}\n{} | {}^",
+            gutter, source_line, padding, indent
+        ))
+    }
+
+    /// Format the error for display in a terminal
+    ///
+    /// Includes the stack trace (or location) followed by the code frame.
+    pub fn to_pretty_string(&self) -> String {
+        let mut output = match &self.stack {
+            Some(stack) if !stack.is_empty() => stack.clone(),
+            _ => match self.location() {
+                Some(location) => format!("{}\n    at {}", self.header(), location),
+                None => self.header(),
+            },
+        };
+
+        if let Some(code_frame) = self.code_frame() {
+            output.push_str("\n\n");
+            output.push_str(&code_frame);
+        }
+
+        output
+    }
+
+    /// Format the `name: message` header
+    fn header(&self) -> String {
+        match &self.name {
+            Some(name) if !name.is_empty() => format!("{}: {}", name, self.message),
+            _ => self.message.clone(),
+        }
+    }
+}
+
+impl fmt::Display for JsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.header())?;
+        if let Some(location) = self.location() {
+            write!(f, " (at {})", location)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for JsError {}
+
+impl From<String> for JsError {
+    fn from(message: String) -> Self {
+        Self::from_message(message)
+    }
+}
+
+impl From<&str> for JsError {
+    fn from(message: &str) -> Self {
+        Self::from_message(message)
+    }
+}
+
+impl From<String> for Box<JsError> {
+    fn from(message: String) -> Self {
+        Box::new(JsError::from_message(message))
+    }
+}
+
+impl From<&str> for Box<JsError> {
+    fn from(message: &str) -> Self {
+        Box::new(JsError::from_message(message))
+    }
+}
+
+/// Read an object property as a string, skipping `undefined`
+fn get_string_property(
+    scope: &mut v8::HandleScope,
+    object: v8::Local<v8::Object>,
+    name: &str,
+) -> Option<String> {
+    let key = v8::String::new(scope, name)?;
+    let value = object.get(scope, key.into())?;
+    if value.is_undefined() {
+        return None;
+    }
+    Some(value.to_rust_string_lossy(scope))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_error() -> JsError {
+        JsError {
+            name: Some("TypeError".to_string()),
+            message: "x is not a function".to_string(),
+            stack: None,
+            file_name: Some("main.js".to_string()),
+            line_number: Some(12),
+            column_number: Some(5),
+            source_line: Some("    x();".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            sample_error().to_string(),
+            "TypeError: x is not a function (at main.js:12:5)"
+        );
+        assert_eq!(JsError::from("plain message").to_string(), "plain message");
+    }
+
+    #[test]
+    fn test_code_frame() {
+        assert_eq!(
+            sample_error().code_frame().unwrap(),
+            "12 |     x();\n   |     ^"
+        );
+        assert!(JsError::from("no source").code_frame().is_none());
+
+        let error = JsError {
+            column_number: Some(0),
+            ..sample_error()
+        };
+        assert_eq!(error.code_frame().unwrap(), "12 |     x();\n   | ^");
+    }
+
+    #[test]
+    fn test_pretty_string_without_stack() {
+        assert_eq!(
+            sample_error().to_pretty_string(),
+            "TypeError: x is not a function\n    at main.js:12:5\n\n12 |     x();\n   |     ^"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Event Loop
+//!
+//! This module holds the per-isolate state of the event loop that drives
+//! asynchronous work scheduled from JavaScript (timers, async ops).
+//!
+//! # Architecture
+//!
+//! Every pending operation is a Rust future paired with the V8 context it was
+//! scheduled from. When the future completes it yields an [`OpCompletion`],
+//! a closure that is run back on the JavaScript thread inside that context
+//! (for example to call a timer callback or settle a promise).
+//!
+//! The [`EventLoop`] itself is stored in an isolate slot so that V8 callbacks
+//! can schedule work without access to the owning `JsRuntime`. The runtime
+//! polls the pending futures on its tokio runtime in
+//! `JsRuntime::run_event_loop`.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use v8;
+
+/// Identifier of a pending operation (also used as timer ID)
+pub type OpId = u64;
+
+/// Callback run on the JavaScript thread once a pending operation completes
+///
+/// The scope passed in has the operation's context entered and is wrapped in
+/// a `TryCatch`, so exceptions thrown here are reported by the event loop.
+pub type OpCompletion = Box<dyn FnOnce(&mut v8::HandleScope)>;
+
+/// Future driving a pending operation
+pub type OpFuture = Pin<Box<dyn Future<Output = OpCompletion>>>;
+
+/// A scheduled operation waiting for its future to complete
+struct PendingOp {
+    id: OpId,
+    context: v8::Global<v8::Context>,
+    future: OpFuture,
+}
+
+/// Per-isolate event loop state
+///
+/// Shared as `Rc<RefCell<EventLoop>>` through an isolate slot.
+#[derive(Default)]
+pub struct EventLoop {
+    /// Next operation ID to hand out
+    next_id: OpId,
+    /// Operations whose futures have not completed yet
+    pending: Vec<PendingOp>,
+    /// Timers that have not been cleared (used to re-arm intervals)
+    active_timers: HashSet<OpId>,
+    /// Promises rejected without a handler since the last check
+    unhandled_rejections: Vec<(v8::Global<v8::Promise>, v8::Global<v8::Value>)>,
+}
+
+impl EventLoop {
+    /// Create an empty event loop
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the event loop stored in the isolate
+    ///
+    /// # Panics
+    ///
+    /// Panics if the isolate was not created by `JsRuntime`.
+    pub fn from_isolate(isolate: &v8::Isolate) -> Rc<RefCell<EventLoop>> {
+        isolate
+            .get_slot::<Rc<RefCell<EventLoop>>>()
+            .expect("EventLoop not installed in isolate")
+            .clone()
+    }
+
+    /// Allocate a new operation ID
+    pub fn next_op_id(&mut self) -> OpId {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Schedule a future to run in the given context
+    ///
+    /// The completion returned by the future is run by the event loop once
+    /// the future resolves.
+    pub fn schedule(&mut self, id: OpId, context: v8::Global<v8::Context>, future: OpFuture) {
+        self.pending.push(PendingOp {
+            id,
+            context,
+            future,
+        });
+    }
+
+    /// Cancel a pending operation
+    ///
+    /// Returns `true` if the operation was still pending.
+    pub fn cancel(&mut self, id: OpId) -> bool {
+        self.active_timers.remove(&id);
+        let before = self.pending.len();
+        self.pending.retain(|op| op.id != id);
+        self.pending.len() != before
+    }
+
+    /// Cancel a timer
+    ///
+    /// Does nothing unless `id` is an active timer, so other pending
+    /// operations cannot be cancelled through `clearTimeout`.
+    ///
+    /// Returns `true` if the timer was active.
+    pub fn cancel_timer(&mut self, id: OpId) -> bool {
+        if !self.active_timers.remove(&id) {
+            return false;
+        }
+        self.pending.retain(|op| op.id != id);
+        true
+    }
+
+    /// Mark a timer as active
+    pub fn add_timer(&mut self, id: OpId) {
+        self.active_timers.insert(id);
+    }
+
+    /// Mark a timer as finished
+    pub fn remove_timer(&mut self, id: OpId) {
+        self.active_timers.remove(&id);
+    }
+
+    /// Check if a timer has not been cleared
+    pub fn is_timer_active(&self, id: OpId) -> bool {
+        self.active_timers.contains(&id)
+    }
+
+    /// Cancel every pending operation and timer
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.active_timers.clear();
+        self.unhandled_rejections.clear();
+    }
+
+    /// Record a promise that was rejected without a handler
+    pub fn add_unhandled_rejection(&mut self, promise: v8::Global<v8::Promise>, reason: v8::Global<v8::Value>) {
+        self.unhandled_rejections.push((promise, reason));
+    }
+
+    /// Forget a rejected promise once a handler was attached to it
+    pub fn remove_unhandled_rejection(&mut self, promise: &v8::Global<v8::Promise>) {
+        self.unhandled_rejections.retain(|(rejected, _)| rejected != promise);
+    }
+
+    /// Take the rejections recorded since the last call
+    pub fn take_unhandled_rejections(&mut self) -> Vec<(v8::Global<v8::Promise>, v8::Global<v8::Value>)> {
+        std::mem::take(&mut self.unhandled_rejections)
+    }
+
+    /// Check if any operation is still pending
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Get the number of pending operations
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Poll all pending operations
+    ///
+    /// Returns `Poll::Ready` with the completions of every operation that
+    /// finished during this poll (in scheduling order), or `Poll::Pending`
+    /// if none did.
+    pub(crate) fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Vec<(v8::Global<v8::Context>, OpCompletion)>> {
+        let mut ready = Vec::new();
+        let mut i = 0;
+
+        while i < self.pending.len() {
+            if let Poll::Ready(completion) = self.pending[i].future.as_mut().poll(cx) {
+                let op = self.pending.remove(i);
+                ready.push((op.context, completion));
+            } else {
+                i += 1;
+            }
+        }
+
+        if ready.is_empty() {
+            Poll::Pending
+        } else {
+            Poll::Ready(ready)
+        }
+    }
+}
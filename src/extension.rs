@@ -0,0 +1,309 @@
+//! Runtime Extensions
+//!
+//! An [`Extension`] packages a JavaScript API so it can be shipped as a
+//! separate crate and plugged into any runtime through
+//! `RuntimeConfig::extensions`.
+//!
+//! # Architecture
+//!
+//! An extension bundles:
+//! - an [`OpRegistry`] with the native ops backing the API,
+//! - JavaScript sources that build the public API on top of those ops,
+//!   as classic scripts or as ES modules loaded under `ext:` specifiers,
+//! - state initializers that put per-runtime values into the [`OpState`],
+//! - an init hook with direct access to the V8 context.
+//!
+//! State is initialized once per runtime. The ops, scripts, modules and
+//! init hook are installed into every context the runtime creates
+//! (including after `JsRuntime::reset_context`), in that order, after the
+//! built-in globals and the embedder's own ops, so before any user code
+//! runs. Workers inherit the extensions of the runtime that created them.
+//!
+//! Extension modules are resolved by the runtime's module map without a
+//! module loader: an `ext:` specifier is looked up in the modules of all
+//! installed extensions, so extensions can import each other's modules.
+
+use std::fmt;
+use std::sync::Arc;
+
+use v8;
+
+use crate::error::JsError;
+use crate::ops::bindings::install_registered_ops;
+use crate::ops::dispatch::OpRegistry;
+use crate::ops::state::OpState;
+use crate::runtime::JsRuntime;
+
+/// Initializer of per-runtime extension state
+type StateFn = dyn Fn(&mut OpState) + Send + Sync;
+
+/// Hook run with each new context entered
+type InitFn = dyn Fn(&mut v8::HandleScope) -> Result<(), String> + Send + Sync;
+
+/// Scheme of the specifiers of extension modules
+pub(crate) const EXTENSION_SCHEME: &str = "ext:";
+
+/// A JavaScript source of an extension
+#[derive(Debug, Clone)]
+struct ExtensionSource {
+    /// Name shown in stack traces (the specifier of modules), e.g.
+    /// `ext:kv/kv.js`
+    name: String,
+    /// JavaScript code (a classic script or an ES module)
+    code: String,
+}
+
+/// A bundle of ops, JavaScript glue and state extending the runtime
+///
+/// # Example
+///
+/// ```no_run
+/// # use ferrum::{Extension, JsRuntime, OpRegistry, OpState, Permissions, RuntimeConfig};
+/// # use std::collections::HashMap;
+/// # ferrum::init_v8();
+/// type Store = HashMap<String, String>;
+///
+/// let mut ops = OpRegistry::new();
+/// ops.set_namespace("__kv");
+/// ops.register_op_with_state("get", |state: &mut OpState, (key,): (String,)| {
+///     Ok::<_, String>(state.get::<Store>().get(&key).cloned())
+/// });
+/// ops.register_op_with_state("set", |state: &mut OpState, (key, value): (String, String)| {
+///     state.get_mut::<Store>().insert(key, value);
+///     Ok::<_, String>(())
+/// });
+///
+/// let kv = Extension::new("kv")
+///     .ops(ops)
+///     .state(Store::new)
+///     .js("ext:kv/kv.js", "globalThis.kv = { get: __kv.get, set: __kv.set };");
+///
+/// let config = RuntimeConfig {
+///     extensions: vec![kv],
+///     ..RuntimeConfig::default()
+/// };
+/// let mut runtime = JsRuntime::new(config, Permissions::default()).unwrap();
+/// assert_eq!(runtime.execute("kv.set('a', '1'); kv.get('a')", None).unwrap(), "1");
+/// ```
+#[derive(Clone)]
+pub struct Extension {
+    /// Extension name (used in error messages)
+    name: String,
+    /// Native ops installed on the registry's namespace
+    ops: OpRegistry,
+    /// Scripts evaluated after the ops are installed
+    js: Vec<ExtensionSource>,
+    /// ES modules evaluated after the scripts
+    esm: Vec<ExtensionSource>,
+    /// Initializers of the extension's per-runtime state
+    state: Vec<Arc<StateFn>>,
+    /// Hook run after the scripts
+    init: Option<Arc<InitFn>>,
+}
+
+impl Extension {
+    /// Create an empty extension
+    ///
+    /// # Arguments
+    /// * `name` - Extension name, e.g. the name of the crate providing it
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ops: OpRegistry::new(),
+            js: Vec::new(),
+            esm: Vec::new(),
+            state: Vec::new(),
+            init: None,
+        }
+    }
+
+    /// Set the ops of the extension
+    ///
+    /// The ops are installed on the registry's namespace object (see
+    /// [`OpRegistry::set_namespace`]); use a namespace of its own so the
+    /// ops of different extensions do not collide.
+    #[must_use]
+    pub fn ops(mut self, ops: OpRegistry) -> Self {
+        self.ops = ops;
+        self
+    }
+
+    /// Add a JavaScript source building the extension's API
+    ///
+    /// Sources are evaluated as classic scripts, in the order they were
+    /// added, once the extension's ops are installed. Use
+    /// [`Extension::esm`] for ES modules.
+    ///
+    /// # Arguments
+    /// * `name` - Name shown in stack traces, e.g. `ext:kv/kv.js`
+    /// * `code` - JavaScript code
+    #[must_use]
+    pub fn js(mut self, name: impl Into<String>, code: impl Into<String>) -> Self {
+        self.js.push(ExtensionSource {
+            name: name.into(),
+            code: code.into(),
+        });
+        self
+    }
+
+    /// Add an ES module building the extension's API
+    ///
+    /// Modules are evaluated in the order they were added, after the
+    /// scripts. They can import the modules of any installed extension by
+    /// their `ext:` specifier; a module that was already imported is not
+    /// evaluated again. Top-level await is not supported.
+    ///
+    /// # Arguments
+    /// * `specifier` - Module specifier starting with `ext:`, e.g.
+    ///   `ext:kv/mod.js`
+    /// * `code` - JavaScript module code
+    #[must_use]
+    pub fn esm(mut self, specifier: impl Into<String>, code: impl Into<String>) -> Self {
+        self.esm.push(ExtensionSource {
+            name: specifier.into(),
+            code: code.into(),
+        });
+        self
+    }
+
+    /// Add per-runtime state
+    ///
+    /// `init` is called once for every runtime the extension is installed
+    /// in and its result is put into the runtime's [`OpState`].
+    #[must_use]
+    pub fn state<T, F>(mut self, init: F) -> Self
+    where
+        T: 'static,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        self.state.push(Arc::new(move |state: &mut OpState| {
+            state.put(init());
+        }));
+        self
+    }
+
+    /// Set a hook run whenever the extension is installed in a context
+    ///
+    /// The hook runs after the extension's scripts, with the new context
+    /// entered. The runtime's state is available through
+    /// [`OpState::from_isolate`]. An error fails runtime creation (or the
+    /// context reset).
+    #[must_use]
+    pub fn init<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut v8::HandleScope) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.init = Some(Arc::new(hook));
+        self
+    }
+
+    /// Get the extension name
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the ops of the extension
+    #[must_use]
+    pub fn registry(&self) -> &OpRegistry {
+        &self.ops
+    }
+
+    /// Get the code of one of the extension's modules
+    ///
+    /// # Arguments
+    /// * `specifier` - Module specifier, e.g. `ext:kv/mod.js`
+    pub(crate) fn module_source(&self, specifier: &str) -> Option<&str> {
+        self.esm
+            .iter()
+            .find(|source| source.name == specifier)
+            .map(|source| source.code.as_str())
+    }
+
+    /// Initialize the extension's state in a runtime's op state
+    pub(crate) fn init_state(&self, state: &mut OpState) {
+        for init in &self.state {
+            init(state);
+        }
+    }
+
+    /// Install the extension into the entered context
+    ///
+    /// Installs the ops, evaluates the scripts and modules and runs the
+    /// init hook.
+    ///
+    /// # Returns
+    /// An error message if a step failed
+    pub(crate) fn install(&self, scope: &mut v8::HandleScope) -> Result<(), String> {
+        install_registered_ops(scope, &self.ops).map_err(|e| e.to_string())?;
+
+        for source in &self.js {
+            let scope = &mut v8::HandleScope::new(scope);
+            let tc_scope = &mut v8::TryCatch::new(scope);
+            let code = v8::String::new(tc_scope, &source.code).ok_or("Source is too large")?;
+            let origin = JsRuntime::script_origin(tc_scope, Some(&source.name), 0, 0, false);
+            let result = v8::Script::compile(tc_scope, code, Some(&origin)).and_then(|script| script.run(tc_scope));
+            if result.is_none() {
+                let error = match tc_scope.exception() {
+                    Some(exception) => JsError::from_v8_exception(tc_scope, exception),
+                    None => JsError::from("Execution terminated"),
+                };
+                return Err(format!("{}: {}", source.name, error));
+            }
+        }
+
+        for source in &self.esm {
+            if !source.name.starts_with(EXTENSION_SCHEME) {
+                return Err(format!(
+                    "Module specifier '{}' must start with '{}'",
+                    source.name, EXTENSION_SCHEME
+                ));
+            }
+            let scope = &mut v8::HandleScope::new(scope);
+            JsRuntime::evaluate_extension_module(scope, &source.name)
+                .map_err(|e| format!("{}: {}", source.name, e))?;
+        }
+
+        if let Some(init) = &self.init {
+            init(scope)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Extension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extension")
+            .field("name", &self.name)
+            .field("namespace", &self.ops.namespace())
+            .field("ops", &self.ops.names().collect::<Vec<_>>())
+            .field("js", &self.js.iter().map(|source| &source.name).collect::<Vec<_>>())
+            .field("esm", &self.esm.iter().map(|source| &source.name).collect::<Vec<_>>())
+            .field("state", &self.state.len())
+            .field("init", &self.init.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extension_state() {
+        let extension = Extension::new("counter").state(|| 41u32).state(|| "label");
+
+        let mut state = OpState::new();
+        extension.init_state(&mut state);
+        assert_eq!(state.get::<u32>(), &41);
+        assert_eq!(state.get::<&str>(), &"label");
+
+        // Every runtime gets fresh state
+        let mut other = OpState::new();
+        *state.get_mut::<u32>() += 1;
+        extension.init_state(&mut other);
+        assert_eq!(other.get::<u32>(), &41);
+    }
+}
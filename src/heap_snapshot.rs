@@ -0,0 +1,242 @@
+//! V8 Heap Snapshots
+//!
+//! This module writes snapshots of the JavaScript heap in the
+//! `.heapsnapshot` JSON format loaded by the Memory panel of Chrome
+//! DevTools.
+//!
+//! # Architecture
+//!
+//! V8 serializes the snapshot in chunks that are streamed straight to the
+//! output file. Snapshots can be written on demand (`JsRuntime` and
+//! `Deno.writeHeapSnapshot()`) or whenever the process receives a signal:
+//! [`HeapSnapshotSignal`] waits for the signal on a background thread and
+//! requests the snapshot on the isolate thread, from an interrupt while
+//! JavaScript runs or from the event loop while it is idle.
+
+use std::ffi::c_void;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Waker};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use v8;
+
+/// Sequence number distinguishing snapshots written in the same millisecond
+static SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+/// Write a snapshot of the isolate's heap to a file
+///
+/// # Arguments
+/// * `isolate` - Isolate whose heap is serialized
+/// * `path` - Output file (conventionally ending in `.heapsnapshot`)
+pub fn write_heap_snapshot(isolate: &mut v8::Isolate, path: &Path) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut result = Ok(());
+
+    isolate.take_heap_snapshot(|chunk| match writer.write_all(chunk) {
+        Ok(()) => true,
+        Err(e) => {
+            result = Err(e);
+            false
+        }
+    });
+
+    result?;
+    writer.flush()?;
+    tracing::debug!("Heap snapshot written to {}", path.display());
+    Ok(())
+}
+
+/// Generate a unique file name for a heap snapshot
+///
+/// Names look like `Heap.<unix millis>.<pid>.<sequence>.heapsnapshot`.
+pub fn default_file_name() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!(
+        "Heap.{}.{}.{}.heapsnapshot",
+        millis,
+        std::process::id(),
+        SEQUENCE.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Parse a signal name such as `SIGUSR2` (or `USR2`) into its number
+///
+/// Only signals that are safe to repurpose are accepted. Always returns
+/// `None` on platforms without Unix signals.
+pub fn parse_signal(name: &str) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::SignalKind;
+
+        let name = name.trim().to_ascii_uppercase();
+        let kind = match name.strip_prefix("SIG").unwrap_or(&name) {
+            "USR1" => SignalKind::user_defined1(),
+            "USR2" => SignalKind::user_defined2(),
+            "HUP" => SignalKind::hangup(),
+            "QUIT" => SignalKind::quit(),
+            "WINCH" => SignalKind::window_change(),
+            _ => return None,
+        };
+        Some(kind.as_raw_value())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = name;
+        None
+    }
+}
+
+/// State shared between the signal thread and the isolate thread
+struct SignalState {
+    /// Set when a signal arrived and no snapshot has been written for it yet
+    requested: AtomicBool,
+    /// Event loop task to wake when a signal arrives
+    waker: Mutex<Option<Waker>>,
+}
+
+/// Writes a heap snapshot to the current directory on a signal
+pub(crate) struct HeapSnapshotSignal {
+    /// State shared with the signal thread and the interrupt callback
+    state: Arc<SignalState>,
+    /// Stops the signal thread
+    shutdown: Option<tokio::sync::oneshot::Sender<()>>,
+    /// Signal thread
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HeapSnapshotSignal {
+    /// Start listening for a signal
+    ///
+    /// # Arguments
+    /// * `isolate` - Isolate to snapshot
+    /// * `signal` - Signal number (see [`parse_signal`])
+    #[cfg(unix)]
+    pub(crate) fn start(isolate: &mut v8::Isolate, signal: i32) -> io::Result<Self> {
+        use tokio::signal::unix::SignalKind;
+
+        let tokio_runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let mut signals = {
+            let _guard = tokio_runtime.enter();
+            tokio::signal::unix::signal(SignalKind::from_raw(signal))?
+        };
+
+        let state = Arc::new(SignalState {
+            requested: AtomicBool::new(false),
+            waker: Mutex::new(None),
+        });
+        let (shutdown, mut shutdown_rx) = tokio::sync::oneshot::channel();
+
+        let handle = isolate.thread_safe_handle();
+        let thread_state = state.clone();
+        let thread = std::thread::Builder::new()
+            .name("ferrum-heap-snapshot".to_string())
+            .spawn(move || {
+                tokio_runtime.block_on(async {
+                    loop {
+                        tokio::select! {
+                            _ = &mut shutdown_rx => break,
+                            received = signals.recv() => {
+                                if received.is_none() {
+                                    break;
+                                }
+                                thread_state.requested.store(true, Ordering::SeqCst);
+                                handle.request_interrupt(
+                                    Self::interrupt_callback,
+                                    Arc::as_ptr(&thread_state) as *mut c_void,
+                                );
+                                if let Some(waker) = thread_state.waker.lock().unwrap().take() {
+                                    waker.wake();
+                                }
+                            }
+                        }
+                    }
+                });
+            })?;
+
+        Ok(Self {
+            state,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        })
+    }
+
+    /// Start listening for a signal (unsupported on this platform)
+    #[cfg(not(unix))]
+    pub(crate) fn start(_isolate: &mut v8::Isolate, _signal: i32) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "Signals are not supported on this platform"))
+    }
+
+    /// Check for a requested snapshot, registering the task to be woken if none
+    pub(crate) fn poll_requested(&self, cx: &mut Context<'_>) -> bool {
+        *self.state.waker.lock().unwrap() = Some(cx.waker().clone());
+        self.state.requested.load(Ordering::SeqCst)
+    }
+
+    /// Write the requested snapshot, if any
+    pub(crate) fn write_requested(&self, isolate: &mut v8::Isolate) {
+        Self::write_if_requested(&self.state, isolate);
+    }
+
+    /// Write a snapshot to the current directory if one was requested
+    fn write_if_requested(state: &SignalState, isolate: &mut v8::Isolate) {
+        if !state.requested.swap(false, Ordering::SeqCst) {
+            return;
+        }
+        let file_name = default_file_name();
+        match write_heap_snapshot(isolate, Path::new(&file_name)) {
+            Ok(()) => tracing::info!("Heap snapshot written to {}", file_name),
+            Err(e) => tracing::error!("Failed to write heap snapshot {}: {}", file_name, e),
+        }
+    }
+
+    /// Interrupt callback writing the snapshot while JavaScript runs
+    extern "C" fn interrupt_callback(isolate: &mut v8::Isolate, data: *mut c_void) {
+        // SAFETY: `data` points to the signal state, which outlives every
+        // interrupt the runtime's isolate can still run
+        let state = unsafe { &*(data as *const SignalState) };
+        Self::write_if_requested(state, isolate);
+    }
+}
+
+impl Drop for HeapSnapshotSignal {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_file_name_is_unique() {
+        let first = default_file_name();
+        let second = default_file_name();
+        assert!(first.starts_with("Heap."));
+        assert!(first.ends_with(".heapsnapshot"));
+        assert_ne!(first, second);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("SIGUSR2"), parse_signal("usr2"));
+        assert!(parse_signal("SIGUSR1").is_some());
+        assert!(parse_signal("SIGKILL").is_none());
+        assert!(parse_signal("bogus").is_none());
+    }
+}
@@ -0,0 +1,468 @@
+//! Chrome DevTools Inspector
+//!
+//! This module connects the V8 inspector to Chrome DevTools (or any client
+//! speaking the Chrome DevTools Protocol) so JavaScript running in a
+//! `JsRuntime` can be debugged: breakpoints, stepping, console messages and
+//! evaluation in paused frames are all implemented by V8 itself.
+//!
+//! # Architecture
+//!
+//! - [`server`] accepts DevTools connections on a background thread and
+//!   queues protocol messages for the isolate thread.
+//! - [`JsRuntimeInspector`] lives on the isolate thread. It implements the
+//!   V8 inspector client, owns one V8 session per connected client and
+//!   dispatches queued messages: between executions, from V8 interrupts
+//!   while JavaScript runs, and in a blocking loop while execution is
+//!   paused at a breakpoint.
+//! - [`LocalSession`] lets the runtime itself call protocol methods (e.g.
+//!   the `Profiler` domain) without a DevTools client.
+//!
+//! All V8 calls happen on the isolate thread.
+
+pub(crate) mod server;
+mod websocket;
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::net::SocketAddr;
+use std::ptr::addr_of;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::Context;
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use v8::inspector::{
+    ChannelBase, ChannelImpl, StringBuffer, StringView, V8Inspector, V8InspectorClientBase,
+    V8InspectorClientImpl, V8InspectorClientTrustLevel, V8InspectorSession,
+};
+use v8::UniquePtr;
+
+use server::{InspectorMessage, InspectorServer, MessageQueue, Target};
+
+/// Context group of every context created by the runtime
+const CONTEXT_GROUP_ID: i32 = 1;
+
+/// V8 inspector client for one isolate
+///
+/// Boxed so its address, which V8 and the interrupt callback hold on to,
+/// stays stable.
+pub struct JsRuntimeInspector {
+    /// Base object V8 calls the client methods through
+    base: V8InspectorClientBase,
+    /// V8 inspector instance
+    v8_inspector: RefCell<Option<v8::UniqueRef<V8Inspector>>>,
+    /// Connected sessions by id
+    ///
+    /// A session may disconnect while one of its own messages is being
+    /// dispatched (e.g. from the pause loop); the dispatch holds a
+    /// reference, so it is only dropped once the dispatch returns.
+    sessions: RefCell<HashMap<u32, Rc<InspectorSession>>>,
+    /// Depth of nested message dispatches
+    dispatch_depth: Cell<u32>,
+    /// Messages from the server thread
+    queue: Arc<MessageQueue>,
+    /// Debuggable target served over HTTP
+    target: Arc<Target>,
+    /// WebSocket server (`None` until started)
+    server: Option<InspectorServer>,
+    /// Set while execution is paused in the message loop
+    paused: Cell<bool>,
+    /// Set while waiting for a client to start debugging (`--inspect-brk`)
+    waiting_for_session: Cell<bool>,
+}
+
+impl JsRuntimeInspector {
+    /// Create an inspector for an isolate
+    ///
+    /// # Arguments
+    /// * `isolate` - Isolate to inspect
+    /// * `title` - Target title shown by DevTools
+    pub fn new(isolate: &mut v8::Isolate, title: &str) -> Box<Self> {
+        let mut inspector = Box::new(Self {
+            base: V8InspectorClientBase::new::<Self>(),
+            v8_inspector: RefCell::new(None),
+            sessions: RefCell::new(HashMap::new()),
+            dispatch_depth: Cell::new(0),
+            queue: Arc::new(MessageQueue::new()),
+            target: Arc::new(Target {
+                id: uuid::Uuid::new_v4().to_string(),
+                title: title.to_string(),
+                url: Mutex::new(String::new()),
+            }),
+            server: None,
+            paused: Cell::new(false),
+            waiting_for_session: Cell::new(false),
+        });
+
+        let v8_inspector = V8Inspector::create(isolate, &mut *inspector);
+        *inspector.v8_inspector.borrow_mut() = Some(v8_inspector);
+
+        // Messages arriving while JavaScript runs are dispatched from an
+        // interrupt on the isolate thread
+        let handle = isolate.thread_safe_handle();
+        let data = &*inspector as *const Self as usize;
+        inspector.queue.set_notify(move || {
+            handle.request_interrupt(Self::interrupt_callback, data as *mut c_void);
+        });
+
+        inspector
+    }
+
+    /// Start accepting DevTools connections
+    ///
+    /// # Arguments
+    /// * `address` - Address to listen on (port 0 picks a free port)
+    ///
+    /// # Returns
+    /// The address the server listens on
+    pub fn start_server(&mut self, address: SocketAddr) -> std::io::Result<SocketAddr> {
+        let server = InspectorServer::start(address, self.target.clone(), self.queue.clone())?;
+        let address = server.address();
+        self.server = Some(server);
+        Ok(address)
+    }
+
+    /// Get the address of the DevTools server (if started)
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.server.as_ref().map(|server| server.address())
+    }
+
+    /// Get the WebSocket URL clients connect to (if the server is started)
+    pub fn websocket_url(&self) -> Option<String> {
+        self.address()
+            .map(|address| format!("ws://{}/ws/{}", address, self.target.id))
+    }
+
+    /// Set the URL of the main module or script shown by DevTools
+    pub fn set_target_url(&self, url: &str) {
+        *self.target.url.lock().unwrap() = url.to_string();
+    }
+
+    /// Report a new context to the inspector
+    pub fn context_created(&self, context: v8::Local<v8::Context>) {
+        let name = b"ferrum";
+        let aux_data = br#"{"isDefault":true}"#;
+        if let Some(v8_inspector) = self.v8_inspector.borrow_mut().as_mut() {
+            v8_inspector.context_created(
+                context,
+                CONTEXT_GROUP_ID,
+                StringView::from(&name[..]),
+                StringView::from(&aux_data[..]),
+            );
+        }
+    }
+
+    /// Report a context that is no longer used to the inspector
+    pub fn context_destroyed(&self, context: v8::Local<v8::Context>) {
+        if let Some(v8_inspector) = self.v8_inspector.borrow_mut().as_mut() {
+            v8_inspector.context_destroyed(context);
+        }
+    }
+
+    /// Connect an in-process session
+    ///
+    /// The session must be dropped before the inspector.
+    pub fn connect_local(&self) -> LocalSession {
+        let (outgoing, incoming) = unbounded_channel();
+        let mut v8_inspector = self.v8_inspector.borrow_mut();
+        let v8_inspector = v8_inspector.as_mut().expect("inspector is alive while the runtime exists");
+        LocalSession {
+            session: InspectorSession::new(v8_inspector, outgoing),
+            incoming,
+            next_id: 1,
+        }
+    }
+
+    /// Check if any DevTools client is connected
+    pub fn has_sessions(&self) -> bool {
+        !self.sessions.borrow().is_empty()
+    }
+
+    /// Dispatch all queued messages without blocking
+    pub fn poll_sessions(&self) {
+        while let Some(message) = self.queue.try_pop() {
+            self.handle_message(message);
+        }
+    }
+
+    /// Check for queued messages, registering the task to be woken if none
+    pub(crate) fn poll_pending(&self, cx: &mut Context<'_>) -> bool {
+        self.queue.poll_pending(cx)
+    }
+
+    /// Block until a client starts debugging, then pause on the next statement
+    ///
+    /// DevTools signals that it is ready with `Runtime.runIfWaitingForDebugger`.
+    pub fn wait_for_session_and_break_on_next_statement(&self) {
+        self.waiting_for_session.set(true);
+        while self.waiting_for_session.get() {
+            match self.queue.pop_blocking() {
+                Some(message) => self.handle_message(message),
+                None => return,
+            }
+        }
+
+        let session = self.sessions.borrow().values().next().cloned();
+        if let Some(session) = session {
+            session.schedule_pause_on_next_statement();
+        }
+    }
+
+    /// Handle a message from the server thread
+    fn handle_message(&self, message: InspectorMessage) {
+        match message {
+            InspectorMessage::Connect { session_id, outgoing } => {
+                let mut v8_inspector = self.v8_inspector.borrow_mut();
+                let Some(v8_inspector) = v8_inspector.as_mut() else {
+                    return;
+                };
+                let session = InspectorSession::new(v8_inspector, outgoing);
+                self.sessions.borrow_mut().insert(session_id, session);
+            }
+            InspectorMessage::Message { session_id, message } => {
+                self.dispatch(session_id, &message);
+            }
+            InspectorMessage::Disconnect { session_id } => {
+                self.sessions.borrow_mut().remove(&session_id);
+                if self.sessions.borrow().is_empty() {
+                    // Nobody is left to resume execution
+                    self.paused.set(false);
+                    self.waiting_for_session.set(false);
+                }
+            }
+        }
+    }
+
+    /// Dispatch a protocol message to a session
+    ///
+    /// May re-enter itself through the pause loop, which V8 runs from
+    /// within a dispatch when a protocol method hits a breakpoint.
+    fn dispatch(&self, session_id: u32, message: &str) {
+        let Some(session) = self.sessions.borrow().get(&session_id).cloned() else {
+            return;
+        };
+
+        self.dispatch_depth.set(self.dispatch_depth.get() + 1);
+        session.dispatch(message);
+        self.dispatch_depth.set(self.dispatch_depth.get() - 1);
+    }
+
+    /// Interrupt callback dispatching queued messages while JavaScript runs
+    ///
+    /// JavaScript run by a protocol method (e.g. `Runtime.evaluate`) is
+    /// interrupted in the middle of a dispatch; the messages then stay
+    /// queued until the loop running that dispatch picks them up.
+    extern "C" fn interrupt_callback(_isolate: &mut v8::Isolate, data: *mut c_void) {
+        // SAFETY: `data` points to the boxed inspector, which is dropped
+        // before its isolate and never while JavaScript runs
+        let inspector = unsafe { &*(data as *const Self) };
+        if inspector.dispatch_depth.get() == 0 {
+            inspector.poll_sessions();
+        }
+    }
+}
+
+impl V8InspectorClientImpl for JsRuntimeInspector {
+    fn base(&self) -> &V8InspectorClientBase {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut V8InspectorClientBase {
+        &mut self.base
+    }
+
+    unsafe fn base_ptr(this: *const Self) -> *const V8InspectorClientBase
+    where
+        Self: Sized,
+    {
+        addr_of!((*this).base)
+    }
+
+    fn run_message_loop_on_pause(&mut self, _context_group_id: i32) {
+        self.paused.set(true);
+        while self.paused.get() {
+            match self.queue.pop_blocking() {
+                Some(message) => self.handle_message(message),
+                None => break,
+            }
+        }
+        self.paused.set(false);
+    }
+
+    fn quit_message_loop_on_pause(&mut self) {
+        self.paused.set(false);
+    }
+
+    fn run_if_waiting_for_debugger(&mut self, _context_group_id: i32) {
+        self.waiting_for_session.set(false);
+    }
+}
+
+impl Drop for JsRuntimeInspector {
+    fn drop(&mut self) {
+        // Stop accepting messages before tearing down the sessions
+        self.server.take();
+        self.sessions.borrow_mut().clear();
+        self.v8_inspector.borrow_mut().take();
+    }
+}
+
+/// An in-process inspector session
+///
+/// Protocol methods are dispatched synchronously, so each call returns its
+/// result directly.
+pub struct LocalSession {
+    /// Underlying V8 session
+    session: Rc<InspectorSession>,
+    /// Responses and notifications sent by V8
+    incoming: UnboundedReceiver<String>,
+    /// Id of the next protocol call
+    next_id: u64,
+}
+
+impl LocalSession {
+    /// Call a protocol method
+    ///
+    /// Notifications received while the method runs are discarded.
+    ///
+    /// # Arguments
+    /// * `method` - Protocol method (e.g. `Profiler.start`)
+    /// * `params` - Method parameters
+    ///
+    /// # Returns
+    /// The `result` of the response, or the protocol error message
+    pub fn post(&mut self, method: &str, params: serde_json::Value) -> Result<serde_json::Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let message = serde_json::json!({ "id": id, "method": method, "params": params });
+        self.session.dispatch(&message.to_string());
+
+        while let Ok(message) = self.incoming.try_recv() {
+            let Ok(mut message) = serde_json::from_str::<serde_json::Value>(&message) else {
+                continue;
+            };
+            if message["id"] != id {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                return Err(error["message"].as_str().unwrap_or("Unknown protocol error").to_string());
+            }
+            return Ok(message["result"].take());
+        }
+
+        Err(format!("No response to {}", method))
+    }
+}
+
+/// A V8 inspector session connected to one client
+///
+/// Only ever accessed through shared references, since dispatching a
+/// message may re-enter [`InspectorSession::dispatch`] for the same
+/// session from the pause loop.
+struct InspectorSession {
+    /// V8 session (declared first so it is dropped before the channel base)
+    v8_session: Option<v8::UniqueRef<V8InspectorSession>>,
+    /// Pointer to the V8 session, through which it is called
+    v8_session_ptr: *mut V8InspectorSession,
+    /// Base object V8 sends protocol messages through
+    base: ChannelBase,
+    /// Protocol messages to the client
+    outgoing: UnboundedSender<String>,
+}
+
+impl InspectorSession {
+    /// Connect a new session
+    ///
+    /// Returned in an `Rc` right away, as V8 holds on to its address.
+    fn new(v8_inspector: &mut V8Inspector, outgoing: UnboundedSender<String>) -> Rc<Self> {
+        let mut session = Rc::new(Self {
+            v8_session: None,
+            v8_session_ptr: std::ptr::null_mut(),
+            base: ChannelBase::new::<Self>(),
+            outgoing,
+        });
+
+        let this = Rc::get_mut(&mut session).expect("new session is not shared");
+        let v8_session = v8_inspector.connect(
+            CONTEXT_GROUP_ID,
+            this,
+            StringView::empty(),
+            V8InspectorClientTrustLevel::FullyTrusted,
+        );
+        let v8_session = this.v8_session.insert(v8_session);
+        this.v8_session_ptr = &mut **v8_session;
+        session
+    }
+
+    /// Get the V8 session
+    ///
+    /// # Safety
+    /// The reference must not outlive the call made through it.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn v8_session(&self) -> &mut V8InspectorSession {
+        // SAFETY: the pointer is set on creation and valid until the session
+        // is dropped. `V8InspectorSession` is an opaque (zero-sized) handle
+        // to a C++ object that supports re-entrant dispatch, so nested
+        // calls do not alias any Rust-visible memory.
+        unsafe { &mut *self.v8_session_ptr }
+    }
+
+    /// Dispatch a protocol message
+    fn dispatch(&self, message: &str) {
+        // SAFETY: the session is only used for the duration of each call
+        let v8_session = unsafe { self.v8_session() };
+
+        // StringView treats 8-bit strings as Latin-1, so anything else is
+        // passed as UTF-16
+        if message.is_ascii() {
+            v8_session.dispatch_protocol_message(StringView::from(message.as_bytes()));
+        } else {
+            let utf16: Vec<u16> = message.encode_utf16().collect();
+            v8_session.dispatch_protocol_message(StringView::from(&utf16[..]));
+        }
+    }
+
+    /// Pause on the next JavaScript statement
+    fn schedule_pause_on_next_statement(&self) {
+        let reason = b"debugCommand";
+        // SAFETY: the session is only used for the duration of the call
+        let v8_session = unsafe { self.v8_session() };
+        v8_session.schedule_pause_on_next_statement(StringView::from(&reason[..]), StringView::empty());
+    }
+
+    /// Forward a message from V8 to the client
+    fn send(&self, message: UniquePtr<StringBuffer>) {
+        if let Some(message) = message.as_ref() {
+            let _ = self.outgoing.send(message.string().to_string());
+        }
+    }
+}
+
+impl ChannelImpl for InspectorSession {
+    fn base(&self) -> &ChannelBase {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut ChannelBase {
+        &mut self.base
+    }
+
+    unsafe fn base_ptr(this: *const Self) -> *const ChannelBase
+    where
+        Self: Sized,
+    {
+        addr_of!((*this).base)
+    }
+
+    fn send_response(&mut self, _call_id: i32, message: UniquePtr<StringBuffer>) {
+        self.send(message);
+    }
+
+    fn send_notification(&mut self, message: UniquePtr<StringBuffer>) {
+        self.send(message);
+    }
+
+    fn flush_protocol_notifications(&mut self) {}
+}
@@ -0,0 +1,571 @@
+//! Inspector Server
+//!
+//! Serves the Chrome DevTools discovery endpoints (`/json`, `/json/list`,
+//! `/json/version`) and the WebSocket endpoint of the inspector target.
+//!
+//! # Architecture
+//!
+//! The server runs on its own thread with a single-threaded tokio runtime,
+//! so it keeps accepting connections while the isolate thread is busy or
+//! paused. Protocol messages received over WebSocket are pushed onto the
+//! [`MessageQueue`] shared with the isolate thread; responses from V8 are
+//! written back through a per-session channel.
+
+use std::collections::VecDeque;
+use std::io;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Waker};
+use std::thread::JoinHandle;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::sync::Notify;
+
+use super::websocket::{self, Message};
+
+/// Longest request or header line accepted, in bytes
+const MAX_LINE_LENGTH: usize = 8 * 1024;
+
+/// Most headers accepted in a request
+const MAX_HEADERS: usize = 100;
+
+/// Message from the server thread to the isolate thread
+pub(crate) enum InspectorMessage {
+    /// A DevTools client connected
+    Connect {
+        /// Session identifier
+        session_id: u32,
+        /// Channel for protocol messages to the client
+        outgoing: UnboundedSender<String>,
+    },
+    /// A protocol message from a client
+    Message {
+        /// Session identifier
+        session_id: u32,
+        /// JSON-encoded protocol message
+        message: String,
+    },
+    /// A DevTools client disconnected
+    Disconnect {
+        /// Session identifier
+        session_id: u32,
+    },
+}
+
+/// Queue of messages for the isolate thread
+///
+/// The isolate thread drains it between JavaScript executions, from V8
+/// interrupts while JavaScript runs, and blocks on it while paused.
+pub(crate) struct MessageQueue {
+    /// Pending messages and whether the server is still running
+    state: Mutex<QueueState>,
+    /// Wakes the isolate thread blocked in [`MessageQueue::pop_blocking`]
+    condvar: Condvar,
+    /// Called after each push to wake the isolate thread
+    notify: Mutex<Option<Box<dyn Fn() + Send>>>,
+}
+
+/// Mutable message queue state
+#[derive(Default)]
+struct QueueState {
+    /// Pending messages
+    messages: VecDeque<InspectorMessage>,
+    /// Waker of the event loop waiting for ops
+    waker: Option<Waker>,
+    /// Set once the server stopped
+    closed: bool,
+}
+
+impl MessageQueue {
+    /// Create an empty queue
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Mutex::new(QueueState::default()),
+            condvar: Condvar::new(),
+            notify: Mutex::new(None),
+        }
+    }
+
+    /// Set the callback run after each push (e.g. to interrupt the isolate)
+    pub(crate) fn set_notify(&self, notify: impl Fn() + Send + 'static) {
+        *self.notify.lock().unwrap() = Some(Box::new(notify));
+    }
+
+    /// Push a message and wake the isolate thread
+    pub(crate) fn push(&self, message: InspectorMessage) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            state.messages.push_back(message);
+            self.condvar.notify_one();
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        if let Some(notify) = &*self.notify.lock().unwrap() {
+            notify();
+        }
+    }
+
+    /// Take the next message without blocking
+    pub(crate) fn try_pop(&self) -> Option<InspectorMessage> {
+        self.state.lock().unwrap().messages.pop_front()
+    }
+
+    /// Take the next message, blocking until one arrives
+    ///
+    /// Returns `None` once the server stopped and the queue is empty.
+    pub(crate) fn pop_blocking(&self) -> Option<InspectorMessage> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(message) = state.messages.pop_front() {
+                return Some(message);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.condvar.wait(state).unwrap();
+        }
+    }
+
+    /// Check for pending messages, registering the task to be woken if none
+    pub(crate) fn poll_pending(&self, cx: &mut Context<'_>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.messages.is_empty() {
+            state.waker = Some(cx.waker().clone());
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Mark the queue as closed and wake blocked readers
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.condvar.notify_all();
+    }
+}
+
+/// Description of the debuggable target
+pub(crate) struct Target {
+    /// Unique target identifier (part of the WebSocket path)
+    pub(crate) id: String,
+    /// Title shown in `chrome://inspect`
+    pub(crate) title: String,
+    /// URL of the main module or script
+    pub(crate) url: Mutex<String>,
+}
+
+impl Target {
+    /// Get the WebSocket path of the target
+    fn ws_path(&self) -> String {
+        format!("/ws/{}", self.id)
+    }
+
+    /// Build the `/json/list` entry of the target
+    fn json(&self, host: &str) -> serde_json::Value {
+        let ws = format!("{}{}", host, self.ws_path());
+        serde_json::json!({
+            "description": "ferrum",
+            "devtoolsFrontendUrl": format!(
+                "devtools://devtools/bundled/js_app.html?experiments=true&v8only=true&ws={}",
+                ws
+            ),
+            "faviconUrl": "",
+            "id": self.id,
+            "title": self.title,
+            "type": "node",
+            "url": *self.url.lock().unwrap(),
+            "webSocketDebuggerUrl": format!("ws://{}", ws),
+        })
+    }
+}
+
+/// HTTP and WebSocket server for one inspector target
+pub(crate) struct InspectorServer {
+    /// Address the server listens on
+    address: SocketAddr,
+    /// Signals the server thread to stop
+    shutdown: Arc<Notify>,
+    /// Queue shared with the isolate thread
+    queue: Arc<MessageQueue>,
+    /// Server thread
+    thread: Option<JoinHandle<()>>,
+}
+
+impl InspectorServer {
+    /// Start serving a target
+    ///
+    /// # Arguments
+    /// * `address` - Address to listen on (port 0 picks a free port)
+    /// * `target` - Target served by this server
+    /// * `queue` - Queue receiving messages for the isolate thread
+    pub(crate) fn start(
+        address: SocketAddr,
+        target: Arc<Target>,
+        queue: Arc<MessageQueue>,
+    ) -> io::Result<Self> {
+        let listener = std::net::TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+
+        let shutdown = Arc::new(Notify::new());
+        let thread_shutdown = shutdown.clone();
+        let thread_queue = queue.clone();
+
+        let thread = std::thread::Builder::new()
+            .name("ferrum-inspector".to_string())
+            .spawn(move || {
+                let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        tracing::error!("Failed to start inspector runtime: {}", e);
+                        return;
+                    }
+                };
+                runtime.block_on(Self::serve(listener, target, thread_queue.clone(), thread_shutdown));
+                thread_queue.close();
+            })?;
+
+        Ok(Self {
+            address,
+            shutdown,
+            queue,
+            thread: Some(thread),
+        })
+    }
+
+    /// Get the address the server listens on
+    pub(crate) fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Accept connections until shutdown
+    async fn serve(
+        listener: std::net::TcpListener,
+        target: Arc<Target>,
+        queue: Arc<MessageQueue>,
+        shutdown: Arc<Notify>,
+    ) {
+        let listener = match TcpListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::error!("Failed to listen for inspector connections: {}", e);
+                return;
+            }
+        };
+
+        loop {
+            tokio::select! {
+                _ = shutdown.notified() => return,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        tokio::spawn(Self::handle_connection(stream, target.clone(), queue.clone()));
+                    }
+                    Err(e) => tracing::warn!("Failed to accept inspector connection: {}", e),
+                },
+            }
+        }
+    }
+
+    /// Serve one HTTP request, upgrading it to a WebSocket session if asked
+    async fn handle_connection(stream: TcpStream, target: Arc<Target>, queue: Arc<MessageQueue>) {
+        let local_addr = stream.local_addr().ok();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+
+        let Ok(request) = read_request(&mut reader).await else {
+            return;
+        };
+
+        // Browsers send the page's own host name, so this rejects web pages
+        // that rebind their domain to this address (DNS rebinding)
+        if let Some(host) = request.header("host").filter(|host| !is_allowed_host(host)) {
+            tracing::warn!("Rejected inspector request with Host {:?}", host);
+            let body = "Host header is not an IP address or localhost";
+            if let Err(e) = write_response(&mut writer, "403 Forbidden", body).await {
+                tracing::debug!("Inspector connection closed: {}", e);
+            }
+            return;
+        }
+
+        let host = request
+            .header("host")
+            .map(str::to_string)
+            .or_else(|| local_addr.map(|addr| addr.to_string()))
+            .unwrap_or_default();
+
+        let result = match request.path.as_str() {
+            "/json" | "/json/list" => {
+                let body = serde_json::json!([target.json(&host)]);
+                write_response(&mut writer, "200 OK", &body.to_string()).await
+            }
+            "/json/version" => {
+                let body = serde_json::json!({
+                    "Browser": format!("Ferrum/v{}", crate::VERSION),
+                    "Protocol-Version": "1.3",
+                    "V8-Version": v8::V8::get_version(),
+                });
+                write_response(&mut writer, "200 OK", &body.to_string()).await
+            }
+            path if path == target.ws_path() => match request.header("sec-websocket-key") {
+                Some(key) => Self::run_session(reader, writer, key, queue).await,
+                None => write_response(&mut writer, "400 Bad Request", "Expected a WebSocket upgrade").await,
+            },
+            _ => write_response(&mut writer, "404 Not Found", "Not Found").await,
+        };
+
+        if let Err(e) = result {
+            tracing::debug!("Inspector connection closed: {}", e);
+        }
+    }
+
+    /// Relay protocol messages between a WebSocket client and the isolate
+    async fn run_session(
+        mut reader: BufReader<tokio::net::tcp::OwnedReadHalf>,
+        mut writer: OwnedWriteHalf,
+        key: &str,
+        queue: Arc<MessageQueue>,
+    ) -> io::Result<()> {
+        static NEXT_SESSION_ID: AtomicU32 = AtomicU32::new(1);
+
+        let handshake = format!(
+            "HTTP/1.1 101 Switching Protocols\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\n\r\n",
+            websocket::accept_key(key)
+        );
+        writer.write_all(handshake.as_bytes()).await?;
+
+        let session_id = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
+        let (outgoing, mut outgoing_rx) = unbounded_channel::<String>();
+        let (control, mut control_rx) = unbounded_channel::<(u8, Vec<u8>)>();
+        queue.push(InspectorMessage::Connect { session_id, outgoing });
+        tracing::info!("Inspector session {} connected", session_id);
+
+        // Writer: protocol messages from V8 and control frames
+        let writer_task = tokio::spawn(async move {
+            loop {
+                let (opcode, payload) = tokio::select! {
+                    message = outgoing_rx.recv() => match message {
+                        Some(message) => (websocket::OPCODE_TEXT, message.into_bytes()),
+                        None => (websocket::OPCODE_CLOSE, Vec::new()),
+                    },
+                    frame = control_rx.recv() => match frame {
+                        Some(frame) => frame,
+                        None => return,
+                    },
+                };
+                let is_close = opcode == websocket::OPCODE_CLOSE;
+                if websocket::write_frame(&mut writer, opcode, &payload).await.is_err() || is_close {
+                    return;
+                }
+            }
+        });
+
+        // Reader: protocol messages from the client
+        let result = loop {
+            match websocket::read_message(&mut reader).await {
+                Ok(Message::Text(message)) => queue.push(InspectorMessage::Message { session_id, message }),
+                Ok(Message::Ping(payload)) => {
+                    let _ = control.send((websocket::OPCODE_PONG, payload));
+                }
+                Ok(Message::Close) => {
+                    let _ = control.send((websocket::OPCODE_CLOSE, Vec::new()));
+                    break Ok(());
+                }
+                Ok(Message::Binary(_) | Message::Pong(_)) => {}
+                Err(e) => break Err(e),
+            }
+        };
+
+        queue.push(InspectorMessage::Disconnect { session_id });
+        tracing::info!("Inspector session {} disconnected", session_id);
+        drop(control);
+        let _ = writer_task.await;
+        result
+    }
+}
+
+impl Drop for InspectorServer {
+    fn drop(&mut self) {
+        self.shutdown.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        self.queue.close();
+    }
+}
+
+/// A parsed HTTP request head
+struct Request {
+    /// Request path
+    path: String,
+    /// Headers with lowercase names
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Get a header value by lowercase name
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Check if a `Host` header names this machine by IP address or as `localhost`
+fn is_allowed_host(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(rest) => match rest.split_once(']') {
+            Some((address, port)) if port.is_empty() || is_port(port) => {
+                return address.parse::<Ipv6Addr>().is_ok();
+            }
+            _ => return false,
+        },
+        None => match host.split_once(':') {
+            Some((name, port)) if is_port(port) => name,
+            Some(_) => return false,
+            None => host,
+        },
+    };
+    name.eq_ignore_ascii_case("localhost") || name.parse::<IpAddr>().is_ok()
+}
+
+/// Check if a string is a `:port` suffix
+fn is_port(suffix: &str) -> bool {
+    suffix
+        .strip_prefix(':')
+        .is_some_and(|port| !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Read a line of at most [`MAX_LINE_LENGTH`] bytes
+///
+/// # Returns
+/// The number of bytes read (0 at the end of the stream)
+async fn read_line<R: tokio::io::AsyncBufRead + Unpin>(reader: &mut R, line: &mut String) -> io::Result<usize> {
+    line.clear();
+    let read = reader.take(MAX_LINE_LENGTH as u64 + 1).read_line(line).await?;
+    if read > MAX_LINE_LENGTH {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "HTTP request line too long"));
+    }
+    Ok(read)
+}
+
+/// Read an HTTP request head
+async fn read_request<R: tokio::io::AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Request> {
+    let mut line = String::new();
+    read_line(reader, &mut line).await?;
+    let path = line
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Malformed HTTP request"))?
+        .to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        if read_line(reader, &mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if headers.len() >= MAX_HEADERS {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Too many HTTP headers"));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    Ok(Request { path, headers })
+}
+
+/// Write an HTTP response and close the connection
+async fn write_response(writer: &mut OwnedWriteHalf, status: &str, body: &str) -> io::Result<()> {
+    let content_type = if body.starts_with(['[', '{']) {
+        "application/json; charset=UTF-8"
+    } else {
+        "text/plain; charset=UTF-8"
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    writer.write_all(response.as_bytes()).await?;
+    writer.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let input = b"GET /json/list HTTP/1.1\r\nHost: 127.0.0.1:9229\r\nUpgrade: websocket\r\n\r\n";
+        let mut reader = &input[..];
+        let request = read_request(&mut reader).await.unwrap();
+
+        assert_eq!(request.path, "/json/list");
+        assert_eq!(request.header("host"), Some("127.0.0.1:9229"));
+        assert_eq!(request.header("upgrade"), Some("websocket"));
+        assert_eq!(request.header("sec-websocket-key"), None);
+    }
+
+    #[tokio::test]
+    async fn test_read_request_line_limit() {
+        let mut input = b"GET /json HTTP/1.1\r\nX-Long: ".to_vec();
+        input.extend(std::iter::repeat_n(b'a', MAX_LINE_LENGTH * 4));
+        let mut reader = &input[..];
+        let error = read_request(&mut reader).await.err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_read_request_header_limit() {
+        let request = |count: usize| {
+            let mut input = b"GET /json HTTP/1.1\r\n".to_vec();
+            for i in 0..count {
+                input.extend(format!("X-Header-{}: {}\r\n", i, i).as_bytes());
+            }
+            input.extend(b"\r\n");
+            input
+        };
+
+        let input = request(MAX_HEADERS);
+        assert_eq!(read_request(&mut &input[..]).await.unwrap().headers.len(), MAX_HEADERS);
+
+        let input = request(MAX_HEADERS + 1);
+        let error = read_request(&mut &input[..]).await.err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_allowed_hosts() {
+        for host in ["localhost", "LOCALHOST:9229", "127.0.0.1", "127.0.0.1:9229", "[::1]", "[::1]:9229", "10.0.0.2:80"] {
+            assert!(is_allowed_host(host), "{}", host);
+        }
+        for host in ["evil.example", "evil.example:9229", "localhost.evil.example", "127.0.0.1:", "[::1", "::1", ""] {
+            assert!(!is_allowed_host(host), "{}", host);
+        }
+    }
+
+    #[test]
+    fn test_target_json() {
+        let target = Target {
+            id: "abc".to_string(),
+            title: "ferrum".to_string(),
+            url: Mutex::new("file:///main.js".to_string()),
+        };
+        let json = target.json("127.0.0.1:9229");
+
+        assert_eq!(json["webSocketDebuggerUrl"], "ws://127.0.0.1:9229/ws/abc");
+        assert_eq!(json["url"], "file:///main.js");
+    }
+}
@@ -0,0 +1,286 @@
+//! Minimal WebSocket Protocol (RFC 6455)
+//!
+//! Just enough of the server side of the WebSocket protocol to talk to
+//! Chrome DevTools: the opening handshake, reading (possibly fragmented)
+//! masked client frames and writing unmasked server frames.
+
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// GUID appended to the client key in the opening handshake
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest message accepted from a client
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Continuation frame opcode
+const OPCODE_CONTINUATION: u8 = 0x0;
+/// Text frame opcode
+pub(crate) const OPCODE_TEXT: u8 = 0x1;
+/// Binary frame opcode
+const OPCODE_BINARY: u8 = 0x2;
+/// Close frame opcode
+pub(crate) const OPCODE_CLOSE: u8 = 0x8;
+/// Ping frame opcode
+const OPCODE_PING: u8 = 0x9;
+/// Pong frame opcode
+pub(crate) const OPCODE_PONG: u8 = 0xA;
+
+/// A message received from a WebSocket client
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Message {
+    /// UTF-8 text message
+    Text(String),
+    /// Binary message
+    Binary(Vec<u8>),
+    /// Ping with its payload (must be answered with a pong)
+    Ping(Vec<u8>),
+    /// Pong with its payload
+    Pong(Vec<u8>),
+    /// Close request
+    Close,
+}
+
+/// Compute the `Sec-WebSocket-Accept` header for a client key
+pub(crate) fn accept_key(client_key: &str) -> String {
+    let mut input = client_key.trim().as_bytes().to_vec();
+    input.extend_from_slice(HANDSHAKE_GUID.as_bytes());
+    base64_encode(&sha1(&input))
+}
+
+/// Read the next complete message from a client
+///
+/// Fragmented messages are reassembled; control frames received between
+/// fragments are returned first.
+pub(crate) async fn read_message<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Message> {
+    let mut fragments: Option<(u8, Vec<u8>)> = None;
+
+    loop {
+        let mut head = [0u8; 2];
+        reader.read_exact(&mut head).await?;
+
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0F;
+        let masked = head[1] & 0x80 != 0;
+        let len = match head[1] & 0x7F {
+            126 => u64::from(reader.read_u16().await?),
+            127 => reader.read_u64().await?,
+            len => u64::from(len),
+        };
+
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|len| *len <= MAX_MESSAGE_SIZE)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "WebSocket frame too large"))?;
+
+        let mut mask = [0u8; 4];
+        if masked {
+            reader.read_exact(&mut mask).await?;
+        }
+
+        let mut payload = vec![0u8; len];
+        reader.read_exact(&mut payload).await?;
+        if masked {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+
+        match opcode {
+            OPCODE_CLOSE => return Ok(Message::Close),
+            OPCODE_PING => return Ok(Message::Ping(payload)),
+            OPCODE_PONG => return Ok(Message::Pong(payload)),
+            OPCODE_TEXT | OPCODE_BINARY if fragments.is_none() => {
+                if fin {
+                    return to_message(opcode, payload);
+                }
+                fragments = Some((opcode, payload));
+            }
+            OPCODE_CONTINUATION if fragments.is_some() => {
+                let (first_opcode, mut data) = fragments.take().unwrap();
+                if data.len() + payload.len() > MAX_MESSAGE_SIZE {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "WebSocket message too large"));
+                }
+                data.extend_from_slice(&payload);
+                if fin {
+                    return to_message(first_opcode, data);
+                }
+                fragments = Some((first_opcode, data));
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unexpected WebSocket opcode {:#x}", opcode),
+                ));
+            }
+        }
+    }
+}
+
+/// Convert a complete data frame into a message
+fn to_message(opcode: u8, payload: Vec<u8>) -> io::Result<Message> {
+    if opcode == OPCODE_BINARY {
+        return Ok(Message::Binary(payload));
+    }
+    String::from_utf8(payload)
+        .map(Message::Text)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "WebSocket text is not UTF-8"))
+}
+
+/// Write a single unmasked frame (servers never mask)
+pub(crate) async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    opcode: u8,
+    payload: &[u8],
+) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= usize::from(u16::MAX) => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+
+/// SHA-1 digest (only used for the handshake)
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476, 0xC3D2_E1F0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (i, word) in h.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Standard base64 encoding with padding
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha1() {
+        let hex = |digest: [u8; 20]| digest.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(hex(sha1(b"abc")), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    }
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_accept_key() {
+        // Example from RFC 6455, section 1.3
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[tokio::test]
+    async fn test_read_masked_fragmented_message() {
+        let mask = [1u8, 2, 3, 4];
+        let masked = |data: &[u8]| -> Vec<u8> {
+            data.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]).collect()
+        };
+
+        let mut input = vec![OPCODE_TEXT, 0x80 | 3];
+        input.extend_from_slice(&mask);
+        input.extend(masked(b"Hel"));
+        input.extend([0x80 | OPCODE_CONTINUATION, 0x80 | 2]);
+        input.extend_from_slice(&mask);
+        input.extend(masked(b"lo"));
+        input.extend([0x80 | OPCODE_CLOSE, 0]);
+
+        let mut reader = &input[..];
+        assert_eq!(read_message(&mut reader).await.unwrap(), Message::Text("Hello".to_string()));
+        assert_eq!(read_message(&mut reader).await.unwrap(), Message::Close);
+    }
+
+    #[tokio::test]
+    async fn test_write_frame_lengths() {
+        let mut output = Vec::new();
+        write_frame(&mut output, OPCODE_TEXT, b"hi").await.unwrap();
+        assert_eq!(output, [0x81, 2, b'h', b'i']);
+
+        let mut output = Vec::new();
+        write_frame(&mut output, OPCODE_TEXT, &[0; 300]).await.unwrap();
+        assert_eq!(&output[..4], &[0x81, 126, 1, 44]);
+        assert_eq!(output.len(), 304);
+    }
+}
@@ -0,0 +1,171 @@
+/**
+ * Ferrum Events
+ *
+ * Minimal `Event`/`EventTarget` support, used for runtime events such as
+ * `unhandledrejection` on `globalThis` and `message` on workers. Evaluated
+ * by `bootstrap_globals`; the script's completion value is the internal
+ * function the runtime calls to dispatch an unhandled promise rejection.
+ */
+(() => {
+  class Event {
+    #type;
+    #cancelable;
+    #defaultPrevented = false;
+
+    constructor(type, init = {}) {
+      if (arguments.length === 0) {
+        throw new TypeError("Event constructor requires a type");
+      }
+      this.#type = String(type);
+      this.#cancelable = Boolean(init.cancelable);
+    }
+
+    get type() {
+      return this.#type;
+    }
+
+    get cancelable() {
+      return this.#cancelable;
+    }
+
+    get defaultPrevented() {
+      return this.#defaultPrevented;
+    }
+
+    preventDefault() {
+      if (this.#cancelable) {
+        this.#defaultPrevented = true;
+      }
+    }
+  }
+
+  class PromiseRejectionEvent extends Event {
+    #promise;
+    #reason;
+
+    constructor(type, init = {}) {
+      super(type, init);
+      this.#promise = init.promise;
+      this.#reason = init.reason;
+    }
+
+    get promise() {
+      return this.#promise;
+    }
+
+    get reason() {
+      return this.#reason;
+    }
+  }
+
+  class MessageEvent extends Event {
+    #data;
+
+    constructor(type, init = {}) {
+      super(type, init);
+      this.#data = init.data;
+    }
+
+    get data() {
+      return this.#data;
+    }
+  }
+
+  class ErrorEvent extends Event {
+    #message;
+    #error;
+
+    constructor(type, init = {}) {
+      super(type, init);
+      this.#message = init.message === undefined ? "" : String(init.message);
+      this.#error = init.error;
+    }
+
+    get message() {
+      return this.#message;
+    }
+
+    get error() {
+      return this.#error;
+    }
+  }
+
+  // target -> type -> [{ listener, once }]
+  const targets = new WeakMap();
+
+  class EventTarget {
+    addEventListener(type, listener, options) {
+      if (typeof listener !== "function" && typeof listener?.handleEvent !== "function") {
+        return;
+      }
+      const target = this ?? globalThis;
+      const listeners = targets.get(target) ?? new Map();
+      targets.set(target, listeners);
+
+      type = String(type);
+      const entries = listeners.get(type) ?? [];
+      if (entries.some((entry) => entry.listener === listener)) {
+        return;
+      }
+      const once = typeof options === "object" && options !== null && Boolean(options.once);
+      entries.push({ listener, once });
+      listeners.set(type, entries);
+    }
+
+    removeEventListener(type, listener) {
+      const listeners = targets.get(this ?? globalThis);
+      const entries = listeners?.get(String(type));
+      if (entries) {
+        listeners.set(String(type), entries.filter((entry) => entry.listener !== listener));
+      }
+    }
+
+    // Exceptions thrown by listeners propagate to the caller
+    dispatchEvent(event) {
+      if (!(event instanceof Event)) {
+        throw new TypeError("dispatchEvent requires an Event");
+      }
+      const target = this ?? globalThis;
+
+      const handler = target[`on${event.type}`];
+      if (typeof handler === "function") {
+        handler.call(target, event);
+      }
+
+      const entries = targets.get(target)?.get(event.type) ?? [];
+      for (const entry of [...entries]) {
+        if (entry.once) {
+          target.removeEventListener.call(target, event.type, entry.listener);
+        }
+        if (typeof entry.listener === "function") {
+          entry.listener.call(target, event);
+        } else {
+          entry.listener.handleEvent(event);
+        }
+      }
+
+      return !event.defaultPrevented;
+    }
+  }
+
+  const globals = { Event, PromiseRejectionEvent, MessageEvent, ErrorEvent, EventTarget };
+  for (const [name, value] of Object.entries(globals)) {
+    Object.defineProperty(globalThis, name, { value, writable: true, configurable: true });
+  }
+
+  // The global object is an event target as well
+  for (const name of ["addEventListener", "removeEventListener", "dispatchEvent"]) {
+    Object.defineProperty(globalThis, name, {
+      value: EventTarget.prototype[name],
+      writable: true,
+      configurable: true,
+    });
+  }
+  globalThis.onunhandledrejection = null;
+
+  // Returns true when a listener called preventDefault()
+  return (promise, reason) =>
+    !globalThis.dispatchEvent(
+      new PromiseRejectionEvent("unhandledrejection", { cancelable: true, promise, reason }),
+    );
+})();
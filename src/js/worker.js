@@ -0,0 +1,62 @@
+/**
+ * Ferrum Workers
+ *
+ * The `Worker` class. Evaluates to a function called by
+ * `bootstrap_globals` with the native worker ops (`create`, `postMessage`
+ * and `terminate`); it defines `Worker` on `globalThis`.
+ */
+((ops) => {
+  const transferList = (options) =>
+    Array.isArray(options) ? options : Array.from(options?.transfer ?? []);
+
+  class Worker extends EventTarget {
+    #id;
+    #name;
+
+    constructor(specifier, options = {}) {
+      super();
+      if (arguments.length === 0) {
+        throw new TypeError("Worker constructor requires a module specifier");
+      }
+      if (options?.type !== "module") {
+        throw new TypeError('Only module workers are supported, use { type: "module" }');
+      }
+
+      this.#name = options.name === undefined ? "" : String(options.name);
+      const permissions = JSON.stringify(options.deno?.permissions ?? "inherit");
+      this.onmessage = null;
+      this.onmessageerror = null;
+      this.onerror = null;
+      this.#id = ops.create(String(specifier), this.#name, permissions, (type, data) =>
+        this.#onEvent(type, data),
+      );
+    }
+
+    // The second argument is a transfer list or `{ transfer }`
+    postMessage(message, options) {
+      if (arguments.length === 0) {
+        throw new TypeError("postMessage requires a message");
+      }
+      ops.postMessage(this.#id, message, transferList(options));
+    }
+
+    terminate() {
+      ops.terminate(this.#id);
+    }
+
+    // Uncaught worker errors nobody prevented are rethrown in the parent
+    #onEvent(type, data) {
+      if (type === "error") {
+        const event = new ErrorEvent("error", { cancelable: true, message: data });
+        if (this.dispatchEvent(event)) {
+          const name = this.#name ? ` "${this.#name}"` : "";
+          throw new Error(`Uncaught (in worker${name}) ${data}`);
+        }
+        return;
+      }
+      this.dispatchEvent(new MessageEvent(type, { data }));
+    }
+  }
+
+  Object.defineProperty(globalThis, "Worker", { value: Worker, writable: true, configurable: true });
+});
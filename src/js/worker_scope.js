@@ -0,0 +1,33 @@
+/**
+ * Ferrum Worker Scope
+ *
+ * Turns the global object of a worker into its global scope: `self`,
+ * `name`, `postMessage`, `close` and the `onmessage`/`onmessageerror`
+ * handlers. Evaluates to a function called by the runtime with the native
+ * ops and the worker name; it returns the function the runtime calls to
+ * dispatch messages from the parent.
+ */
+((ops, name) => {
+  const define = (key, value) =>
+    Object.defineProperty(globalThis, key, { value, writable: true, configurable: true });
+
+  define("self", globalThis);
+  define("name", name);
+  // The second argument is a transfer list or `{ transfer }`
+  define("postMessage", function postMessage(message, options) {
+    if (arguments.length === 0) {
+      throw new TypeError("postMessage requires a message");
+    }
+    const transfer = Array.isArray(options) ? options : Array.from(options?.transfer ?? []);
+    ops.postMessage(message, transfer);
+  });
+  define("close", function close() {
+    ops.close();
+  });
+  globalThis.onmessage = null;
+  globalThis.onmessageerror = null;
+
+  return (type, data) => {
+    globalThis.dispatchEvent(new MessageEvent(type, { data }));
+  };
+});
@@ -7,18 +7,38 @@
 #![warn(unused_extern_crates)]
 
 pub mod cli;
+pub mod code_cache;
+pub mod error;
+pub mod event_loop;
+pub mod extension;
+pub mod heap_snapshot;
+pub mod inspector;
 pub mod module_loader;
 pub mod ops;
 pub mod permissions;
+pub mod pool;
 pub mod repl;
 pub mod runtime;
+pub mod snapshot;
+pub mod structured_clone;
+pub mod watchdog;
+pub mod worker;
 
 // Re-exports for convenience
 pub use cli::{parse_args, Cli, Commands};
+pub use error::JsError;
+pub use extension::Extension;
 pub use module_loader::{ImportMap, ModuleLoader, ModuleLoaderConfig};
-pub use permissions::{Permissions, ReadPermission, WritePermission, NetPermission, EnvPermission, RunPermission};
+pub use ops::dispatch::{Op, OpRegistry};
+pub use ops::state::OpState;
+pub use permissions::{
+    ChildPermission, ChildPermissions, Permissions, ReadPermission, WritePermission, NetPermission, EnvPermission,
+    RunPermission,
+};
+pub use pool::{JsRuntimePool, PoolConfig, PoolMetrics};
 pub use repl::{Repl, ReplConfig, start_repl};
-pub use runtime::{JsRuntime, RuntimeConfig, RuntimeError, RuntimeResult};
+pub use runtime::{FunctionArgs, HeapStats, JsRuntime, RuntimeConfig, RuntimeError, RuntimeResult};
+pub use structured_clone::SerializedValue;
 
 /// Version information
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
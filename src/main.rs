@@ -5,7 +5,7 @@
 
 use std::process::ExitCode;
 
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 // Import Ferrum library
 use ferrum::{
@@ -154,6 +154,69 @@ fn run(args: &ferrum::Cli) -> Result<(), FerrumError> {
     }
 }
 
+/// Build the runtime configuration for a command
+fn runtime_config(command: &Commands) -> Result<RuntimeConfig, FerrumError> {
+    let heap_snapshot_signal = command
+        .heap_snapshot_signal()
+        .map(|name| {
+            ferrum::heap_snapshot::parse_signal(name)
+                .ok_or_else(|| FerrumError::Runtime(format!("Unsupported heap snapshot signal: {}", name)))
+        })
+        .transpose()?;
+
+    Ok(RuntimeConfig {
+        timeout_ms: command.timeout_ms().unwrap_or(0),
+        enable_inspector: command.inspect_enabled().is_some(),
+        inspector_port: command.inspect_enabled().unwrap_or(9229),
+        inspect_brk: command.inspect_brk(),
+        code_cache_dir: code_cache_dir(),
+        heap_snapshot_signal,
+        ..RuntimeConfig::default()
+    })
+}
+
+/// Get the directory of the V8 code cache
+fn code_cache_dir() -> Option<std::path::PathBuf> {
+    ferrum::snapshot::default_cache_dir().map(|dir| dir.join("code_cache"))
+}
+
+/// Create a runtime, booting from the cached startup snapshot when possible
+fn create_runtime(
+    config: RuntimeConfig,
+    permissions: ferrum::Permissions,
+) -> Result<ferrum::JsRuntime, FerrumError> {
+    let snapshot = ferrum::snapshot::default_cache_dir().and_then(|dir| {
+        ferrum::snapshot::load_or_create_snapshot(&dir)
+            .map_err(|e| warn!("Startup snapshot unavailable: {}", e))
+            .ok()
+    });
+
+    let runtime = match snapshot {
+        Some(snapshot) => ferrum::JsRuntime::from_snapshot(config, permissions, snapshot),
+        None => ferrum::JsRuntime::new(config, permissions),
+    };
+    runtime.map_err(|e| FerrumError::Runtime(e.to_string()))
+}
+
+/// Print where DevTools can connect when the inspector is enabled
+fn announce_inspector(runtime: &ferrum::JsRuntime, script: &str, command: &Commands) {
+    let Some(inspector) = runtime.inspector() else {
+        return;
+    };
+
+    if let Ok(url) = std::fs::canonicalize(script).map_err(|_| ()).and_then(url::Url::from_file_path) {
+        inspector.set_target_url(url.as_str());
+    }
+
+    if let Some(url) = inspector.websocket_url() {
+        eprintln!("Debugger listening on {}", url);
+        eprintln!("Visit chrome://inspect to connect to the debugger.");
+        if command.inspect_brk() {
+            eprintln!("Waiting for the debugger to connect...");
+        }
+    }
+}
+
 /// Run a JavaScript/TypeScript file
 fn run_script(script: &str, command: &Commands) -> Result<(), FerrumError> {
     let permissions = command.permissions();
@@ -161,10 +224,46 @@ fn run_script(script: &str, command: &Commands) -> Result<(), FerrumError> {
     info!("Running script: {}", script);
     info!("Permissions: {:?}", permissions);
 
-    let config = RuntimeConfig::default();
-    let mut runtime = ferrum::JsRuntime::new(config, permissions)
+    let config = runtime_config(command)?;
+    let mut runtime = create_runtime(config, permissions)?;
+    announce_inspector(&runtime, script, command);
+
+    if let Some((_, interval)) = command.cpu_prof() {
+        runtime
+            .start_cpu_profile(interval)
+            .map_err(|e| FerrumError::Runtime(e.to_string()))?;
+    }
+
+    let result = execute_script(&mut runtime, script, command);
+
+    // The profile is written even when the script failed
+    if let Some((dir, _)) = command.cpu_prof() {
+        write_cpu_profile(&mut runtime, &dir)?;
+    }
+
+    result
+}
+
+/// Write the running CPU profile to a `.cpuprofile` file
+fn write_cpu_profile(runtime: &mut ferrum::JsRuntime, dir: &std::path::Path) -> Result<(), FerrumError> {
+    let profile = runtime
+        .stop_cpu_profile()
         .map_err(|e| FerrumError::Runtime(e.to_string()))?;
 
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = dir.join(format!("CPU.{}.{}.cpuprofile", timestamp, std::process::id()));
+
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&path, profile)?;
+    info!("CPU profile written to {}", path.display());
+    Ok(())
+}
+
+/// Execute a script or module in a runtime and drive its event loop
+fn execute_script(runtime: &mut ferrum::JsRuntime, script: &str, command: &Commands) -> Result<(), FerrumError> {
     // Check if we should use module loading
     // Use module loading for .mjs files or when import map is specified
     let use_module_loading = script.ends_with(".mjs")
@@ -196,26 +295,28 @@ fn run_script(script: &str, command: &Commands) -> Result<(), FerrumError> {
 
         runtime.setup_module_loader(module_config);
 
-        // Execute as module
-        match runtime.execute_module(script) {
+        // Execute as module, then drive any work it scheduled
+        match runtime.execute_module(script).and_then(|_| runtime.run_event_loop()) {
             Ok(_) => {
                 info!("Module executed successfully");
                 Ok(())
             }
             Err(e) => {
                 error!("Module execution failed: {}", e);
+                report_runtime_error(&e);
                 Err(FerrumError::Runtime(e.to_string()))
             }
         }
     } else {
-        // Execute as regular script
-        match runtime.execute_file(script) {
+        // Execute as regular script, then drive any work it scheduled
+        match runtime.execute_file(script).and_then(|_| runtime.run_event_loop()) {
             Ok(_) => {
                 info!("Script executed successfully");
                 Ok(())
             }
             Err(e) => {
                 error!("Script execution failed: {}", e);
+                report_runtime_error(&e);
                 Err(FerrumError::Runtime(e.to_string()))
             }
         }
@@ -226,18 +327,33 @@ fn run_script(script: &str, command: &Commands) -> Result<(), FerrumError> {
 fn run_eval(code: &str, command: &Commands) -> Result<(), FerrumError> {
     let permissions = command.permissions();
 
-    let config = RuntimeConfig::default();
-    let mut runtime = ferrum::JsRuntime::new(config, permissions)
-        .map_err(|e| FerrumError::Runtime(e.to_string()))?;
+    let config = runtime_config(command)?;
+    let mut runtime = create_runtime(config, permissions)?;
 
-    match runtime.execute(code, Some("<eval>")) {
+    match runtime.execute_and_wait(code, Some("<eval>")) {
         Ok(output) => {
             if !output.is_empty() {
                 println!("{}", output);
             }
             Ok(())
         }
-        Err(e) => Err(FerrumError::Runtime(e.to_string())),
+        Err(e) => {
+            report_runtime_error(&e);
+            Err(FerrumError::Runtime(e.to_string()))
+        }
+    }
+}
+
+/// Print a JavaScript exception with its stack trace and a code frame
+fn report_runtime_error(e: &RuntimeError) {
+    match e {
+        RuntimeError::ExecutionError(js_error) | RuntimeError::CompilationError(js_error) => {
+            eprintln!("error: Uncaught {}", js_error.to_pretty_string());
+        }
+        RuntimeError::UnhandledRejection(js_error) => {
+            eprintln!("error: Uncaught (in promise) {}", js_error.to_pretty_string());
+        }
+        _ => {}
     }
 }
 
@@ -320,6 +436,10 @@ fn run_cache(subcommand: &ferrum::cli::CacheCommands) -> Result<(), FerrumError>
     match subcommand {
         ferrum::cli::CacheCommands::Clear => {
             info!("Clearing cache");
+            if let Some(dir) = code_cache_dir() {
+                ferrum::code_cache::CodeCache::new(dir)
+                    .clear()?;
+            }
             println!("Cache cleared");
             Ok(())
         }
@@ -281,6 +281,11 @@ impl ModuleLoader {
         }
     }
 
+    /// Get the loader configuration
+    pub fn config(&self) -> &ModuleLoaderConfig {
+        &self.config
+    }
+
     /// Resolve a module specifier to an absolute path or URL
     pub fn resolve(&self, specifier: &str, referrer: Option<&str>) -> ModuleResult<String> {
         // Check import map first
@@ -1,63 +1,46 @@
 //! V8-Rust Bridge Bindings
 //!
 //! This module provides V8 function callbacks that expose Rust operations to JavaScript.
-//! These bridges allow JavaScript code to call native functions like console.log and
-//! Deno.readTextFile through the V8 API.
+//! These bridges allow JavaScript code to call native functions like console.log,
+//! setTimeout and Deno.readTextFile through the V8 API.
 //!
 //! # Architecture
 //!
 //! The bridge works by:
-//! 1. Storing RuntimeContext in thread-local storage during execution
-//! 2. V8 callbacks access the context through thread-local storage
+//! 1. Storing the runtime's RuntimeContext in a slot of its isolate
+//! 2. V8 callbacks read the context from the isolate they are called on
 //! 3. Callbacks extract arguments, check permissions, execute Rust code, and return values
 //!
-//! # Thread Safety
+//! The `Deno` API is a registry of built-in ops (see [`builtin_ops`]),
+//! installed and dispatched like the ops registered by the embedder. Most
+//! of them are typed ops with serde-converted arguments.
 //!
-//! RuntimeContext is wrapped in Arc<Mutex<>> to allow safe access from V8 callbacks
-//! which may execute from different threads. We use thread-local storage to pass
-//! the context to callbacks.
+//! # Isolation
+//!
+//! Every runtime owns its isolate, so callbacks always see the permissions
+//! and ops of their own runtime, even with many runtimes on one thread.
 
 use std::cell::{Cell, RefCell};
-use std::sync::Arc;
-
-use v8;
-
-use crate::ops::fs;
-use crate::runtime::RuntimeContext;
-
-// Thread-local storage for the current runtime context
-// This is set during script execution and accessed by V8 callbacks
-thread_local! {
-    static CURRENT_CONTEXT: RefCell<Option<Arc<RuntimeContext>>> = RefCell::new(None);
-}
-
-/// Set the current runtime context for this thread
-fn set_current_context(context: Arc<RuntimeContext>) {
-    CURRENT_CONTEXT.with(|ctx| {
-        *ctx.borrow_mut() = Some(context);
-    });
-}
-
-/// Get the current runtime context for this thread
-fn get_current_context() -> Option<Arc<RuntimeContext>> {
-    CURRENT_CONTEXT.with(|ctx| {
-        ctx.borrow().clone()
-    })
-}
-
-/// Clear the current runtime context for this thread
-fn clear_current_context() {
-    CURRENT_CONTEXT.with(|ctx| {
-        *ctx.borrow_mut() = None;
-    });
-}
-
-/// Extract RuntimeContext from thread-local storage
-///
-/// # Safety
-///
-/// This function accesses thread-local storage that should have been
-/// set during script execution.
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use v8::{self, MapFnTo};
+
+use crate::event_loop::{EventLoop, OpCompletion, OpId};
+use crate::heap_snapshot;
+use crate::ops::dispatch::{Op, OpRegistry};
+use crate::ops::state::OpState;
+use crate::ops::{fs, timers};
+use crate::permissions::Permissions;
+use crate::runtime::{main_module, HeapStats, RuntimeContext};
+use crate::structured_clone;
+use crate::worker::{self, WorkerEvent, WorkerEvents, WorkerHost};
+
+/// Get the RuntimeContext of the isolate a callback runs on
 ///
 /// # Arguments
 ///
@@ -65,19 +48,9 @@ fn clear_current_context() {
 ///
 /// # Returns
 ///
-/// Returns `Some(&RuntimeContext)` if the context was found, `None` otherwise
-unsafe fn get_context<'a>(_scope: &mut v8::HandleScope) -> Option<&'a RuntimeContext> {
-    // Get the Arc from thread-local storage
-    // We extend the lifetime to match the scope - this is safe because:
-    // 1. The context is set before script execution
-    // 2. The context lives for the duration of the script
-    // 3. We only access it during the script execution
-    get_current_context().and_then(|arc| {
-        // Get a raw pointer to the inner RuntimeContext
-        // Safety: The Arc ensures the data is alive
-        let ptr = Arc::as_ptr(&arc) as *const RuntimeContext;
-        Some(&*ptr)
-    })
+/// Returns `Some(Arc<RuntimeContext>)` if the isolate has a context, `None` otherwise
+fn get_context(scope: &mut v8::HandleScope) -> Option<Arc<RuntimeContext>> {
+    scope.get_slot::<Arc<RuntimeContext>>().cloned()
 }
 
 /// Throw a JavaScript error from a Rust callback
@@ -99,7 +72,10 @@ fn throw_type_error(scope: &mut v8::HandleScope, message: &str) {
     scope.throw_exception(error);
 }
 
-/// Extract a string argument from V8
+/// Deserialize an argument of a raw op
+///
+/// The argument is converted like the arguments of typed ops (see
+/// [`v8_to_json`]), so a missing argument reads as `null`.
 ///
 /// # Arguments
 ///
@@ -109,23 +85,14 @@ fn throw_type_error(scope: &mut v8::HandleScope, message: &str) {
 ///
 /// # Returns
 ///
-/// Returns `Some(String)` if the argument exists and is a string, `None` otherwise
-fn extract_string_arg(
+/// Returns the argument, or an error message if it cannot be converted to `T`
+fn deserialize_arg<T: DeserializeOwned>(
     scope: &mut v8::HandleScope,
     args: &v8::FunctionCallbackArguments,
     index: i32,
-) -> Option<String> {
-    if args.length() <= index {
-        return None;
-    }
-
-    let arg = args.get(index);
-    if arg.is_string() {
-        let str_val = arg.to_rust_string_lossy(scope);
-        Some(str_val)
-    } else {
-        None
-    }
+) -> Result<T, String> {
+    let value = v8_to_json(scope, args.get(index)).ok_or("argument cannot be converted")?;
+    serde_json::from_value(value).map_err(|e| e.to_string())
 }
 
 /// Extract a bytes argument from V8 (ArrayBuffer or Uint8Array)
@@ -228,6 +195,9 @@ pub fn op_console_log(
     // Output to stdout with newline
     println!("{}", output);
 
+    // Show the message in DevTools as well
+    mirror_console_call(scope, &args);
+
     // Return undefined
     rv.set_undefined();
 }
@@ -263,6 +233,9 @@ pub fn op_console_error(
 
     eprintln!("{}", output);
 
+    // Show the message in DevTools as well
+    mirror_console_call(scope, &args);
+
     rv.set_undefined();
 }
 
@@ -295,20 +268,145 @@ pub fn op_console_warn(
 
     eprintln!("{}", output);
 
+    // Show the message in DevTools as well
+    mirror_console_call(scope, &args);
+
     rv.set_undefined();
 }
 
 // ============================================================================
-// Deno File System API Callbacks
+// Async Ops
+// ============================================================================
+
+/// Converts the output of an async op to a JavaScript value
+pub(crate) type ToJs<T> = for<'s> fn(&mut v8::HandleScope<'s>, T) -> v8::Local<'s, v8::Value>;
+
+/// Start an async op and return its promise
+///
+/// The future is polled by the event loop on the runtime's tokio runtime
+/// (see `JsRuntime::run_event_loop`). Once it completes, the promise is
+/// resolved with the output converted by `to_js`, or rejected with an
+/// `Error` carrying the error message.
+///
+/// # Arguments
+///
+/// * `scope` - The V8 handle scope (must be a ContextScope)
+/// * `future` - The op's work
+/// * `to_js` - Conversion of the future's output
+pub(crate) fn spawn_async_op<'s, T: 'static>(
+    scope: &mut v8::HandleScope<'s>,
+    future: impl Future<Output = Result<T, String>> + 'static,
+    to_js: ToJs<T>,
+) -> v8::Local<'s, v8::Promise> {
+    let resolver = v8::PromiseResolver::new(scope).unwrap();
+    let promise = resolver.get_promise(scope);
+    let resolver = v8::Global::new(scope, resolver);
+
+    let context = scope.get_current_context();
+    let context = v8::Global::new(scope, context);
+
+    let future = Box::pin(async move {
+        let result = future.await;
+        Box::new(move |scope: &mut v8::HandleScope| {
+            let resolver = v8::Local::new(scope, &resolver);
+            match result {
+                Ok(output) => {
+                    let value = to_js(scope, output);
+                    resolver.resolve(scope, value);
+                }
+                Err(message) => {
+                    let message = v8::String::new(scope, &message).unwrap();
+                    let exception = v8::Exception::error(scope, message);
+                    resolver.reject(scope, exception);
+                }
+            }
+        }) as OpCompletion
+    });
+
+    let event_loop = EventLoop::from_isolate(scope);
+    let mut event_loop = event_loop.borrow_mut();
+    let id = event_loop.next_op_id();
+    event_loop.schedule(id, context, future);
+
+    promise
+}
+
+// ============================================================================
+// Deno File System API
 // ============================================================================
+//
+// The file system ops are ops of the built-in registry (see `builtin_ops`).
+// Ops exchanging text and plain values are typed ops: their arguments are
+// deserialized with serde, and an argument of the wrong type throws (or
+// rejects the promise of an async op). Ops exchanging binary data are raw
+// ops, since typed ops convert their values through JSON.
+//
+// The async variants return a promise and run the file system call on
+// tokio's blocking thread pool. The `*Sync` variants block the JavaScript
+// thread.
+
+/// Options of `Deno.mkdir()` and `Deno.remove()`
+#[derive(Debug, Default, Deserialize)]
+struct FsOptions {
+    /// Create missing parent directories / remove directories with their contents
+    #[serde(default)]
+    recursive: bool,
+}
+
+/// Arguments of ops taking a path and an optional options object
+#[derive(Debug, Deserialize)]
+struct PathWithOptions(String, #[serde(default)] Option<FsOptions>);
 
-/// Deno.readTextFile() implementation
+impl PathWithOptions {
+    /// Get the `recursive` option (`false` without options)
+    fn recursive(&self) -> bool {
+        self.1.as_ref().is_some_and(|options| options.recursive)
+    }
+}
+
+/// Metadata returned by `Deno.stat()`, shaped like `Deno.FileInfo`
+///
+/// Timestamps are converted from seconds to milliseconds and omitted when
+/// the platform does not provide them.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileInfo {
+    is_file: bool,
+    is_directory: bool,
+    is_symlink: bool,
+    size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mtime: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    atime: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    birthtime: Option<u64>,
+    readonly: bool,
+}
+
+impl From<fs::FileMetadata> for FileInfo {
+    fn from(meta: fs::FileMetadata) -> Self {
+        Self {
+            is_file: meta.is_file,
+            is_directory: meta.is_directory,
+            is_symlink: meta.is_symlink,
+            size: meta.size,
+            mtime: meta.modified.map(|secs| secs * 1000),
+            atime: meta.accessed.map(|secs| secs * 1000),
+            birthtime: meta.created.map(|secs| secs * 1000),
+            readonly: meta.readonly,
+        }
+    }
+}
+
+/// Deno.readTextFile() / Deno.readTextFileSync() implementation
 ///
 /// Reads a file and returns its contents as a string.
 ///
 /// # JavaScript Signature
 /// ```javascript
 /// async function Deno.readTextFile(path: string): Promise<string>
+/// function Deno.readTextFileSync(path: string): string
 /// ```
 ///
 /// # Example
@@ -319,57 +417,22 @@ pub fn op_console_warn(
 ///
 /// # Errors
 ///
-/// Throws if:
-/// - Path argument is missing or not a string
+/// Fails if:
 /// - Permission is denied
 /// - File does not exist
 /// - File cannot be read
-pub fn op_read_text_file(
-    scope: &mut v8::HandleScope,
-    args: v8::FunctionCallbackArguments,
-    mut rv: v8::ReturnValue,
-) {
-    // Extract RuntimeContext from V8
-    let ctx = match unsafe { get_context(scope) } {
-        Some(ctx) => ctx,
-        None => {
-            throw_error(scope, "Runtime context not found");
-            return;
-        }
-    };
-
-    // Extract path argument
-    let path = match extract_string_arg(scope, &args, 0) {
-        Some(p) => p,
-        None => {
-            throw_type_error(scope, "readTextFile requires a string path argument");
-            return;
-        }
-    };
-
-    // Get permissions
-    let permissions = ctx.permissions.lock().unwrap();
-
-    // Execute the file read
-    match fs::read_text_file(&path, &permissions) {
-        Ok(content) => {
-            // Convert result to V8 string
-            let result_str = v8::String::new(scope, &content).unwrap();
-            rv.set(result_str.into());
-        }
-        Err(e) => {
-            throw_error(scope, &format!("readTextFile: {}", e));
-        }
-    }
+fn op_read_text_file(permissions: &Permissions, (path,): (String,)) -> fs::FsResult<String> {
+    fs::read_text_file(&path, permissions)
 }
 
-/// Deno.writeTextFile() implementation
+/// Deno.writeTextFile() / Deno.writeTextFileSync() implementation
 ///
 /// Writes a string to a file, creating parent directories if needed.
 ///
 /// # JavaScript Signature
 /// ```javascript
 /// async function Deno.writeTextFile(path: string, data: string): Promise<void>
+/// function Deno.writeTextFileSync(path: string, data: string): void
 /// ```
 ///
 /// # Example
@@ -379,56 +442,97 @@ pub fn op_read_text_file(
 ///
 /// # Errors
 ///
-/// Throws if:
-/// - Path or data argument is missing
+/// Fails if:
 /// - Permission is denied
 /// - File cannot be written
-pub fn op_write_text_file(
-    scope: &mut v8::HandleScope,
-    args: v8::FunctionCallbackArguments,
-    mut rv: v8::ReturnValue,
-) {
-    let ctx = match unsafe { get_context(scope) } {
-        Some(ctx) => ctx,
-        None => {
-            throw_error(scope, "Runtime context not found");
-            return;
-        }
-    };
+fn op_write_text_file(permissions: &Permissions, (path, data): (String, String)) -> fs::FsResult<()> {
+    fs::write_text_file(&path, &data, permissions)
+}
 
-    // Extract path argument
-    let path = match extract_string_arg(scope, &args, 0) {
-        Some(p) => p,
-        None => {
-            throw_type_error(scope, "writeTextFile requires a string path argument");
-            return;
-        }
-    };
+/// Deno.exists() / Deno.existsSync() implementation
+///
+/// Checks if a path exists.
+///
+/// # JavaScript Signature
+/// ```javascript
+/// async function Deno.exists(path: string): Promise<boolean>
+/// function Deno.existsSync(path: string): boolean
+/// ```
+///
+/// # Example
+/// ```javascript
+/// if (await Deno.exists("./config.json")) {
+///     console.log("Config file found");
+/// }
+/// ```
+fn op_exists(permissions: &Permissions, (path,): (String,)) -> fs::FsResult<bool> {
+    fs::exists(&path, permissions)
+}
 
-    // Extract data argument
-    let data = match extract_string_arg(scope, &args, 1) {
-        Some(d) => d,
-        None => {
-            throw_type_error(scope, "writeTextFile requires a string data argument");
-            return;
-        }
-    };
+/// Deno.stat() / Deno.statSync() implementation
+///
+/// Gets metadata for a file or directory.
+///
+/// # JavaScript Signature
+/// ```javascript
+/// async function Deno.stat(path: string): Promise<FileInfo>
+/// function Deno.statSync(path: string): FileInfo
+/// ```
+///
+/// # Returns
+///
+/// An object with properties: isFile, isDirectory, isSymlink, size, mtime,
+/// atime, birthtime, readonly
+///
+/// # Example
+/// ```javascript
+/// const info = await Deno.stat("./file.txt");
+/// console.log(info.size, "bytes");
+/// ```
+fn op_stat(permissions: &Permissions, (path,): (String,)) -> fs::FsResult<FileInfo> {
+    fs::metadata(&path, permissions).map(FileInfo::from)
+}
 
-    let permissions = ctx.permissions.lock().unwrap();
+/// Deno.mkdir() / Deno.mkdirSync() implementation
+///
+/// Creates a directory.
+///
+/// # JavaScript Signature
+/// ```javascript
+/// async function Deno.mkdir(path: string, options?: { recursive: boolean }): Promise<void>
+/// function Deno.mkdirSync(path: string, options?: { recursive: boolean }): void
+/// ```
+///
+/// # Example
+/// ```javascript
+/// await Deno.mkdir("./dist", { recursive: true });
+/// ```
+fn op_mkdir(permissions: &Permissions, args: PathWithOptions) -> fs::FsResult<()> {
+    fs::create_dir(&args.0, permissions, args.recursive())
+}
 
-    match fs::write_text_file(&path, &data, &permissions) {
-        Ok(_) => {
-            rv.set_undefined();
-        }
-        Err(e) => {
-            throw_error(scope, &format!("writeTextFile: {}", e));
-        }
-    }
+/// Deno.remove() / Deno.removeSync() implementation
+///
+/// Removes a file or directory.
+///
+/// # JavaScript Signature
+/// ```javascript
+/// async function Deno.remove(path: string, options?: { recursive: boolean }): Promise<void>
+/// function Deno.removeSync(path: string, options?: { recursive: boolean }): void
+/// ```
+///
+/// # Example
+/// ```javascript
+/// await Deno.remove("./old-file.txt");
+/// await Deno.remove("./dist", { recursive: true });
+/// ```
+fn op_remove(permissions: &Permissions, args: PathWithOptions) -> fs::FsResult<()> {
+    fs::remove(&args.0, permissions, args.recursive())
 }
 
 /// Deno.readFile() implementation
 ///
-/// Reads a file and returns its contents as a Uint8Array.
+/// Reads a file and resolves with its contents as a Uint8Array.
 ///
 /// # JavaScript Signature
 /// ```javascript
@@ -442,8 +546,8 @@ pub fn op_write_text_file(
 ///
 /// # Errors
 ///
-/// Throws if:
-/// - Path argument is missing or not a string
+/// Throws if the path argument is missing or not a string.
+/// The promise is rejected if:
 /// - Permission is denied
 /// - File does not exist or cannot be read
 pub fn op_read_file(
@@ -451,41 +555,19 @@ pub fn op_read_file(
     args: v8::FunctionCallbackArguments,
     mut rv: v8::ReturnValue,
 ) {
-    let ctx = match unsafe { get_context(scope) } {
-        Some(ctx) => ctx,
-        None => {
-            throw_error(scope, "Runtime context not found");
-            return;
-        }
-    };
-
-    let path = match extract_string_arg(scope, &args, 0) {
-        Some(p) => p,
-        None => {
-            throw_type_error(scope, "readFile requires a string path argument");
-            return;
-        }
+    let Ok(path) = deserialize_arg::<String>(scope, &args, 0) else {
+        throw_type_error(scope, "readFile requires a string path argument");
+        return;
     };
 
-    let permissions = ctx.permissions.lock().unwrap();
-
-    match fs::read_file(&path, &permissions) {
-        Ok(bytes) => {
-            // Create Uint8Array from bytes
-            let buffer = v8::ArrayBuffer::new(scope, bytes.len());
-            {
-                let backing_store = buffer.get_backing_store();
-                for (i, byte) in bytes.iter().enumerate() {
-                    backing_store[i].set(*byte);
-                }
-            }
-            let uint8_array = v8::Uint8Array::new(scope, buffer, 0, bytes.len()).unwrap();
-
-            rv.set(uint8_array.into());
-        }
-        Err(e) => {
-            throw_error(scope, &format!("readFile: {}", e));
-        }
+    let promise = spawn_fs_op(
+        scope,
+        "readFile",
+        move |permissions| fs::read_file(&path, permissions),
+        bytes_to_uint8_array,
+    );
+    if let Some(promise) = promise {
+        rv.set(promise.into());
     }
 }
 
@@ -505,8 +587,8 @@ pub fn op_read_file(
 ///
 /// # Errors
 ///
-/// Throws if:
-/// - Path or data argument is missing
+/// Throws if the path or data argument is missing.
+/// The promise is rejected if:
 /// - Permission is denied
 /// - File cannot be written
 pub fn op_write_file(
@@ -514,20 +596,9 @@ pub fn op_write_file(
     args: v8::FunctionCallbackArguments,
     mut rv: v8::ReturnValue,
 ) {
-    let ctx = match unsafe { get_context(scope) } {
-        Some(ctx) => ctx,
-        None => {
-            throw_error(scope, "Runtime context not found");
-            return;
-        }
-    };
-
-    let path = match extract_string_arg(scope, &args, 0) {
-        Some(p) => p,
-        None => {
-            throw_type_error(scope, "writeFile requires a string path argument");
-            return;
-        }
+    let Ok(path) = deserialize_arg::<String>(scope, &args, 0) else {
+        throw_type_error(scope, "writeFile requires a string path argument");
+        return;
     };
 
     let data = match extract_bytes_arg(scope, &args, 1) {
@@ -538,39 +609,38 @@ pub fn op_write_file(
         }
     };
 
-    let permissions = ctx.permissions.lock().unwrap();
-
-    match fs::write_file(&path, &data, &permissions) {
-        Ok(_) => {
-            rv.set_undefined();
-        }
-        Err(e) => {
-            throw_error(scope, &format!("writeFile: {}", e));
-        }
+    let promise = spawn_fs_op(
+        scope,
+        "writeFile",
+        move |permissions| fs::write_file(&path, &data, permissions),
+        |scope, ()| v8::undefined(scope).into(),
+    );
+    if let Some(promise) = promise {
+        rv.set(promise.into());
     }
 }
 
-/// Deno.exists() implementation
+/// Deno.readFileSync() implementation
 ///
-/// Checks if a path exists.
+/// Reads a file and returns its contents as a Uint8Array.
 ///
 /// # JavaScript Signature
 /// ```javascript
-/// function Deno.exists(path: string): boolean
+/// function Deno.readFileSync(path: string): Uint8Array
 /// ```
 ///
-/// # Example
-/// ```javascript
-/// if (Deno.exists("./config.json")) {
-///     console.log("Config file found");
-/// }
-/// ```
-pub fn op_exists(
+/// # Errors
+///
+/// Throws if:
+/// - Path argument is missing or not a string
+/// - Permission is denied
+/// - File does not exist or cannot be read
+pub fn op_read_file_sync(
     scope: &mut v8::HandleScope,
     args: v8::FunctionCallbackArguments,
     mut rv: v8::ReturnValue,
 ) {
-    let ctx = match unsafe { get_context(scope) } {
+    let ctx = match get_context(scope) {
         Some(ctx) => ctx,
         None => {
             throw_error(scope, "Runtime context not found");
@@ -578,51 +648,45 @@ pub fn op_exists(
         }
     };
 
-    let path = match extract_string_arg(scope, &args, 0) {
-        Some(p) => p,
-        None => {
-            throw_type_error(scope, "exists requires a string path argument");
-            return;
-        }
+    let Ok(path) = deserialize_arg::<String>(scope, &args, 0) else {
+        throw_type_error(scope, "readFileSync requires a string path argument");
+        return;
     };
 
     let permissions = ctx.permissions.lock().unwrap();
 
-    match fs::exists(&path, &permissions) {
-        Ok(result) => {
-            let bool_val = v8::Boolean::new(scope, result);
-            rv.set(bool_val.into());
+    match fs::read_file(&path, &permissions) {
+        Ok(bytes) => {
+            let uint8_array = bytes_to_uint8_array(scope, bytes);
+            rv.set(uint8_array);
         }
         Err(e) => {
-            throw_error(scope, &format!("exists: {}", e));
+            throw_error(scope, &format!("readFileSync: {}", e));
         }
     }
 }
 
-/// Deno.stat() / Deno.metadata() implementation
+/// Deno.writeFileSync() implementation
 ///
-/// Gets metadata for a file or directory.
+/// Writes bytes to a file, creating parent directories if needed.
 ///
 /// # JavaScript Signature
 /// ```javascript
-/// async function Deno.stat(path: string): Promise<FileInfo>
+/// function Deno.writeFileSync(path: string, data: Uint8Array): void
 /// ```
 ///
-/// # Returns
-///
-/// An object with properties: isFile, isDirectory, size, mtime, atime, birthtime, readonly
+/// # Errors
 ///
-/// # Example
-/// ```javascript
-/// const info = await Deno.stat("./file.txt");
-/// console.log(info.size, "bytes");
-/// ```
-pub fn op_metadata(
+/// Throws if:
+/// - Path or data argument is missing
+/// - Permission is denied
+/// - File cannot be written
+pub fn op_write_file_sync(
     scope: &mut v8::HandleScope,
     args: v8::FunctionCallbackArguments,
     mut rv: v8::ReturnValue,
 ) {
-    let ctx = match unsafe { get_context(scope) } {
+    let ctx = match get_context(scope) {
         Some(ctx) => ctx,
         None => {
             throw_error(scope, "Runtime context not found");
@@ -630,94 +694,199 @@ pub fn op_metadata(
         }
     };
 
-    let path = match extract_string_arg(scope, &args, 0) {
-        Some(p) => p,
+    let Ok(path) = deserialize_arg::<String>(scope, &args, 0) else {
+        throw_type_error(scope, "writeFileSync requires a string path argument");
+        return;
+    };
+
+    let data = match extract_bytes_arg(scope, &args, 1) {
+        Some(d) => d,
         None => {
-            throw_type_error(scope, "metadata requires a string path argument");
+            throw_type_error(scope, "writeFileSync requires Uint8Array or ArrayBuffer data");
             return;
         }
     };
 
     let permissions = ctx.permissions.lock().unwrap();
 
-    match fs::metadata(&path, &permissions) {
-        Ok(meta) => {
-            // Create a JavaScript object with the metadata
-            let obj = v8::Object::new(scope);
-
-            // Set isFile
-            let key_is_file = v8::String::new(scope, "isFile").unwrap();
-            let val_is_file = v8::Boolean::new(scope, meta.is_file);
-            obj.set(scope, key_is_file.into(), val_is_file.into());
-
-            // Set isDirectory
-            let key_is_dir = v8::String::new(scope, "isDirectory").unwrap();
-            let val_is_dir = v8::Boolean::new(scope, meta.is_directory);
-            obj.set(scope, key_is_dir.into(), val_is_dir.into());
-
-            // Set isSymlink
-            let key_is_symlink = v8::String::new(scope, "isSymlink").unwrap();
-            let val_is_symlink = v8::Boolean::new(scope, meta.is_symlink);
-            obj.set(scope, key_is_symlink.into(), val_is_symlink.into());
-
-            // Set size
-            let key_size = v8::String::new(scope, "size").unwrap();
-            let val_size = v8::Number::new(scope, meta.size as f64);
-            obj.set(scope, key_size.into(), val_size.into());
-
-            // Set modified (mtime)
-            if let Some(mtime) = meta.modified {
-                let key_mtime = v8::String::new(scope, "mtime").unwrap();
-                let val_mtime = v8::Number::new(scope, mtime as f64 * 1000.0); // Convert to ms
-                obj.set(scope, key_mtime.into(), val_mtime.into());
-            }
-
-            // Set accessed (atime)
-            if let Some(atime) = meta.accessed {
-                let key_atime = v8::String::new(scope, "atime").unwrap();
-                let val_atime = v8::Number::new(scope, atime as f64 * 1000.0);
-                obj.set(scope, key_atime.into(), val_atime.into());
-            }
-
-            // Set created (birthtime)
-            if let Some(birthtime) = meta.created {
-                let key_birthtime = v8::String::new(scope, "birthtime").unwrap();
-                let val_birthtime = v8::Number::new(scope, birthtime as f64 * 1000.0);
-                obj.set(scope, key_birthtime.into(), val_birthtime.into());
-            }
-
-            // Set readonly
-            let key_readonly = v8::String::new(scope, "readonly").unwrap();
-            let val_readonly = v8::Boolean::new(scope, meta.readonly);
-            obj.set(scope, key_readonly.into(), val_readonly.into());
-
-            rv.set(obj.into());
+    match fs::write_file(&path, &data, &permissions) {
+        Ok(_) => {
+            rv.set_undefined();
         }
         Err(e) => {
-            throw_error(scope, &format!("metadata: {}", e));
+            throw_error(scope, &format!("writeFileSync: {}", e));
         }
     }
 }
 
-/// Deno.mkdir() implementation
+/// Register a file system op as an async op of `ops`
+///
+/// The op runs on tokio's blocking thread pool with a copy of the
+/// runtime's permissions.
+fn register_fs_op<A, R, F>(ops: &mut OpRegistry, name: &'static str, op: F)
+where
+    A: DeserializeOwned + Send + 'static,
+    R: Serialize + Send + 'static,
+    F: Fn(&Permissions, A) -> fs::FsResult<R> + Copy + Send + Sync + 'static,
+{
+    ops.register_async_op_with_state(name, move |state: Rc<RefCell<OpState>>, args: A| {
+        let permissions = state.borrow().get::<Arc<Mutex<Permissions>>>().lock().unwrap().clone();
+        run_fs_op(name, permissions, move |permissions| op(permissions, args))
+    });
+}
+
+/// Register a file system op as a blocking typed op of `ops`
+///
+/// The op's error is prefixed with `name`.
+fn register_fs_op_sync<A, R, F>(ops: &mut OpRegistry, name: &'static str, op: F)
+where
+    A: DeserializeOwned,
+    R: Serialize,
+    F: Fn(&Permissions, A) -> fs::FsResult<R> + Send + Sync + 'static,
+{
+    ops.register_op_with_state(name, move |state: &mut OpState, args: A| {
+        let permissions = state.get::<Arc<Mutex<Permissions>>>().lock().unwrap();
+        op(&permissions, args).map_err(|e| format!("{}: {}", name, e))
+    });
+}
+
+/// Run a file system op on tokio's blocking thread pool
+///
+/// The blocking task is spawned on first poll, inside the event loop's
+/// tokio runtime. The op's error is prefixed with `name`.
+async fn run_fs_op<T: Send + 'static>(
+    name: &'static str,
+    permissions: Permissions,
+    op: impl FnOnce(&Permissions) -> fs::FsResult<T> + Send + 'static,
+) -> Result<T, String> {
+    match tokio::task::spawn_blocking(move || op(&permissions)).await {
+        Ok(result) => result.map_err(|e| format!("{}: {}", name, e)),
+        Err(e) => Err(format!("{}: {}", name, e)),
+    }
+}
+
+/// Run a file system op of a raw op on tokio's blocking thread pool
 ///
-/// Creates a directory.
+/// The op gets a copy of the runtime's permissions; its error is prefixed
+/// with `name` when the promise is rejected.
+///
+/// # Returns
+///
+/// The op's promise, or `None` if an exception was thrown
+fn spawn_fs_op<'s, T: Send + 'static>(
+    scope: &mut v8::HandleScope<'s>,
+    name: &'static str,
+    op: impl FnOnce(&Permissions) -> fs::FsResult<T> + Send + 'static,
+    to_js: ToJs<T>,
+) -> Option<v8::Local<'s, v8::Promise>> {
+    let ctx = match get_context(scope) {
+        Some(ctx) => ctx,
+        None => {
+            throw_error(scope, "Runtime context not found");
+            return None;
+        }
+    };
+    let permissions = ctx.permissions.lock().unwrap().clone();
+
+    Some(spawn_async_op(scope, run_fs_op(name, permissions, op), to_js))
+}
+
+/// Copy bytes into a new Uint8Array
+fn bytes_to_uint8_array<'s>(scope: &mut v8::HandleScope<'s>, bytes: Vec<u8>) -> v8::Local<'s, v8::Value> {
+    let backing_store = v8::ArrayBuffer::new_backing_store_from_vec(bytes).make_shared();
+    let buffer = v8::ArrayBuffer::with_backing_store(scope, &backing_store);
+    v8::Uint8Array::new(scope, buffer, 0, buffer.byte_length()).unwrap().into()
+}
+
+// ============================================================================
+// Deno Runtime API Callbacks
+// ============================================================================
+
+/// Deno.memoryUsage() implementation
+///
+/// Reports the memory usage of the process and the V8 heap.
 ///
 /// # JavaScript Signature
 /// ```javascript
-/// async function Deno.mkdir(path: string, options?: { recursive: boolean }): Promise<void>
+/// function Deno.memoryUsage(): MemoryUsage
 /// ```
 ///
+/// # Returns
+///
+/// An object with properties (in bytes): rss, heapTotal, heapUsed, heapLimit,
+/// external, mallocedMemory, peakMallocedMemory, physicalHeapSize, availableHeapSize
+///
 /// # Example
 /// ```javascript
-/// await Deno.mkdir("./dist", { recursive: true });
+/// const { heapUsed, heapTotal } = Deno.memoryUsage();
+/// console.log(`${heapUsed} / ${heapTotal} bytes`);
 /// ```
-pub fn op_mkdir(
+pub fn op_memory_usage(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let stats = HeapStats::from_isolate(scope);
+    let rss = resident_set_size().unwrap_or(stats.total_physical_size);
+
+    let fields = [
+        ("rss", rss),
+        ("heapTotal", stats.total_heap_size),
+        ("heapUsed", stats.used_heap_size),
+        ("heapLimit", stats.heap_size_limit),
+        ("external", stats.external_memory),
+        ("mallocedMemory", stats.malloced_memory),
+        ("peakMallocedMemory", stats.peak_malloced_memory),
+        ("physicalHeapSize", stats.total_physical_size),
+        ("availableHeapSize", stats.total_available_size),
+    ];
+
+    let obj = v8::Object::new(scope);
+    for (name, bytes) in fields {
+        let key = v8::String::new(scope, name).unwrap();
+        let value = v8::Number::new(scope, bytes as f64);
+        obj.set(scope, key.into(), value.into());
+    }
+
+    rv.set(obj.into());
+}
+
+/// Read the resident set size of the process in bytes
+///
+/// Returns `None` on platforms without `/proc/self/status`.
+fn resident_set_size() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Deno.writeHeapSnapshot() implementation
+///
+/// Writes a snapshot of the JavaScript heap that Chrome DevTools can load.
+/// Requires write permission for the output file.
+///
+/// # JavaScript Signature
+/// ```javascript
+/// function Deno.writeHeapSnapshot(path?: string): string
+/// ```
+///
+/// # Returns
+///
+/// The path of the written file (a unique `Heap.*.heapsnapshot` name in the
+/// current directory if no path is given)
+///
+/// # Example
+/// ```javascript
+/// const file = Deno.writeHeapSnapshot();
+/// console.log("Heap snapshot written to", file);
+/// ```
+pub fn op_write_heap_snapshot(
     scope: &mut v8::HandleScope,
     args: v8::FunctionCallbackArguments,
     mut rv: v8::ReturnValue,
 ) {
-    let ctx = match unsafe { get_context(scope) } {
+    let ctx = match get_context(scope) {
         Some(ctx) => ctx,
         None => {
             throw_error(scope, "Runtime context not found");
@@ -725,75 +894,329 @@ pub fn op_mkdir(
         }
     };
 
-    let path = match extract_string_arg(scope, &args, 0) {
-        Some(p) => p,
-        None => {
-            throw_type_error(scope, "mkdir requires a string path argument");
+    let path = match deserialize_arg::<Option<String>>(scope, &args, 0) {
+        Ok(path) => path.unwrap_or_else(heap_snapshot::default_file_name),
+        Err(_) => {
+            throw_type_error(scope, "writeHeapSnapshot path must be a string");
             return;
         }
     };
 
-    // Check for recursive option (second argument, if object with recursive property)
-    let recursive = if args.length() > 1 {
-        let options = args.get(1);
-        if options.is_object() {
-            let obj = v8::Local::<v8::Object>::try_from(options).ok();
-            if let Some(obj) = obj {
-                let scope2 = &mut v8::HandleScope::new(scope);
-                let key = v8::String::new(scope2, "recursive").unwrap();
-                let val = obj.get(scope2, key.into());
-
-                if let Some(val) = val {
-                    if val.is_boolean() {
-                        val.boolean_value(scope2)
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            } else {
-                false
-            }
-        } else {
-            false
+    let permitted = ctx.permissions.lock().unwrap().check_write(&path);
+    if let Err(e) = permitted {
+        throw_error(scope, &format!("writeHeapSnapshot: {}", e));
+        return;
+    }
+
+    match heap_snapshot::write_heap_snapshot(scope, std::path::Path::new(&path)) {
+        Ok(()) => {
+            let path = v8::String::new(scope, &path).unwrap();
+            rv.set(path.into());
         }
+        Err(e) => {
+            throw_error(scope, &format!("writeHeapSnapshot: {}", e));
+        }
+    }
+}
+
+// ============================================================================
+// Timer API Callbacks
+// ============================================================================
+
+/// A timer registered with setTimeout() or setInterval()
+struct Timer {
+    id: OpId,
+    callback: v8::Global<v8::Function>,
+    args: Vec<v8::Global<v8::Value>>,
+    delay_ms: u64,
+    repeat: bool,
+}
+
+/// Register a timer from setTimeout()/setInterval() arguments
+///
+/// Returns the timer ID, or `None` if an exception was thrown.
+fn schedule_timer(
+    scope: &mut v8::HandleScope,
+    args: &v8::FunctionCallbackArguments,
+    repeat: bool,
+) -> Option<OpId> {
+    let callback = match v8::Local::<v8::Function>::try_from(args.get(0)) {
+        Ok(f) => f,
+        Err(_) => {
+            throw_type_error(scope, "Timer callback must be a function");
+            return None;
+        }
+    };
+
+    // Non-numeric, negative or NaN delays are treated as 0
+    let delay = if args.length() > 1 {
+        args.get(1).number_value(scope).unwrap_or(0.0)
     } else {
-        false
+        0.0
+    };
+    let delay_ms = if delay.is_finite() && delay > 0.0 {
+        delay as u64
+    } else {
+        0
     };
 
-    let permissions = ctx.permissions.lock().unwrap();
+    let callback = v8::Global::new(scope, callback);
+    let mut extra_args = Vec::new();
+    for i in 2..args.length() {
+        extra_args.push(v8::Global::new(scope, args.get(i)));
+    }
 
-    match fs::create_dir(&path, &permissions, recursive) {
-        Ok(_) => {
-            rv.set_undefined();
-        }
-        Err(e) => {
-            throw_error(scope, &format!("mkdir: {}", e));
+    let event_loop = EventLoop::from_isolate(scope);
+    let id = {
+        let mut event_loop = event_loop.borrow_mut();
+        let id = event_loop.next_op_id();
+        event_loop.add_timer(id);
+        id
+    };
+
+    let timer = Rc::new(Timer {
+        id,
+        callback,
+        args: extra_args,
+        delay_ms,
+        repeat,
+    });
+    arm_timer(scope, timer);
+
+    Some(id)
+}
+
+/// Schedule the next firing of a timer on the event loop
+fn arm_timer(scope: &mut v8::HandleScope, timer: Rc<Timer>) {
+    let context = scope.get_current_context();
+    let context = v8::Global::new(scope, context);
+    let id = timer.id;
+    let delay_ms = timer.delay_ms;
+
+    let future = Box::pin(async move {
+        timers::sleep(delay_ms).await;
+        Box::new(move |scope: &mut v8::HandleScope| fire_timer(scope, timer)) as OpCompletion
+    });
+
+    EventLoop::from_isolate(scope)
+        .borrow_mut()
+        .schedule(id, context, future);
+}
+
+/// Run a timer callback, re-arming it if it is an uncleared interval
+fn fire_timer(scope: &mut v8::HandleScope, timer: Rc<Timer>) {
+    let event_loop = EventLoop::from_isolate(scope);
+    if !timer.repeat {
+        event_loop.borrow_mut().remove_timer(timer.id);
+    }
+
+    let callback = v8::Local::new(scope, &timer.callback);
+    let args: Vec<v8::Local<v8::Value>> = timer
+        .args
+        .iter()
+        .map(|arg| v8::Local::new(scope, arg))
+        .collect();
+    let recv = v8::undefined(scope).into();
+
+    // On exception, stop here and let the event loop report it
+    if callback.call(scope, recv, &args).is_none() {
+        return;
+    }
+
+    // The callback may have cleared its own interval
+    let still_active = event_loop.borrow().is_timer_active(timer.id);
+    if timer.repeat && still_active {
+        arm_timer(scope, timer);
+    }
+}
+
+/// setTimeout() implementation
+///
+/// Schedules a callback to run once after a delay. The callback runs when
+/// the runtime's event loop is driven (see `JsRuntime::run_event_loop`).
+///
+/// # JavaScript Signature
+/// ```javascript
+/// function setTimeout(callback: (...args: any[]) => void, delay?: number, ...args: any[]): number
+/// ```
+///
+/// # Example
+/// ```javascript
+/// const id = setTimeout(() => console.log("later"), 100);
+/// ```
+pub fn op_set_timeout(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    if let Some(id) = schedule_timer(scope, &args, false) {
+        let id = v8::Number::new(scope, id as f64);
+        rv.set(id.into());
+    }
+}
+
+/// setInterval() implementation
+///
+/// Schedules a callback to run repeatedly with a fixed delay until the
+/// interval is cleared.
+///
+/// # JavaScript Signature
+/// ```javascript
+/// function setInterval(callback: (...args: any[]) => void, delay?: number, ...args: any[]): number
+/// ```
+///
+/// # Example
+/// ```javascript
+/// const id = setInterval(() => console.log("tick"), 500);
+/// ```
+pub fn op_set_interval(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    if let Some(id) = schedule_timer(scope, &args, true) {
+        let id = v8::Number::new(scope, id as f64);
+        rv.set(id.into());
+    }
+}
+
+/// clearTimeout() / clearInterval() implementation
+///
+/// Cancels a pending timer. Unknown IDs and the IDs of other pending
+/// operations are ignored.
+///
+/// # JavaScript Signature
+/// ```javascript
+/// function clearTimeout(id?: number): void
+/// function clearInterval(id?: number): void
+/// ```
+pub fn op_clear_timer(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let id = args.get(0);
+    if id.is_number() {
+        let id = id.number_value(scope).unwrap_or(0.0);
+        if id.is_finite() && id > 0.0 {
+            EventLoop::from_isolate(scope)
+                .borrow_mut()
+                .cancel_timer(id as OpId);
         }
     }
+
+    rv.set_undefined();
 }
 
-/// Deno.remove() implementation
+/// Forward a console call to V8's built-in console method
 ///
-/// Removes a file or directory.
+/// The built-in method is stored as the function's data. It reports the
+/// call to the inspector, which shows it in DevTools; without a connected
+/// session it does nothing.
+fn mirror_console_call(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) {
+    let Ok(builtin) = v8::Local::<v8::Function>::try_from(args.data()) else {
+        return;
+    };
+    let values: Vec<_> = (0..args.length()).map(|i| args.get(i)).collect();
+    let recv = v8::undefined(scope).into();
+    builtin.call(scope, recv, &values);
+}
+
+// ============================================================================
+// Structured Clone API Callbacks
+// ============================================================================
+
+/// structuredClone() implementation
+///
+/// Deep-copies a value with V8's serializer (the HTML structured clone
+/// algorithm). Supports `Map`, `Set`, `Date`, `RegExp`, `ArrayBuffer` and
+/// typed arrays, `Error` objects and cyclic object graphs; throws a
+/// `DataCloneError` for values that cannot be cloned, such as functions.
+/// `ArrayBuffer`s in the `transfer` list are moved to the clone and
+/// detached.
 ///
 /// # JavaScript Signature
 /// ```javascript
-/// async function Deno.remove(path: string, options?: { recursive: boolean }): Promise<void>
+/// function structuredClone(value: any, options?: { transfer?: ArrayBuffer[] }): any
 /// ```
 ///
 /// # Example
 /// ```javascript
-/// await Deno.remove("./old-file.txt");
-/// await Deno.remove("./dist", { recursive: true });
+/// const original = { when: new Date(), tags: new Set(["a"]) };
+/// original.self = original;
+/// const copy = structuredClone(original);
+/// console.log(copy.self === copy); // true
 /// ```
-pub fn op_remove(
+pub fn op_structured_clone(
     scope: &mut v8::HandleScope,
     args: v8::FunctionCallbackArguments,
     mut rv: v8::ReturnValue,
 ) {
-    let ctx = match unsafe { get_context(scope) } {
+    if args.length() == 0 {
+        throw_type_error(scope, "structuredClone requires a value");
+        return;
+    }
+
+    let options = args.get(1);
+    let transfer = if options.is_null_or_undefined() {
+        v8::undefined(scope).into()
+    } else if let Ok(options) = v8::Local::<v8::Object>::try_from(options) {
+        let key = v8::String::new(scope, "transfer").unwrap();
+        let Some(transfer) = options.get(scope, key.into()) else {
+            return;
+        };
+        transfer
+    } else {
+        throw_type_error(scope, "structuredClone options must be an object");
+        return;
+    };
+
+    let Some(transfer) = structured_clone::transfer_list(scope, transfer) else {
+        return;
+    };
+    let Some(serialized) = structured_clone::serialize(scope, args.get(0), &transfer) else {
+        return;
+    };
+    if let Some(clone) = structured_clone::deserialize(scope, serialized) {
+        rv.set(clone);
+    }
+}
+
+// ============================================================================
+// Worker API Callbacks
+// ============================================================================
+
+/// Extract a worker ID argument
+fn extract_worker_id(
+    scope: &mut v8::HandleScope,
+    args: &v8::FunctionCallbackArguments,
+    index: i32,
+) -> Option<OpId> {
+    let id = args.get(index).number_value(scope)?;
+    (id.is_finite() && id > 0.0).then_some(id as OpId)
+}
+
+/// Worker constructor op: start a worker thread
+///
+/// Called by the `Worker` class (see `js/worker.js`). The worker gets the
+/// caller's permissions, narrowed by the `deno.permissions` option;
+/// requesting more than the caller has throws.
+///
+/// # JavaScript Signature
+/// ```javascript
+/// function create(specifier: string, name: string, permissions: string,
+///                 onEvent: (type: string, data: any) => void): number
+/// ```
+///
+/// # Returns
+///
+/// The worker ID
+fn op_worker_create(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let ctx = match get_context(scope) {
         Some(ctx) => ctx,
         None => {
             throw_error(scope, "Runtime context not found");
@@ -801,52 +1224,336 @@ pub fn op_remove(
         }
     };
 
-    let path = match extract_string_arg(scope, &args, 0) {
-        Some(p) => p,
-        None => {
-            throw_type_error(scope, "remove requires a string path argument");
+    let (Ok(specifier), Ok(name), Ok(permissions)) = (
+        deserialize_arg::<String>(scope, &args, 0),
+        deserialize_arg::<String>(scope, &args, 1),
+        deserialize_arg::<String>(scope, &args, 2),
+    ) else {
+        throw_type_error(scope, "Worker specifier, name and permissions must be strings");
+        return;
+    };
+    let Ok(on_event) = v8::Local::<v8::Function>::try_from(args.get(3)) else {
+        throw_type_error(scope, "Worker event callback must be a function");
+        return;
+    };
+
+    let request = match worker::parse_child_permissions(&permissions) {
+        Ok(request) => request,
+        Err(e) => {
+            throw_type_error(scope, &e);
             return;
         }
     };
-
-    let recursive = if args.length() > 1 {
-        let options = args.get(1);
-        if options.is_object() {
-            let obj = v8::Local::<v8::Object>::try_from(options).ok();
-            if let Some(obj) = obj {
-                let scope2 = &mut v8::HandleScope::new(scope);
-                let key = v8::String::new(scope2, "recursive").unwrap();
-                let val = obj.get(scope2, key.into());
-
-                if let Some(val) = val {
-                    if val.is_boolean() {
-                        val.boolean_value(scope2)
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            } else {
-                false
-            }
-        } else {
-            false
+    let parent = ctx.permissions.lock().unwrap().clone();
+    let permissions = match parent.narrow(&request) {
+        Ok(permissions) => permissions,
+        Err(e) => {
+            throw_error(scope, &format!("Worker: {}", e));
+            return;
         }
-    } else {
-        false
     };
 
-    let permissions = ctx.permissions.lock().unwrap();
+    let host = WorkerHost::from_isolate(scope);
+    let main = main_module(scope);
+    let specifier = match host.borrow().resolve(&specifier, main.as_deref(), &parent) {
+        Ok(specifier) => specifier,
+        Err(e) => {
+            throw_error(scope, &format!("Worker: {}", e));
+            return;
+        }
+    };
 
-    match fs::remove(&path, &permissions, recursive) {
-        Ok(_) => {
-            rv.set_undefined();
+    let id = EventLoop::from_isolate(scope).borrow_mut().next_op_id();
+    let spawned = host.borrow_mut().spawn(id, specifier, name, permissions);
+    match spawned {
+        Ok(events) => {
+            let on_event = Rc::new(v8::Global::new(scope, on_event));
+            arm_worker_events(scope, id, events, on_event);
+            let id = v8::Number::new(scope, id as f64);
+            rv.set(id.into());
         }
         Err(e) => {
-            throw_error(scope, &format!("remove: {}", e));
+            throw_error(scope, &format!("Worker: failed to start thread: {}", e));
+        }
+    }
+}
+
+/// Wait for the next event from a worker on the event loop
+fn arm_worker_events(
+    scope: &mut v8::HandleScope,
+    id: OpId,
+    events: WorkerEvents,
+    on_event: Rc<v8::Global<v8::Function>>,
+) {
+    let context = scope.get_current_context();
+    let context = v8::Global::new(scope, context);
+
+    let future = Box::pin(async move {
+        let event = std::future::poll_fn(|cx| events.borrow_mut().poll_recv(cx)).await;
+        Box::new(move |scope: &mut v8::HandleScope| {
+            deliver_worker_event(scope, id, event, events, on_event)
+        }) as OpCompletion
+    });
+
+    EventLoop::from_isolate(scope)
+        .borrow_mut()
+        .schedule(id, context, future);
+}
+
+/// Pass a worker event to the `Worker` object and wait for the next one
+fn deliver_worker_event(
+    scope: &mut v8::HandleScope,
+    id: OpId,
+    event: Option<WorkerEvent>,
+    events: WorkerEvents,
+    on_event: Rc<v8::Global<v8::Function>>,
+) {
+    // The worker thread has finished
+    let Some(event) = event else {
+        return;
+    };
+
+    let (kind, data) = match event {
+        WorkerEvent::Message(message) => worker::deserialize_message(scope, message),
+        WorkerEvent::Error(message) => ("error", v8::String::new(scope, &message).unwrap().into()),
+    };
+    let kind = v8::String::new(scope, kind).unwrap();
+    let callback = v8::Local::new(scope, &*on_event);
+    let recv = v8::undefined(scope).into();
+
+    // On exception, stop here and let the event loop report it
+    if callback.call(scope, recv, &[kind.into(), data]).is_none() {
+        return;
+    }
+
+    // The listener may have terminated the worker
+    if WorkerHost::from_isolate(scope).borrow().contains(id) {
+        arm_worker_events(scope, id, events, on_event);
+    }
+}
+
+/// Worker.prototype.postMessage() op
+///
+/// Clones the message and sends it to the worker, moving the transferred
+/// `ArrayBuffer`s. Throws a `DataCloneError` for values that cannot be
+/// cloned; messages to terminated workers are dropped.
+///
+/// # JavaScript Signature
+/// ```javascript
+/// function postMessage(id: number, message: any, transfer: ArrayBuffer[]): void
+/// ```
+fn op_worker_post_message(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(id) = extract_worker_id(scope, &args, 0) else {
+        throw_type_error(scope, "Invalid worker ID");
+        return;
+    };
+    let Some(transfer) = structured_clone::transfer_list(scope, args.get(2)) else {
+        return;
+    };
+    let Some(message) = structured_clone::serialize(scope, args.get(1), &transfer) else {
+        return;
+    };
+
+    WorkerHost::from_isolate(scope).borrow().post_message(id, message);
+    rv.set_undefined();
+}
+
+/// Worker.prototype.terminate() op
+///
+/// Stops the worker immediately; events it has not delivered yet are
+/// discarded. Terminating a worker twice does nothing.
+///
+/// # JavaScript Signature
+/// ```javascript
+/// function terminate(id: number): void
+/// ```
+fn op_worker_terminate(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    if let Some(id) = extract_worker_id(scope, &args, 0) {
+        WorkerHost::from_isolate(scope).borrow_mut().terminate(id);
+        EventLoop::from_isolate(scope).borrow_mut().cancel(id);
+    }
+    rv.set_undefined();
+}
+
+// ============================================================================
+// Registered Op Callbacks
+// ============================================================================
+
+/// Dispatch a call to a typed or async op of the runtime's `OpRegistry`
+/// or of one of its extensions
+///
+/// The op's qualified name is stored as the function's data. Arguments are converted
+/// to JSON values (`undefined` becomes `null`) and the op's result is
+/// converted back; op errors are thrown as `Error`s. Async ops return a
+/// promise that is settled with the result instead.
+fn op_dispatch(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let ctx = match get_context(scope) {
+        Some(ctx) => ctx,
+        None => {
+            throw_error(scope, "Runtime context not found");
+            return;
+        }
+    };
+
+    let name = args.data().to_rust_string_lossy(scope);
+    let op = ctx.find_op(&name);
+
+    let mut values = Vec::with_capacity(args.length() as usize);
+    for i in 0..args.length() {
+        match v8_to_json(scope, args.get(i)) {
+            Some(value) => values.push(value),
+            None => return,
         }
     }
+
+    match op {
+        Some(Op::Typed(op)) => {
+            let result = op(&mut OpState::from_isolate(scope).borrow_mut(), values);
+            match result {
+                Ok(result) => rv.set(json_to_v8(scope, result)),
+                Err(e) => throw_error(scope, &e),
+            }
+        }
+        Some(Op::Async(op)) => {
+            let promise = spawn_async_op(scope, op(OpState::from_isolate(scope), values), json_to_v8);
+            rv.set(promise.into());
+        }
+        _ => throw_error(scope, &format!("Op '{}' is not registered", name)),
+    }
+}
+
+/// Convert a JavaScript value to a JSON value
+///
+/// Values without a JSON representation (`undefined`, functions, symbols)
+/// become `null`. Returns `None` if `JSON.stringify` threw (e.g. for
+/// cyclic objects or BigInts).
+pub(crate) fn v8_to_json(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Option<serde_json::Value> {
+    if value.is_undefined() {
+        return Some(serde_json::Value::Null);
+    }
+    let json = v8::json::stringify(scope, value)?.to_rust_string_lossy(scope);
+    Some(serde_json::from_str(&json).unwrap_or(serde_json::Value::Null))
+}
+
+/// Convert a JSON value to a JavaScript value
+pub(crate) fn json_to_v8<'s>(scope: &mut v8::HandleScope<'s>, value: serde_json::Value) -> v8::Local<'s, v8::Value> {
+    let json = v8::String::new(scope, &value.to_string()).unwrap();
+    v8::json::parse(scope, json).unwrap_or_else(|| v8::undefined(scope).into())
+}
+
+/// Install the ops of a registry on its namespace object
+///
+/// Raw ops are installed as-is; typed and async ops are routed through
+/// [`op_dispatch`]. Nothing is installed for an empty registry.
+///
+/// # Arguments
+///
+/// * `scope` - The V8 handle scope (must be a ContextScope)
+/// * `registry` - The registry whose ops to install
+pub(crate) fn install_registered_ops(
+    scope: &mut v8::HandleScope,
+    registry: &OpRegistry,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if registry.is_empty() {
+        return Ok(());
+    }
+
+    // Find or create every object along the namespace path
+    let mut target = scope.get_current_context().global(scope);
+    for part in registry.namespace().split('.').filter(|part| !part.is_empty()) {
+        let key = v8::String::new(scope, part).unwrap();
+        let existing = target
+            .get(scope, key.into())
+            .and_then(|value| v8::Local::<v8::Object>::try_from(value).ok());
+        target = match existing {
+            Some(object) => object,
+            None => {
+                let object = v8::Object::new(scope);
+                target.set(scope, key.into(), object.into());
+                object
+            }
+        };
+    }
+
+    for (name, op) in registry.iter() {
+        let key = v8::String::new(scope, name).unwrap();
+        let func = match op {
+            Op::Raw(callback) => v8::Function::builder_raw(*callback).build(scope),
+            Op::Typed(_) | Op::Async(_) => {
+                let qualified_name = v8::String::new(scope, &registry.qualified_name(name)).unwrap();
+                v8::Function::builder_raw(op_dispatch.map_fn_to())
+                    .data(qualified_name.into())
+                    .build(scope)
+            }
+        }
+        .ok_or_else(|| format!("Failed to create op '{}'", name))?;
+        target.set(scope, key.into(), func.into());
+    }
+
+    tracing::debug!("Registered {} ops on '{}'", registry.len(), registry.namespace());
+    Ok(())
+}
+
+// ============================================================================
+// Built-in Ops
+// ============================================================================
+
+/// Namespace of the built-in ops
+const BUILTIN_NAMESPACE: &str = "Deno";
+
+/// Ops installed on the `Deno` object
+static BUILTIN_OPS: Lazy<OpRegistry> = Lazy::new(|| {
+    let mut ops = OpRegistry::new();
+    ops.set_namespace(BUILTIN_NAMESPACE);
+
+    // File system ops, each with a blocking `*Sync` variant
+    register_fs_op(&mut ops, "readTextFile", op_read_text_file);
+    register_fs_op_sync(&mut ops, "readTextFileSync", op_read_text_file);
+    register_fs_op(&mut ops, "writeTextFile", op_write_text_file);
+    register_fs_op_sync(&mut ops, "writeTextFileSync", op_write_text_file);
+    register_fs_op(&mut ops, "exists", op_exists);
+    register_fs_op_sync(&mut ops, "existsSync", op_exists);
+    register_fs_op(&mut ops, "stat", op_stat);
+    register_fs_op_sync(&mut ops, "statSync", op_stat);
+    register_fs_op(&mut ops, "mkdir", op_mkdir);
+    register_fs_op_sync(&mut ops, "mkdirSync", op_mkdir);
+    register_fs_op(&mut ops, "remove", op_remove);
+    register_fs_op_sync(&mut ops, "removeSync", op_remove);
+
+    // Raw ops moving binary data or reading the V8 heap
+    let raw: [(&str, v8::FunctionCallback); 6] = [
+        ("readFile", op_read_file.map_fn_to()),
+        ("readFileSync", op_read_file_sync.map_fn_to()),
+        ("writeFile", op_write_file.map_fn_to()),
+        ("writeFileSync", op_write_file_sync.map_fn_to()),
+        ("memoryUsage", op_memory_usage.map_fn_to()),
+        ("writeHeapSnapshot", op_write_heap_snapshot.map_fn_to()),
+    ];
+    for (name, callback) in raw {
+        ops.register(name.to_string(), callback);
+    }
+
+    ops
+});
+
+/// Get the built-in ops installed on the `Deno` object
+///
+/// They are dispatched like the ops of the embedder's registry (see
+/// `RuntimeContext::find_op`), which take precedence.
+pub(crate) fn builtin_ops() -> &'static OpRegistry {
+    &BUILTIN_OPS
 }
 
 // ============================================================================
@@ -857,55 +1564,64 @@ pub fn op_remove(
 ///
 /// This function creates the global objects that JavaScript code can access:
 /// - `console` object with log, error, warn methods
-/// - `Deno` object with file system methods
+/// - `Deno` object with the built-in ops (see [`builtin_ops`]): file system
+///   methods (async, returning promises, and `*Sync`), `memoryUsage` and
+///   `writeHeapSnapshot`
+/// - `setTimeout`, `setInterval`, `clearTimeout` and `clearInterval`
+/// - `structuredClone`
+/// - `Event`, `PromiseRejectionEvent`, `MessageEvent`, `ErrorEvent`,
+///   `EventTarget` and `addEventListener`, `removeEventListener` and
+///   `dispatchEvent` on the global object
+/// - `Worker`
 ///
 /// # Arguments
 ///
 /// * `scope` - The V8 handle scope (must be a ContextScope)
 /// * `context` - The runtime context to store for callbacks
 ///
-/// # Safety
-///
-/// This function stores the RuntimeContext in thread-local storage.
-/// The context must live as long as the V8 isolate.
-///
 /// # Memory Safety
 ///
-/// We store the Arc<RuntimeContext> in thread-local storage.
-/// This prevents the context from being dropped while V8 is using it.
-/// The caller is responsible for clearing the context after execution.
+/// The Arc<RuntimeContext> is stored in an isolate slot, which keeps it
+/// alive as long as the isolate and thus as long as any callback can run.
 pub fn bootstrap_globals(
     scope: &mut v8::HandleScope,
     context: Arc<RuntimeContext>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     tracing::debug!("Bootstrapping global JavaScript APIs");
 
-    // Store the context in thread-local storage
-    set_current_context(context.clone());
+    // Make the context reachable from callbacks through the isolate
+    scope.set_slot(context);
 
     let context_scope = scope.get_current_context();
     let global = context_scope.global(scope);
 
-    tracing::trace!("Stored RuntimeContext in thread-local storage");
+    tracing::trace!("Stored RuntimeContext in isolate slot");
 
     // Create console object
     let console = v8::Object::new(scope);
 
-    // Register console methods
+    // Register console methods, keeping V8's built-in ones for the inspector
     {
         let scope2 = &mut v8::HandleScope::new(scope);
 
-        let name_log = v8::String::new(scope2, "log").unwrap();
-        let func_log = v8::Function::new(scope2, op_console_log).unwrap();
-        console.set(scope2, name_log.into(), func_log.into());
-
-        let name_error = v8::String::new(scope2, "error").unwrap();
-        let func_error = v8::Function::new(scope2, op_console_error).unwrap();
-        console.set(scope2, name_error.into(), func_error.into());
-
-        let name_warn = v8::String::new(scope2, "warn").unwrap();
-        let func_warn = v8::Function::new(scope2, op_console_warn).unwrap();
-        console.set(scope2, name_warn.into(), func_warn.into());
+        let key = v8::String::new(scope2, "console").unwrap();
+        let builtin_console = global
+            .get(scope2, key.into())
+            .and_then(|value| v8::Local::<v8::Object>::try_from(value).ok());
+
+        let methods: [(&str, v8::FunctionCallback); 3] = [
+            ("log", op_console_log.map_fn_to()),
+            ("error", op_console_error.map_fn_to()),
+            ("warn", op_console_warn.map_fn_to()),
+        ];
+        for (name, callback) in methods {
+            let name = v8::String::new(scope2, name).unwrap();
+            let builtin = builtin_console
+                .and_then(|builtin_console| builtin_console.get(scope2, name.into()))
+                .unwrap_or_else(|| v8::undefined(scope2).into());
+            let func = v8::Function::builder_raw(callback).data(builtin).build(scope2).unwrap();
+            console.set(scope2, name.into(), func.into());
+        }
     }
 
     // Set console on global object
@@ -917,72 +1633,161 @@ pub fn bootstrap_globals(
 
     tracing::debug!("Registered console object");
 
-    // Create Deno object
-    let deno = v8::Object::new(scope);
+    // Deno object with the built-in ops
+    install_registered_ops(scope, builtin_ops())?;
 
-    // Register Deno methods
+    tracing::debug!("Registered Deno object");
+
+    // Register timer functions on the global object
     {
         let scope2 = &mut v8::HandleScope::new(scope);
 
-        // readTextFile
-        let name = v8::String::new(scope2, "readTextFile").unwrap();
-        let func = v8::Function::new(scope2, op_read_text_file).unwrap();
-        deno.set(scope2, name.into(), func.into());
-
-        // writeTextFile
-        let name = v8::String::new(scope2, "writeTextFile").unwrap();
-        let func = v8::Function::new(scope2, op_write_text_file).unwrap();
-        deno.set(scope2, name.into(), func.into());
+        let name = v8::String::new(scope2, "setTimeout").unwrap();
+        let func = v8::Function::new(scope2, op_set_timeout).unwrap();
+        global.set(scope2, name.into(), func.into());
 
-        // readFile
-        let name = v8::String::new(scope2, "readFile").unwrap();
-        let func = v8::Function::new(scope2, op_read_file).unwrap();
-        deno.set(scope2, name.into(), func.into());
+        let name = v8::String::new(scope2, "setInterval").unwrap();
+        let func = v8::Function::new(scope2, op_set_interval).unwrap();
+        global.set(scope2, name.into(), func.into());
 
-        // writeFile
-        let name = v8::String::new(scope2, "writeFile").unwrap();
-        let func = v8::Function::new(scope2, op_write_file).unwrap();
-        deno.set(scope2, name.into(), func.into());
+        let name = v8::String::new(scope2, "clearTimeout").unwrap();
+        let func = v8::Function::new(scope2, op_clear_timer).unwrap();
+        global.set(scope2, name.into(), func.into());
 
-        // exists
-        let name = v8::String::new(scope2, "exists").unwrap();
-        let func = v8::Function::new(scope2, op_exists).unwrap();
-        deno.set(scope2, name.into(), func.into());
+        let name = v8::String::new(scope2, "clearInterval").unwrap();
+        let func = v8::Function::new(scope2, op_clear_timer).unwrap();
+        global.set(scope2, name.into(), func.into());
+    }
 
-        // stat (metadata)
-        let name = v8::String::new(scope2, "stat").unwrap();
-        let func = v8::Function::new(scope2, op_metadata).unwrap();
-        deno.set(scope2, name.into(), func.into());
+    tracing::debug!("Registered timer functions");
 
-        // mkdir
-        let name = v8::String::new(scope2, "mkdir").unwrap();
-        let func = v8::Function::new(scope2, op_mkdir).unwrap();
-        deno.set(scope2, name.into(), func.into());
+    // Register structuredClone on the global object
+    {
+        let scope2 = &mut v8::HandleScope::new(scope);
+        let name = v8::String::new(scope2, "structuredClone").unwrap();
+        let func = v8::Function::new(scope2, op_structured_clone).unwrap();
+        global.set(scope2, name.into(), func.into());
+    }
 
-        // remove
-        let name = v8::String::new(scope2, "remove").unwrap();
-        let func = v8::Function::new(scope2, op_remove).unwrap();
-        deno.set(scope2, name.into(), func.into());
+    // Event and EventTarget support for runtime events
+    {
+        let scope2 = &mut v8::HandleScope::new(scope);
+        let source = v8::String::new(scope2, EVENTS_JS).unwrap();
+        let dispatch = v8::Script::compile(scope2, source, None)
+            .and_then(|script| script.run(scope2))
+            .ok_or("Failed to evaluate events bootstrap")?;
+        let key = unhandled_rejection_key(scope2);
+        global.set_private(scope2, key, dispatch);
     }
 
-    // Set Deno on global object
+    tracing::debug!("Registered global events");
+
+    // Worker class backed by the worker ops
     {
         let scope2 = &mut v8::HandleScope::new(scope);
-        let key = v8::String::new(scope2, "Deno").unwrap();
-        global.set(scope2, key.into(), deno.into());
+
+        let ops = v8::Object::new(scope2);
+        let methods: [(&str, v8::FunctionCallback); 3] = [
+            ("create", op_worker_create.map_fn_to()),
+            ("postMessage", op_worker_post_message.map_fn_to()),
+            ("terminate", op_worker_terminate.map_fn_to()),
+        ];
+        for (name, callback) in methods {
+            let name = v8::String::new(scope2, name).unwrap();
+            let func = v8::Function::builder_raw(callback).build(scope2).unwrap();
+            ops.set(scope2, name.into(), func.into());
+        }
+
+        let source = v8::String::new(scope2, WORKER_JS).unwrap();
+        let install = v8::Script::compile(scope2, source, None)
+            .and_then(|script| script.run(scope2))
+            .and_then(|value| v8::Local::<v8::Function>::try_from(value).ok())
+            .ok_or("Failed to evaluate worker bootstrap")?;
+        let recv = v8::undefined(scope2).into();
+        install
+            .call(scope2, recv, &[ops.into()])
+            .ok_or("Failed to install Worker")?;
     }
 
-    tracing::debug!("Registered Deno object");
+    tracing::debug!("Registered Worker");
     tracing::info!("Global JavaScript APIs bootstrapped successfully");
 
     Ok(())
 }
 
-/// Clear the current runtime context from thread-local storage
+/// JavaScript bootstrapping `Event`, `EventTarget` and the global `EventTarget` methods
+const EVENTS_JS: &str = include_str!("../js/events.js");
+
+/// JavaScript defining the `Worker` class
+const WORKER_JS: &str = include_str!("../js/worker.js");
+
+/// Private key of the internal `unhandledrejection` dispatch function
+fn unhandled_rejection_key<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Private> {
+    let name = v8::String::new(scope, "ferrum.dispatchUnhandledRejection").unwrap();
+    v8::Private::for_api(scope, Some(name))
+}
+
+/// Dispatch an `unhandledrejection` event on `globalThis`
+///
+/// # Arguments
+/// * `scope` - V8 handle scope with the promise's context entered
+/// * `promise` - The rejected promise
+/// * `reason` - The rejection reason
 ///
-/// This should be called after script execution to clean up
-pub fn clear_globals() {
-    clear_current_context();
+/// # Returns
+/// `Some(true)` if a listener called `preventDefault()`, `Some(false)` if
+/// the rejection is still unhandled, or `None` if a listener threw
+pub(crate) fn dispatch_unhandled_rejection(
+    scope: &mut v8::HandleScope,
+    promise: v8::Local<v8::Promise>,
+    reason: v8::Local<v8::Value>,
+) -> Option<bool> {
+    let global = scope.get_current_context().global(scope);
+    let key = unhandled_rejection_key(scope);
+    let Some(dispatch) = global
+        .get_private(scope, key)
+        .and_then(|value| v8::Local::<v8::Function>::try_from(value).ok())
+    else {
+        return Some(false);
+    };
+
+    let recv = v8::undefined(scope).into();
+    dispatch
+        .call(scope, recv, &[promise.into(), reason])
+        .map(|handled| handled.is_true())
+}
+
+/// Native callbacks installed by [`bootstrap_globals`]
+///
+/// V8 serializes functions backed by native callbacks as indices into this
+/// table, so the same table must be passed when creating a startup snapshot
+/// and when booting an isolate from it. Every callback registered in
+/// `bootstrap_globals` must be listed here.
+static EXTERNAL_REFERENCES: Lazy<v8::ExternalReferences> = Lazy::new(|| {
+    v8::ExternalReferences::new(&[
+        v8::ExternalReference { function: op_console_log.map_fn_to() },
+        v8::ExternalReference { function: op_console_error.map_fn_to() },
+        v8::ExternalReference { function: op_console_warn.map_fn_to() },
+        v8::ExternalReference { function: op_read_file.map_fn_to() },
+        v8::ExternalReference { function: op_read_file_sync.map_fn_to() },
+        v8::ExternalReference { function: op_write_file.map_fn_to() },
+        v8::ExternalReference { function: op_write_file_sync.map_fn_to() },
+        v8::ExternalReference { function: op_memory_usage.map_fn_to() },
+        v8::ExternalReference { function: op_write_heap_snapshot.map_fn_to() },
+        v8::ExternalReference { function: op_set_timeout.map_fn_to() },
+        v8::ExternalReference { function: op_set_interval.map_fn_to() },
+        v8::ExternalReference { function: op_clear_timer.map_fn_to() },
+        v8::ExternalReference { function: op_structured_clone.map_fn_to() },
+        v8::ExternalReference { function: op_worker_create.map_fn_to() },
+        v8::ExternalReference { function: op_worker_post_message.map_fn_to() },
+        v8::ExternalReference { function: op_worker_terminate.map_fn_to() },
+        v8::ExternalReference { function: op_dispatch.map_fn_to() },
+    ])
+});
+
+/// Get the external references of the bootstrapped globals
+pub(crate) fn external_references() -> &'static v8::ExternalReferences {
+    &EXTERNAL_REFERENCES
 }
 
 #[cfg(test)]
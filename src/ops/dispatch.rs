@@ -2,43 +2,95 @@
 //!
 //! This module provides the registry and dispatch system for native operations
 //! that can be called from JavaScript via the V8-Rust bridge.
+//!
+//! # Architecture
+//!
+//! Embedders register ops before the runtime starts (see
+//! `JsRuntime::with_ops`). Every op is installed as a function on the
+//! registry's namespace object, e.g. `globalThis.ops.add`. Raw ops are V8
+//! callbacks installed as-is; typed ops are Rust closures whose arguments
+//! and return values are converted with serde, and are called through a
+//! single dispatch callback that looks them up in the registry.
+//!
+//! Async ops return a future instead of a value. JavaScript gets a promise
+//! immediately; the future runs on the event loop's tokio runtime and the
+//! promise is settled with its result.
+//!
+//! Typed and async ops can access the runtime's [`OpState`].
 
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use v8;
 
-/// Operation registry for managing native operations
+use crate::ops::state::OpState;
+
+/// Default namespace object of registered ops (`globalThis.ops`)
+pub const DEFAULT_OP_NAMESPACE: &str = "ops";
+
+/// Typed operation: JavaScript arguments (as JSON values) in, result out
 ///
-/// This registry stores V8 function callbacks that can be invoked from JavaScript.
-/// For the MVP, we use a simple HashMap with function pointers.
+/// The error is thrown in JavaScript as an `Error` with this message.
+pub type TypedOpFn =
+    dyn Fn(&mut OpState, Vec<serde_json::Value>) -> Result<serde_json::Value, String> + Send + Sync;
+
+/// Future returned by an async operation
+///
+/// It is polled on the JavaScript thread, so it does not need to be `Send`.
+pub type AsyncOpFuture = Pin<Box<dyn Future<Output = Result<serde_json::Value, String>>>>;
+
+/// Async operation: JavaScript arguments (as JSON values) in, future of the
+/// result out
 ///
-/// # Note
+/// The promise returned to JavaScript is rejected with an `Error` carrying
+/// the future's error message.
+pub type AsyncOpFn = dyn Fn(Rc<RefCell<OpState>>, Vec<serde_json::Value>) -> AsyncOpFuture + Send + Sync;
+
+/// A native operation callable from JavaScript
+#[derive(Clone)]
+pub enum Op {
+    /// V8 callback that converts its own arguments (see `src/ops/bindings.rs`)
+    Raw(v8::FunctionCallback),
+    /// Rust closure with serde-converted arguments and return value
+    Typed(Arc<TypedOpFn>),
+    /// Rust closure returning a future; called from JavaScript it returns a promise
+    Async(Arc<AsyncOpFn>),
+}
+
+/// Operation registry for managing native operations
 ///
-/// V8 callbacks use a low-level raw pointer API. The callback signature is:
-/// `extern "C" fn(*const v8::FunctionCallbackInfo)`
+/// This registry stores the ops that are exposed to JavaScript under the
+/// registry's namespace.
 ///
-/// Inside the callback, you can use `v8::FunctionCallbackInfo::from()` to convert
-/// the raw pointer to the higher-level API.
+/// Typed ops (see [`OpRegistry::register_op`]) take their JavaScript
+/// arguments as a tuple and return any serializable value; this is the
+/// preferred way to expose Rust functions. Raw ops are V8 callbacks with
+/// the low-level signature `extern "C" fn(*const v8::FunctionCallbackInfo)`.
 ///
 /// # Example
 ///
 /// ```no_run
 /// use ferrum::ops::dispatch::OpRegistry;
-/// use v8::FunctionCallbackInfo;
 ///
 /// let mut registry = OpRegistry::new();
+/// registry.set_namespace("app");
 ///
-/// // Register an operation with an extern "C" function
-/// extern "C" fn my_op(info: *const FunctionCallbackInfo) {
-///     let info = unsafe { &*info };
-///     // Use info to access arguments and return values
-/// }
-///
-/// registry.register("my_op".to_string(), my_op);
+/// // Called from JavaScript as `app.add(1, 2)`
+/// registry.register_op("add", |(a, b): (f64, f64)| Ok::<_, String>(a + b));
 /// ```
 #[derive(Clone)]
 pub struct OpRegistry {
-    /// Map of operation names to their V8 function callbacks
-    ops: HashMap<String, v8::FunctionCallback>,
+    /// Map of operation names to their implementations
+    ops: HashMap<String, Op>,
+    /// Dotted path of the object the ops are installed on
+    namespace: String,
 }
 
 impl Default for OpRegistry {
@@ -53,10 +105,27 @@ impl OpRegistry {
     pub fn new() -> Self {
         Self {
             ops: HashMap::new(),
+            namespace: DEFAULT_OP_NAMESPACE.to_string(),
         }
     }
 
-    /// Register a new operation
+    /// Set the namespace the ops are exposed under
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - Dotted path of a global object, e.g. `"app"` or
+    ///   `"app.storage"` (missing objects are created)
+    pub fn set_namespace(&mut self, namespace: impl Into<String>) {
+        self.namespace = namespace.into();
+    }
+
+    /// Get the namespace the ops are exposed under
+    #[must_use]
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// Register a new raw operation
     ///
     /// # Arguments
     ///
@@ -72,7 +141,143 @@ impl OpRegistry {
     /// // of proper V8 callback implementations
     /// ```
     pub fn register(&mut self, name: String, callback: v8::FunctionCallback) {
-        self.ops.insert(name, callback);
+        self.ops.insert(name, Op::Raw(callback));
+    }
+
+    /// Register a new typed operation
+    ///
+    /// The JavaScript arguments are deserialized into `A` as if they were
+    /// a JSON array, so ops take a tuple: `(String,)` for one argument,
+    /// `(f64, f64)` for two, and `()` for none. The result is serialized
+    /// back into a JavaScript value; an error is thrown as an `Error`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the operation (as it will be called from JavaScript)
+    /// * `op` - Closure implementing the operation
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ferrum::ops::dispatch::OpRegistry;
+    /// let mut registry = OpRegistry::new();
+    /// registry.register_op("greet", |(name,): (String,)| Ok::<_, String>(format!("Hello, {}!", name)));
+    /// ```
+    pub fn register_op<A, R, E, F>(&mut self, name: impl Into<String>, op: F)
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        E: Display,
+        F: Fn(A) -> Result<R, E> + Send + Sync + 'static,
+    {
+        self.register_op_with_state(name, move |_: &mut OpState, args: A| op(args));
+    }
+
+    /// Register a new typed operation with access to the runtime's op state
+    ///
+    /// Like [`OpRegistry::register_op`], but the closure also receives the
+    /// [`OpState`] of the runtime it is called from.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the operation (as it will be called from JavaScript)
+    /// * `op` - Closure implementing the operation
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ferrum::ops::{dispatch::OpRegistry, state::OpState};
+    /// struct Counter(u64);
+    ///
+    /// let mut registry = OpRegistry::new();
+    /// registry.register_op_with_state("increment", |state: &mut OpState, (): ()| {
+    ///     let counter = state.get_mut::<Counter>();
+    ///     counter.0 += 1;
+    ///     Ok::<_, String>(counter.0)
+    /// });
+    /// ```
+    pub fn register_op_with_state<A, R, E, F>(&mut self, name: impl Into<String>, op: F)
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        E: Display,
+        F: Fn(&mut OpState, A) -> Result<R, E> + Send + Sync + 'static,
+    {
+        let typed = move |state: &mut OpState, args: Vec<serde_json::Value>| {
+            let args = deserialize_args::<A>(args).map_err(|e| format!("Invalid arguments: {}", e))?;
+            let result = op(state, args).map_err(|e| e.to_string())?;
+            serde_json::to_value(result).map_err(|e| format!("Invalid return value: {}", e))
+        };
+        self.ops.insert(name.into(), Op::Typed(Arc::new(typed)));
+    }
+
+    /// Register a new async operation
+    ///
+    /// Arguments are converted as for [`OpRegistry::register_op`]. The
+    /// closure returns a future, which is run on the runtime's event loop;
+    /// JavaScript receives a promise that is resolved with the future's
+    /// result or rejected with an `Error` (also for invalid arguments).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the operation (as it will be called from JavaScript)
+    /// * `op` - Closure starting the operation
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ferrum::ops::dispatch::OpRegistry;
+    /// # use std::time::Duration;
+    /// let mut registry = OpRegistry::new();
+    /// // `await ops.sleep(100)` resolves after 100 ms
+    /// registry.register_async_op("sleep", |(ms,): (u64,)| async move {
+    ///     tokio::time::sleep(Duration::from_millis(ms)).await;
+    ///     Ok::<_, String>(())
+    /// });
+    /// ```
+    pub fn register_async_op<A, R, E, F, Fut>(&mut self, name: impl Into<String>, op: F)
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        E: Display,
+        F: Fn(A) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, E>> + 'static,
+    {
+        self.register_async_op_with_state(name, move |_: Rc<RefCell<OpState>>, args: A| op(args));
+    }
+
+    /// Register a new async operation with access to the runtime's op state
+    ///
+    /// Like [`OpRegistry::register_async_op`], but the closure also receives
+    /// the [`OpState`] of the runtime it is called from. The future must not
+    /// hold a borrow of it across an `.await`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the operation (as it will be called from JavaScript)
+    /// * `op` - Closure starting the operation
+    pub fn register_async_op_with_state<A, R, E, F, Fut>(&mut self, name: impl Into<String>, op: F)
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        E: Display,
+        F: Fn(Rc<RefCell<OpState>>, A) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, E>> + 'static,
+    {
+        let async_op = move |state: Rc<RefCell<OpState>>, args: Vec<serde_json::Value>| -> AsyncOpFuture {
+            let future = match deserialize_args::<A>(args) {
+                Ok(args) => op(state, args),
+                Err(e) => {
+                    let error = format!("Invalid arguments: {}", e);
+                    return Box::pin(async move { Err(error) });
+                }
+            };
+            Box::pin(async move {
+                let result = future.await.map_err(|e| e.to_string())?;
+                serde_json::to_value(result).map_err(|e| format!("Invalid return value: {}", e))
+            })
+        };
+        self.ops.insert(name.into(), Op::Async(Arc::new(async_op)));
     }
 
     /// Get a registered operation by name
@@ -83,12 +288,39 @@ impl OpRegistry {
     ///
     /// # Returns
     ///
-    /// Returns `Some(&FunctionCallback)` if the operation exists, `None` otherwise.
+    /// Returns `Some(&Op)` if the operation exists, `None` otherwise.
     #[must_use]
-    pub fn get(&self, name: &str) -> Option<&v8::FunctionCallback> {
+    pub fn get(&self, name: &str) -> Option<&Op> {
         self.ops.get(name)
     }
 
+    /// Get the qualified name of an operation (`<namespace>.<name>`)
+    ///
+    /// This is the path of the op's function from the global object.
+    #[must_use]
+    pub fn qualified_name(&self, name: &str) -> String {
+        if self.namespace.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", self.namespace, name)
+        }
+    }
+
+    /// Get a registered operation by its qualified name
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(&Op)` if the name is in this registry's namespace and
+    /// the operation exists, `None` otherwise.
+    #[must_use]
+    pub fn get_qualified(&self, qualified_name: &str) -> Option<&Op> {
+        if self.namespace.is_empty() {
+            return self.get(qualified_name);
+        }
+        let name = qualified_name.strip_prefix(self.namespace.as_str())?.strip_prefix('.')?;
+        self.get(name)
+    }
+
     /// Check if an operation is registered
     ///
     /// # Arguments
@@ -123,9 +355,9 @@ impl OpRegistry {
     ///
     /// # Returns
     ///
-    /// Returns `Some(FunctionCallback)` if the operation was removed,
+    /// Returns `Some(Op)` if the operation was removed,
     /// `None` if it didn't exist.
-    pub fn unregister(&mut self, name: &str) -> Option<v8::FunctionCallback> {
+    pub fn unregister(&mut self, name: &str) -> Option<Op> {
         self.ops.remove(name)
     }
 
@@ -138,6 +370,26 @@ impl OpRegistry {
     pub fn names(&self) -> impl Iterator<Item = &String> {
         self.ops.keys()
     }
+
+    /// Get an iterator over all registered operations
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Op)> {
+        self.ops.iter()
+    }
+}
+
+/// Deserialize the arguments of a typed op
+///
+/// Ops without arguments take `()`, which serde reads from `null` rather
+/// than from an empty array.
+fn deserialize_args<A: DeserializeOwned>(args: Vec<serde_json::Value>) -> serde_json::Result<A> {
+    let empty = args.is_empty();
+    serde_json::from_value(serde_json::Value::Array(args)).or_else(|e| {
+        if empty {
+            serde_json::from_value(serde_json::Value::Null)
+        } else {
+            Err(e)
+        }
+    })
 }
 
 #[cfg(test)]
@@ -244,4 +496,81 @@ mod tests {
             assert!(registry.contains(&format!("op_{}", i)));
         }
     }
+
+    #[test]
+    fn test_registry_namespace() {
+        let mut registry = OpRegistry::new();
+        assert_eq!(registry.namespace(), DEFAULT_OP_NAMESPACE);
+
+        registry.set_namespace("app.storage");
+        assert_eq!(registry.namespace(), "app.storage");
+    }
+
+    #[test]
+    fn test_qualified_name() {
+        let mut registry = OpRegistry::new();
+        registry.register_op("add", |(a, b): (f64, f64)| Ok::<_, String>(a + b));
+        assert_eq!(registry.qualified_name("add"), "ops.add");
+        assert!(registry.get_qualified("ops.add").is_some());
+        assert!(registry.get_qualified("add").is_none());
+        assert!(registry.get_qualified("opsadd").is_none());
+
+        registry.set_namespace("");
+        assert_eq!(registry.qualified_name("add"), "add");
+        assert!(registry.get_qualified("add").is_some());
+    }
+
+    #[test]
+    fn test_stateful_op() {
+        let mut registry = OpRegistry::new();
+        registry.register_op_with_state("increment", |state: &mut OpState, (): ()| {
+            let counter = state.get_mut::<u32>();
+            *counter += 1;
+            Ok::<_, String>(*counter)
+        });
+
+        let Some(Op::Typed(op)) = registry.get("increment") else {
+            panic!("increment is not a typed op");
+        };
+        let mut state = OpState::new();
+        state.put(0u32);
+        assert_eq!(op(&mut state, vec![]), Ok(1.into()));
+        assert_eq!(op(&mut state, vec![]), Ok(2.into()));
+    }
+
+    #[test]
+    fn test_typed_op() {
+        let mut registry = OpRegistry::new();
+        registry.register_op("add", |(a, b): (f64, f64)| Ok::<_, String>(a + b));
+        registry.register_op("version", |(): ()| Ok::<_, String>("1.0"));
+        registry.register_op("fail", |(message,): (String,)| Err::<(), _>(message));
+
+        let call = |name: &str, args: Vec<serde_json::Value>| match registry.get(name) {
+            Some(Op::Typed(op)) => op(&mut OpState::new(), args),
+            _ => panic!("{} is not a typed op", name),
+        };
+
+        assert_eq!(call("add", vec![1.into(), 2.5.into()]), Ok(3.5.into()));
+        assert_eq!(call("version", vec![]), Ok("1.0".into()));
+        assert_eq!(call("fail", vec!["boom".into()]), Err("boom".to_string()));
+        assert!(call("add", vec!["one".into()]).unwrap_err().starts_with("Invalid arguments"));
+    }
+
+    #[test]
+    fn test_async_op() {
+        let mut registry = OpRegistry::new();
+        registry.register_async_op("double", |(n,): (i64,)| async move { Ok::<_, String>(n * 2) });
+
+        let Some(Op::Async(op)) = registry.get("double") else {
+            panic!("double is not an async op");
+        };
+        let tokio_runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+        let state = Rc::new(RefCell::new(OpState::new()));
+        assert_eq!(tokio_runtime.block_on(op(state.clone(), vec![21.into()])), Ok(42.into()));
+        assert!(tokio_runtime
+            .block_on(op(state, vec!["one".into()]))
+            .unwrap_err()
+            .starts_with("Invalid arguments"));
+    }
 }
@@ -6,6 +6,7 @@ pub mod bindings;
 pub mod dispatch;
 pub mod fs;
 pub mod net;
+pub mod state;
 pub mod timers;
 
 // Re-export common types
@@ -13,4 +14,5 @@ pub use bindings::*;
 pub use dispatch::*;
 pub use fs::*;
 pub use net::*;
+pub use state::*;
 pub use timers::*;
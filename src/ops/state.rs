@@ -0,0 +1,134 @@
+//! Per-Runtime Op State
+//!
+//! [`OpState`] is a type map holding state that ops share across calls,
+//! such as a connection pool or the configuration of an extension. Every
+//! runtime has its own, stored in an isolate slot, so ops of different
+//! runtimes never see each other's state.
+//!
+//! Typed ops registered with `OpRegistry::register_op_with_state` receive
+//! it directly; raw ops can get it with [`OpState::from_isolate`].
+//!
+//! Every runtime's state holds its permissions as
+//! `Arc<Mutex<Permissions>>`, which the built-in file system ops check.
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use v8;
+
+/// Per-runtime state of ops, keyed by type
+#[derive(Default)]
+pub struct OpState {
+    /// One value per type
+    values: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl OpState {
+    /// Create an empty state
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the op state stored in the isolate
+    ///
+    /// Shared as `Rc<RefCell<OpState>>` through an isolate slot, like the
+    /// event loop. Do not hold a borrow while running JavaScript, which may
+    /// call ops that borrow it again.
+    ///
+    /// # Panics
+    /// Panics if the isolate was not created by `JsRuntime`.
+    pub fn from_isolate(isolate: &v8::Isolate) -> Rc<RefCell<OpState>> {
+        isolate
+            .get_slot::<Rc<RefCell<OpState>>>()
+            .expect("OpState not found in isolate")
+            .clone()
+    }
+
+    /// Store a value, replacing any previous value of the same type
+    ///
+    /// # Returns
+    /// The previous value, if any
+    pub fn put<T: 'static>(&mut self, value: T) -> Option<T> {
+        self.values
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|previous| previous.downcast().ok())
+            .map(|previous| *previous)
+    }
+
+    /// Check if a value of type `T` is stored
+    #[must_use]
+    pub fn has<T: 'static>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Get the value of type `T`, if stored
+    #[must_use]
+    pub fn try_get<T: 'static>(&self) -> Option<&T> {
+        self.values.get(&TypeId::of::<T>()).and_then(|value| value.downcast_ref())
+    }
+
+    /// Get the value of type `T` mutably, if stored
+    pub fn try_get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.values.get_mut(&TypeId::of::<T>()).and_then(|value| value.downcast_mut())
+    }
+
+    /// Get the value of type `T`
+    ///
+    /// # Panics
+    /// Panics if no value of type `T` is stored.
+    #[must_use]
+    pub fn get<T: 'static>(&self) -> &T {
+        self.try_get()
+            .unwrap_or_else(|| panic!("{} not found in OpState", std::any::type_name::<T>()))
+    }
+
+    /// Get the value of type `T` mutably
+    ///
+    /// # Panics
+    /// Panics if no value of type `T` is stored.
+    pub fn get_mut<T: 'static>(&mut self) -> &mut T {
+        self.try_get_mut()
+            .unwrap_or_else(|| panic!("{} not found in OpState", std::any::type_name::<T>()))
+    }
+
+    /// Remove the value of type `T`
+    ///
+    /// # Returns
+    /// The removed value, if any
+    pub fn take<T: 'static>(&mut self) -> Option<T> {
+        self.values
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Counter(u32);
+
+    #[test]
+    fn test_op_state() {
+        let mut state = OpState::new();
+        assert!(!state.has::<Counter>());
+        assert!(state.try_get::<Counter>().is_none());
+
+        assert_eq!(state.put(Counter(1)), None);
+        state.get_mut::<Counter>().0 += 1;
+        assert_eq!(state.get::<Counter>(), &Counter(2));
+
+        // Values are keyed by type
+        state.put(String::from("config"));
+        assert_eq!(state.put(Counter(10)), Some(Counter(2)));
+        assert_eq!(state.get::<String>(), "config");
+
+        assert_eq!(state.take::<Counter>(), Some(Counter(10)));
+        assert!(!state.has::<Counter>());
+    }
+}
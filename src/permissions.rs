@@ -337,6 +337,112 @@ impl Permissions {
     }
 }
 
+/// Permission requested for one kind of resource by a child runtime (worker)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ChildPermission {
+    /// Same access as the parent
+    #[default]
+    Inherit,
+    /// No access
+    Deny,
+    /// Unrestricted access (the parent must have it too)
+    GrantAll,
+    /// Access to specific paths, addresses, variables or commands (each one
+    /// must be allowed for the parent too)
+    Grant(Vec<String>),
+}
+
+/// Permissions requested by a child runtime (worker)
+///
+/// Children never get wider access than their parent; see
+/// [`Permissions::narrow`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChildPermissions {
+    /// Read file system permission
+    pub read: ChildPermission,
+    /// Write file system permission
+    pub write: ChildPermission,
+    /// Network permission
+    pub net: ChildPermission,
+    /// Environment variable permission
+    pub env: ChildPermission,
+    /// Subprocess permission
+    pub run: ChildPermission,
+}
+
+impl ChildPermissions {
+    /// Request no access at all
+    pub fn none() -> Self {
+        Self {
+            read: ChildPermission::Deny,
+            write: ChildPermission::Deny,
+            net: ChildPermission::Deny,
+            env: ChildPermission::Deny,
+            run: ChildPermission::Deny,
+        }
+    }
+}
+
+impl Permissions {
+    /// Derive the permissions of a child runtime
+    ///
+    /// # Arguments
+    /// * `request` - Permissions requested for the child
+    ///
+    /// # Returns
+    /// The child's permissions, or `PermissionError::Denied` if the request
+    /// asks for access the parent does not have
+    pub fn narrow(&self, request: &ChildPermissions) -> PermissionResult<Permissions> {
+        Ok(Permissions {
+            read: ReadPermission {
+                state: narrow_state(&self.read.state, &request.read, "read")?,
+            },
+            write: WritePermission {
+                state: narrow_state(&self.write.state, &request.write, "write")?,
+            },
+            net: NetPermission {
+                state: narrow_state(&self.net.state, &request.net, "net")?,
+            },
+            env: EnvPermission {
+                state: narrow_state(&self.env.state, &request.env, "env")?,
+            },
+            run: RunPermission {
+                state: narrow_state(&self.run.state, &request.run, "run")?,
+            },
+        })
+    }
+}
+
+/// Narrow one permission state for a child runtime
+fn narrow_state(
+    parent: &PermissionState,
+    request: &ChildPermission,
+    kind: &str,
+) -> PermissionResult<PermissionState> {
+    match request {
+        ChildPermission::Inherit => Ok(parent.clone()),
+        ChildPermission::Deny => Ok(PermissionState::Denied),
+        ChildPermission::GrantAll => match parent {
+            PermissionState::Granted => Ok(PermissionState::Granted),
+            _ => Err(PermissionError::Denied(format!(
+                "Can't escalate parent {} permission to unrestricted access",
+                kind
+            ))),
+        },
+        ChildPermission::Grant(entries) => {
+            if let Some(entry) = entries.iter().find(|entry| !parent.is_granted(Some(entry))) {
+                return Err(PermissionError::Denied(format!(
+                    "Can't escalate parent {} permission to '{}'",
+                    kind, entry
+                )));
+            }
+            Ok(PermissionState::GrantedPartial {
+                paths: entries.iter().cloned().collect(),
+            })
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -458,4 +564,40 @@ mod tests {
         assert!(perms.check_env("ANY").is_err());
         assert!(perms.check_run("any").is_err());
     }
+
+    #[test]
+    fn test_narrow_child_permissions() {
+        let mut parent = Permissions::default();
+        parent.read.grant_paths(vec!["/tmp".to_string()]);
+        parent.net.grant_all();
+
+        let inherited = parent.narrow(&ChildPermissions::default()).unwrap();
+        assert!(inherited.check_read("/tmp/a").is_ok());
+        assert!(inherited.check_net("example.com").is_ok());
+
+        let request = ChildPermissions {
+            read: ChildPermission::Grant(vec!["/tmp/data".to_string()]),
+            net: ChildPermission::Deny,
+            ..ChildPermissions::default()
+        };
+        let child = parent.narrow(&request).unwrap();
+        assert!(child.check_read("/tmp/data/file").is_ok());
+        assert!(child.check_read("/tmp/other").is_err());
+        assert!(child.check_net("example.com").is_err());
+
+        let escalate = ChildPermissions {
+            read: ChildPermission::GrantAll,
+            ..ChildPermissions::default()
+        };
+        assert!(parent.narrow(&escalate).is_err());
+
+        let escalate = ChildPermissions {
+            write: ChildPermission::Grant(vec!["/tmp".to_string()]),
+            ..ChildPermissions::default()
+        };
+        assert!(parent.narrow(&escalate).is_err());
+
+        let none = parent.narrow(&ChildPermissions::none()).unwrap();
+        assert!(none.check_read("/tmp/a").is_err());
+    }
 }
@@ -0,0 +1,714 @@
+//! Runtime Pool
+//!
+//! Creating a [`JsRuntime`] (an isolate plus the bootstrap of its globals)
+//! is expensive compared to running a small script. A [`JsRuntimePool`]
+//! keeps pre-warmed runtimes and hands them out to callers on any thread.
+//!
+//! # Architecture
+//!
+//! A `JsRuntime` cannot leave the thread that created it, so every pooled
+//! runtime lives on a thread of its own. [`JsRuntimePool::run`] checks out
+//! an idle runtime (creating one if the pool is below its maximum size, or
+//! waiting for one otherwise), sends the caller's closure to the runtime's
+//! thread and blocks until it has run. Async callers should call it from
+//! `tokio::task::spawn_blocking`.
+//!
+//! After every use the runtime is reset, so nothing leaks from one caller
+//! to the next: the permissions the factory created it with are restored,
+//! the [`crate::OpState`] is rebuilt from the extensions' state
+//! initializers and the realm is replaced (see
+//! [`JsRuntime::reset_context`]), discarding globals, timers and modules.
+//! State the factory put into the `OpState` directly is dropped as well;
+//! use an extension state initializer for per-runtime state.
+//!
+//! The runtime then goes through a health check: it is recycled instead of
+//! returned to the pool once it has served `max_uses` calls, when its heap
+//! stays above `max_heap_size` after a garbage collection, or when the
+//! closure panicked. A background thread evicts runtimes that have been
+//! idle for longer than `idle_timeout_ms`, and the pool is topped up to
+//! `min_idle` runtimes in the background.
+
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::permissions::Permissions;
+use crate::runtime::{JsRuntime, RuntimeError, RuntimeResult};
+
+/// Creates the runtimes of a pool (called on the runtime's thread)
+type RuntimeFactory = dyn Fn() -> RuntimeResult<JsRuntime> + Send + Sync;
+
+/// Work sent to a runtime's thread
+///
+/// Receives the permissions the factory created the runtime with and
+/// returns whether the runtime is still healthy; the thread exits if not.
+type Job = Box<dyn FnOnce(&mut JsRuntime, &Permissions) -> bool + Send>;
+
+/// Configuration of a runtime pool
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    /// Runtimes kept alive (and created up front) even when idle
+    pub min_idle: usize,
+    /// Maximum number of runtimes, idle or in use
+    pub max_size: usize,
+    /// Time after which an idle runtime above `min_idle` is dropped in
+    /// milliseconds (0 = never)
+    pub idle_timeout_ms: u64,
+    /// Time to wait for a runtime when all are in use in milliseconds
+    /// (0 = wait indefinitely)
+    pub acquire_timeout_ms: u64,
+    /// Number of uses after which a runtime is recycled (0 = no limit)
+    pub max_uses: usize,
+    /// Used heap size in MB above which a runtime is recycled (0 = no limit)
+    pub max_heap_size: usize,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            min_idle: 1,
+            max_size: std::thread::available_parallelism().map_or(4, |n| n.get()),
+            idle_timeout_ms: 60_000,
+            acquire_timeout_ms: 0,
+            max_uses: 0,
+            max_heap_size: 0,
+        }
+    }
+}
+
+/// Runtime pool statistics
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// Runtimes alive (idle, in use or starting)
+    pub size: usize,
+    /// Runtimes waiting in the pool
+    pub idle: usize,
+    /// Runtimes checked out by callers
+    pub in_use: usize,
+    /// Callers waiting for a runtime
+    pub waiting: usize,
+    /// Runtimes created
+    pub created: usize,
+    /// Runtimes that failed to start
+    pub creation_failures: usize,
+    /// Runtimes dropped by the health check after use
+    pub recycled: usize,
+    /// Runtimes dropped after being idle for too long
+    pub evicted: usize,
+    /// Runtimes handed out to callers
+    pub checkouts: usize,
+    /// Callers that gave up waiting for a runtime
+    pub acquire_timeouts: usize,
+    /// Total time callers spent waiting for a runtime in milliseconds
+    pub total_wait_time_ms: u64,
+    /// Longest time a caller waited for a runtime in milliseconds
+    pub max_wait_time_ms: u64,
+}
+
+/// Handle of a pooled runtime running on its own thread
+struct PooledRuntime {
+    /// Work for the runtime's thread (the thread exits once dropped)
+    jobs: mpsc::Sender<Job>,
+    /// Number of calls the runtime has served
+    uses: usize,
+    /// When the runtime was last returned to the pool
+    idle_since: Instant,
+}
+
+/// Mutable state of a pool
+struct PoolState {
+    /// Idle runtimes, least recently used first
+    idle: VecDeque<PooledRuntime>,
+    /// Runtimes alive, including those being created
+    size: usize,
+    /// Runtimes checked out by callers
+    in_use: usize,
+    /// Set once the pool is dropped
+    closed: bool,
+    /// Statistics (the gauges are filled in by `JsRuntimePool::metrics`)
+    metrics: PoolMetrics,
+}
+
+/// State shared by a pool and its background threads
+struct Shared {
+    /// Pool configuration
+    config: PoolConfig,
+    /// Creates new runtimes
+    factory: Arc<RuntimeFactory>,
+    /// Mutable state
+    state: Mutex<PoolState>,
+    /// Signalled when a runtime is returned or a slot is freed
+    available: Condvar,
+}
+
+/// A pool of pre-warmed JavaScript runtimes
+///
+/// # Example
+///
+/// ```no_run
+/// # use ferrum::{JsRuntime, JsRuntimePool, Permissions, PoolConfig, RuntimeConfig};
+/// # ferrum::init_v8();
+/// let config = PoolConfig {
+///     max_size: 8,
+///     max_uses: 1000,
+///     ..PoolConfig::default()
+/// };
+/// let pool = JsRuntimePool::new(config, || {
+///     JsRuntime::new(RuntimeConfig::default(), Permissions::default())
+/// })
+/// .unwrap();
+///
+/// let result = pool.run(|runtime| runtime.execute("6 * 7", None)).unwrap();
+/// assert_eq!(result, "42");
+/// ```
+pub struct JsRuntimePool {
+    /// State shared with the background threads
+    shared: Arc<Shared>,
+    /// Wakes the idle eviction thread when dropped
+    reaper_shutdown: Option<mpsc::Sender<()>>,
+    /// Idle eviction thread (only when `idle_timeout_ms` is configured)
+    reaper: Option<JoinHandle<()>>,
+}
+
+impl JsRuntimePool {
+    /// Create a pool and start its first `min_idle` runtimes
+    ///
+    /// # Arguments
+    /// * `config` - Pool configuration
+    /// * `factory` - Creates a runtime; called on the thread the runtime
+    ///   will live on
+    ///
+    /// # Returns
+    /// The pool, or an error if the configuration is invalid or a runtime
+    /// could not be created
+    pub fn new<F>(config: PoolConfig, factory: F) -> RuntimeResult<Self>
+    where
+        F: Fn() -> RuntimeResult<JsRuntime> + Send + Sync + 'static,
+    {
+        if config.max_size == 0 {
+            return Err(RuntimeError::InitializationError("Pool max_size must be at least 1".to_string()));
+        }
+        if config.min_idle > config.max_size {
+            return Err(RuntimeError::InitializationError(format!(
+                "Pool min_idle ({}) exceeds max_size ({})",
+                config.min_idle, config.max_size
+            )));
+        }
+
+        let shared = Arc::new(Shared {
+            config,
+            factory: Arc::new(factory),
+            state: Mutex::new(PoolState {
+                idle: VecDeque::new(),
+                size: 0,
+                in_use: 0,
+                closed: false,
+                metrics: PoolMetrics::default(),
+            }),
+            available: Condvar::new(),
+        });
+
+        for _ in 0..shared.config.min_idle {
+            shared.lock().size += 1;
+            let runtime = shared.start_runtime()?;
+            shared.lock().idle.push_back(runtime);
+        }
+
+        let (reaper_shutdown, reaper) = match shared.config.idle_timeout_ms {
+            0 => (None, None),
+            timeout_ms => {
+                let (shutdown_tx, shutdown_rx) = mpsc::channel();
+                let weak = Arc::downgrade(&shared);
+                let interval = Duration::from_millis(timeout_ms.div_ceil(2));
+                let thread = std::thread::Builder::new()
+                    .name("ferrum-pool-reaper".to_string())
+                    .spawn(move || run_reaper(weak, shutdown_rx, interval))?;
+                (Some(shutdown_tx), Some(thread))
+            }
+        };
+
+        Ok(Self {
+            shared,
+            reaper_shutdown,
+            reaper,
+        })
+    }
+
+    /// Run a closure on a pooled runtime
+    ///
+    /// Blocks until a runtime is available and the closure has run on the
+    /// runtime's thread. The runtime is reset afterwards, so the closure
+    /// starts from fresh globals, op state and permissions every time. A panic in the closure is
+    /// resumed on the calling thread (and the runtime is discarded).
+    ///
+    /// # Arguments
+    /// * `f` - Closure receiving the runtime
+    ///
+    /// # Returns
+    /// The closure's result, or a `Timeout` if no runtime became available
+    /// within `acquire_timeout_ms`
+    pub fn run<F, R>(&self, f: F) -> RuntimeResult<R>
+    where
+        F: FnOnce(&mut JsRuntime) -> RuntimeResult<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let runtime = self.shared.acquire()?;
+
+        let (result_tx, result_rx) = mpsc::sync_channel(1);
+        let max_heap_bytes = self.shared.config.max_heap_size * 1024 * 1024;
+        let job: Job = Box::new(move |runtime: &mut JsRuntime, permissions: &Permissions| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| f(runtime)));
+            let healthy = result.is_ok() && check_health(runtime, permissions, max_heap_bytes);
+            let _ = result_tx.send((result, healthy));
+            healthy
+        });
+
+        // The send fails (and the receive below as well) if the thread exited
+        let _ = runtime.jobs.send(job);
+        match result_rx.recv() {
+            Ok((result, healthy)) => {
+                self.shared.release(runtime, healthy);
+                result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+            }
+            Err(_) => {
+                self.shared.release(runtime, false);
+                Err(RuntimeError::Unknown("Pooled runtime thread exited".to_string()))
+            }
+        }
+    }
+
+    /// Get the pool configuration
+    pub fn config(&self) -> &PoolConfig {
+        &self.shared.config
+    }
+
+    /// Get the pool statistics
+    pub fn metrics(&self) -> PoolMetrics {
+        let state = self.shared.lock();
+        PoolMetrics {
+            size: state.size,
+            idle: state.idle.len(),
+            in_use: state.in_use,
+            ..state.metrics.clone()
+        }
+    }
+
+    /// Drop the runtimes that have been idle for longer than `idle_timeout_ms`
+    ///
+    /// Runs periodically in the background; `min_idle` runtimes are kept.
+    pub fn evict_idle(&self) {
+        self.shared.evict_idle();
+    }
+}
+
+impl Drop for JsRuntimePool {
+    fn drop(&mut self) {
+        let idle = {
+            let mut state = self.shared.lock();
+            state.closed = true;
+            state.size -= state.idle.len();
+            std::mem::take(&mut state.idle)
+        };
+        self.shared.available.notify_all();
+        // Closing the job channels stops the idle runtimes' threads
+        drop(idle);
+
+        self.reaper_shutdown = None;
+        if let Some(reaper) = self.reaper.take() {
+            let _ = reaper.join();
+        }
+    }
+}
+
+impl Shared {
+    /// Lock the pool state
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Check out a runtime, creating or waiting for one if none is idle
+    fn acquire(self: &Arc<Self>) -> RuntimeResult<PooledRuntime> {
+        let started = Instant::now();
+        let deadline = match self.config.acquire_timeout_ms {
+            0 => None,
+            timeout_ms => Some(started + Duration::from_millis(timeout_ms)),
+        };
+
+        let mut state = self.lock();
+        loop {
+            if state.closed {
+                return Err(RuntimeError::Unknown("Runtime pool is closed".to_string()));
+            }
+
+            if let Some(runtime) = state.idle.pop_back() {
+                Self::record_checkout(&mut state, started);
+                return Ok(runtime);
+            }
+
+            if state.size < self.config.max_size {
+                state.size += 1;
+                drop(state);
+                let runtime = self.start_runtime()?;
+                Self::record_checkout(&mut self.lock(), started);
+                return Ok(runtime);
+            }
+
+            state.metrics.waiting += 1;
+            let timed_out = match deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    let (guard, result) = self.available.wait_timeout(state, timeout).unwrap();
+                    state = guard;
+                    result.timed_out()
+                }
+                None => {
+                    state = self.available.wait(state).unwrap();
+                    false
+                }
+            };
+            state.metrics.waiting -= 1;
+
+            if timed_out && state.idle.is_empty() && state.size >= self.config.max_size {
+                state.metrics.acquire_timeouts += 1;
+                return Err(RuntimeError::Timeout(format!(
+                    "No runtime available within {} ms",
+                    self.config.acquire_timeout_ms
+                )));
+            }
+        }
+    }
+
+    /// Update the statistics for a runtime handed out to a caller
+    fn record_checkout(state: &mut PoolState, started: Instant) {
+        let waited_ms = started.elapsed().as_millis() as u64;
+        state.in_use += 1;
+        state.metrics.checkouts += 1;
+        state.metrics.total_wait_time_ms += waited_ms;
+        state.metrics.max_wait_time_ms = state.metrics.max_wait_time_ms.max(waited_ms);
+    }
+
+    /// Return a runtime to the pool, or drop it if it is due for recycling
+    fn release(self: &Arc<Self>, mut runtime: PooledRuntime, healthy: bool) {
+        runtime.uses += 1;
+        let worn_out = self.config.max_uses > 0 && runtime.uses >= self.config.max_uses;
+
+        let mut state = self.lock();
+        state.in_use -= 1;
+        if healthy && !worn_out && !state.closed {
+            runtime.idle_since = Instant::now();
+            state.idle.push_back(runtime);
+        } else {
+            state.size -= 1;
+            if !state.closed {
+                state.metrics.recycled += 1;
+                tracing::debug!("Recycling pooled runtime after {} uses", runtime.uses);
+            }
+            self.replenish(&mut state);
+        }
+        drop(state);
+        self.available.notify_one();
+    }
+
+    /// Start runtimes in the background until `min_idle` are alive
+    fn replenish(self: &Arc<Self>, state: &mut PoolState) {
+        while !state.closed && state.size < self.config.min_idle {
+            state.size += 1;
+            let shared = self.clone();
+            let spawned = std::thread::Builder::new()
+                .name("ferrum-pool-spawn".to_string())
+                .spawn(move || {
+                    let Ok(runtime) = shared.start_runtime() else {
+                        return;
+                    };
+                    let mut state = shared.lock();
+                    if state.closed {
+                        state.size -= 1;
+                    } else {
+                        state.idle.push_back(runtime);
+                        drop(state);
+                        shared.available.notify_one();
+                    }
+                });
+            if let Err(e) = spawned {
+                state.size -= 1;
+                tracing::warn!("Failed to replenish runtime pool: {}", e);
+                return;
+            }
+        }
+    }
+
+    /// Start a runtime on a new thread and wait until it is ready
+    ///
+    /// The caller must have reserved a slot by incrementing `size`; it is
+    /// released again if the runtime cannot be created.
+    fn start_runtime(&self) -> RuntimeResult<PooledRuntime> {
+        let (jobs_tx, jobs_rx) = mpsc::channel::<Job>();
+        let (ready_tx, ready_rx) = mpsc::sync_channel(1);
+        let factory = self.factory.clone();
+
+        let spawned = std::thread::Builder::new()
+            .name("ferrum-pool-runtime".to_string())
+            .spawn(move || {
+                let mut runtime = match factory() {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let permissions = runtime.permissions().clone();
+                let _ = ready_tx.send(Ok(()));
+                for job in jobs_rx {
+                    if !job(&mut runtime, &permissions) {
+                        break;
+                    }
+                }
+            });
+
+        let result = match spawned {
+            Ok(_) => ready_rx
+                .recv()
+                .unwrap_or_else(|_| Err(RuntimeError::InitializationError("Runtime factory panicked".to_string()))),
+            Err(e) => Err(e.into()),
+        };
+
+        let mut state = self.lock();
+        match result {
+            Ok(()) => {
+                state.metrics.created += 1;
+                Ok(PooledRuntime {
+                    jobs: jobs_tx,
+                    uses: 0,
+                    idle_since: Instant::now(),
+                })
+            }
+            Err(e) => {
+                state.size -= 1;
+                state.metrics.creation_failures += 1;
+                drop(state);
+                self.available.notify_one();
+                tracing::warn!("Failed to create pooled runtime: {}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Drop idle runtimes past the idle timeout, keeping `min_idle` alive
+    fn evict_idle(&self) {
+        if self.config.idle_timeout_ms == 0 {
+            return;
+        }
+        let timeout = Duration::from_millis(self.config.idle_timeout_ms);
+
+        let mut evicted = Vec::new();
+        let mut state = self.lock();
+        while state.size > self.config.min_idle
+            && state.idle.front().is_some_and(|runtime| runtime.idle_since.elapsed() >= timeout)
+        {
+            evicted.extend(state.idle.pop_front());
+            state.size -= 1;
+            state.metrics.evicted += 1;
+        }
+        drop(state);
+
+        if !evicted.is_empty() {
+            tracing::debug!("Evicted {} idle pooled runtimes", evicted.len());
+            self.available.notify_all();
+        }
+    }
+}
+
+/// Reset a runtime after use and check that it can be reused
+///
+/// # Arguments
+/// * `runtime` - Runtime that just served a call
+/// * `permissions` - Permissions the factory created the runtime with
+/// * `max_heap_bytes` - Used heap size above which the runtime is
+///   recycled (0 = no limit)
+///
+/// # Returns
+/// `true` if the runtime can go back to the pool
+fn check_health(runtime: &mut JsRuntime, permissions: &Permissions, max_heap_bytes: usize) -> bool {
+    runtime.set_permissions(permissions.clone());
+    runtime.reset_op_state();
+    if let Err(e) = runtime.reset_context() {
+        tracing::warn!("Failed to reset pooled runtime: {}", e);
+        return false;
+    }
+    if max_heap_bytes == 0 || runtime.heap_stats().used_heap_size <= max_heap_bytes {
+        return true;
+    }
+    // Most of the heap may be garbage from the discarded realm
+    runtime.gc();
+    runtime.heap_stats().used_heap_size <= max_heap_bytes
+}
+
+/// Idle eviction thread entry point
+///
+/// Runs until the pool is dropped, which closes `shutdown`.
+fn run_reaper(shared: Weak<Shared>, shutdown: mpsc::Receiver<()>, interval: Duration) {
+    while let Err(mpsc::RecvTimeoutError::Timeout) = shutdown.recv_timeout(interval) {
+        match shared.upgrade() {
+            Some(shared) => shared.evict_idle(),
+            None => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::Extension;
+    use crate::ops::dispatch::OpRegistry;
+    use crate::ops::state::OpState;
+    use crate::runtime::{init_v8_platform, RuntimeConfig};
+
+    fn test_pool(config: PoolConfig) -> JsRuntimePool {
+        init_v8_platform();
+        JsRuntimePool::new(config, || JsRuntime::new(RuntimeConfig::default(), Permissions::default())).unwrap()
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config = PoolConfig {
+            min_idle: 2,
+            max_size: 1,
+            ..PoolConfig::default()
+        };
+        let result = JsRuntimePool::new(config, || Err(RuntimeError::Unknown("unused".to_string())));
+        assert!(matches!(result, Err(RuntimeError::InitializationError(_))));
+    }
+
+    #[test]
+    fn test_pool_resets_realm_between_uses() {
+        let pool = test_pool(PoolConfig {
+            min_idle: 1,
+            max_size: 1,
+            ..PoolConfig::default()
+        });
+        assert_eq!(pool.metrics().idle, 1);
+
+        pool.run(|runtime| runtime.execute("globalThis.leaked = 1", None)).unwrap();
+        let result = pool.run(|runtime| runtime.execute("typeof leaked", None)).unwrap();
+        assert_eq!(result, "undefined");
+
+        let metrics = pool.metrics();
+        assert_eq!(metrics.created, 1);
+        assert_eq!(metrics.checkouts, 2);
+        assert_eq!(metrics.in_use, 0);
+    }
+
+    #[test]
+    fn test_pool_resets_state_and_permissions() {
+        init_v8_platform();
+        let mut ops = OpRegistry::new();
+        ops.set_namespace("__counter");
+        ops.register_op_with_state("add", |state: &mut OpState, (n,): (u32,)| {
+            *state.get_mut::<u32>() += n;
+            Ok::<_, String>(*state.get::<u32>())
+        });
+        let counter = Extension::new("counter").ops(ops).state(|| 0u32);
+
+        let config = PoolConfig {
+            min_idle: 1,
+            max_size: 1,
+            ..PoolConfig::default()
+        };
+        let pool = JsRuntimePool::new(config, move || {
+            let config = RuntimeConfig {
+                extensions: vec![counter.clone()],
+                ..RuntimeConfig::default()
+            };
+            JsRuntime::new(config, Permissions::default())
+        })
+        .unwrap();
+
+        let denied = "try { Deno.readTextFileSync('/etc/passwd'); 'read' } catch (e) { 'denied' }";
+        let first = pool
+            .run(move |runtime| {
+                runtime.set_permissions(Permissions::allow_all());
+                runtime.execute(&format!("__counter.add(5) + ':' + ({})", denied), None)
+            })
+            .unwrap();
+        assert_eq!(first, "5:read");
+
+        let second = pool
+            .run(move |runtime| {
+                assert!(runtime.permissions().check_read("/etc/passwd").is_err());
+                runtime.execute(&format!("__counter.add(1) + ':' + ({})", denied), None)
+            })
+            .unwrap();
+        assert_eq!(second, "1:denied");
+        assert_eq!(pool.metrics().created, 1);
+    }
+
+    #[test]
+    fn test_pool_recycles_after_max_uses() {
+        let pool = test_pool(PoolConfig {
+            min_idle: 0,
+            max_size: 1,
+            max_uses: 2,
+            ..PoolConfig::default()
+        });
+
+        let ids: Vec<String> = (0..4)
+            .map(|_| pool.run(|runtime| Ok(runtime.id().to_string())).unwrap())
+            .collect();
+        assert_eq!(ids[0], ids[1]);
+        assert_ne!(ids[1], ids[2]);
+        assert_eq!(ids[2], ids[3]);
+
+        let metrics = pool.metrics();
+        assert_eq!(metrics.created, 2);
+        assert_eq!(metrics.recycled, 2);
+        assert_eq!(metrics.size, 0);
+    }
+
+    #[test]
+    fn test_pool_acquire_timeout() {
+        let pool = Arc::new(test_pool(PoolConfig {
+            min_idle: 1,
+            max_size: 1,
+            acquire_timeout_ms: 50,
+            ..PoolConfig::default()
+        }));
+
+        let (started_tx, started_rx) = mpsc::channel();
+        let busy = {
+            let pool = pool.clone();
+            std::thread::spawn(move || {
+                pool.run(move |_| {
+                    started_tx.send(()).unwrap();
+                    std::thread::sleep(Duration::from_millis(300));
+                    Ok(())
+                })
+            })
+        };
+        started_rx.recv().unwrap();
+
+        assert!(matches!(pool.run(|_| Ok(())), Err(RuntimeError::Timeout(_))));
+        busy.join().unwrap().unwrap();
+        assert_eq!(pool.metrics().acquire_timeouts, 1);
+    }
+
+    #[test]
+    fn test_pool_evicts_idle_runtimes() {
+        let pool = test_pool(PoolConfig {
+            min_idle: 0,
+            max_size: 2,
+            idle_timeout_ms: 10,
+            ..PoolConfig::default()
+        });
+        pool.run(|_| Ok(())).unwrap();
+        assert_eq!(pool.metrics().idle, 1);
+
+        std::thread::sleep(Duration::from_millis(20));
+        pool.evict_idle();
+        let metrics = pool.metrics();
+        assert_eq!(metrics.size, 0);
+        assert_eq!(metrics.evicted, 1);
+    }
+}
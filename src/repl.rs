@@ -203,6 +203,7 @@ impl Repl {
             }
             "reset" => {
                 self.input_buffer.clear();
+                self.runtime.reset_context()?;
                 println!("Context reset");
             }
             "permissions" => {
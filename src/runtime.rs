@@ -3,29 +3,51 @@
 //! This module provides the main runtime implementation using the V8 engine.
 //! It handles V8 initialization, isolate management, and JavaScript execution.
 
+use std::borrow::Cow;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ffi::c_void;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use std::time::Instant;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use thiserror::Error;
 
-use v8::{CreateParams, Module, OwnedIsolate, Platform, Script};
+use v8::script_compiler::{CompileOptions, NoCacheReason};
+use v8::{CreateParams, Module, OwnedIsolate, Platform};
 
+use crate::code_cache::CodeCache;
+use crate::error::JsError;
+use crate::event_loop::{EventLoop, OpCompletion};
+use crate::extension::{Extension, EXTENSION_SCHEME};
+use crate::heap_snapshot::HeapSnapshotSignal;
+use crate::inspector::{JsRuntimeInspector, LocalSession};
 use crate::module_loader::{ModuleLoader, ModuleLoaderConfig};
-use crate::ops::bindings::bootstrap_globals;
-use crate::ops::dispatch::OpRegistry;
+use crate::ops::bindings::{
+    bootstrap_globals, builtin_ops, dispatch_unhandled_rejection, external_references, install_registered_ops,
+    json_to_v8, v8_to_json,
+};
+use crate::ops::dispatch::{Op, OpRegistry};
+use crate::ops::state::OpState;
 use crate::permissions::Permissions;
+use crate::watchdog::Watchdog;
+use crate::worker::WorkerHost;
 
 /// Errors that can occur during runtime operations
 #[derive(Error, Debug)]
 pub enum RuntimeError {
     /// V8 JavaScript execution error
     #[error("V8 execution error: {0}")]
-    ExecutionError(String),
+    ExecutionError(Box<JsError>),
 
     /// Script compilation error
     #[error("Script compilation error: {0}")]
-    CompilationError(String),
+    CompilationError(Box<JsError>),
 
     /// Runtime initialization error
     #[error("Runtime initialization error: {0}")]
@@ -43,11 +65,63 @@ pub enum RuntimeError {
     #[error("Module error: {0}")]
     ModuleError(String),
 
+    /// JavaScript heap limit exceeded
+    #[error("Out of memory: {0}")]
+    OutOfMemory(String),
+
+    /// Promise rejected without a handler (and not handled by an
+    /// `unhandledrejection` listener)
+    #[error("Uncaught (in promise) {0}")]
+    UnhandledRejection(Box<JsError>),
+
+    /// Inspector or profiler error
+    #[error("Inspector error: {0}")]
+    InspectorError(String),
+
+    /// Value could not be converted between Rust and JavaScript
+    #[error("Value conversion error: {0}")]
+    ConversionError(String),
+
+    /// File system error
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+
     /// Unknown error occurred
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
 
+/// Arguments of [`JsRuntime::call_function`]
+///
+/// Implemented for tuples of up to eight serializable values.
+pub trait FunctionArgs {
+    /// Convert each element into a JSON value
+    fn into_values(self) -> serde_json::Result<Vec<serde_json::Value>>;
+}
+
+/// Implement [`FunctionArgs`] for a tuple of the given type parameters
+macro_rules! impl_function_args {
+    ($($arg:ident),*) => {
+        impl<$($arg: Serialize),*> FunctionArgs for ($($arg,)*) {
+            #[allow(non_snake_case)]
+            fn into_values(self) -> serde_json::Result<Vec<serde_json::Value>> {
+                let ($($arg,)*) = self;
+                Ok(vec![$(serde_json::to_value($arg)?),*])
+            }
+        }
+    };
+}
+
+impl_function_args!();
+impl_function_args!(A);
+impl_function_args!(A, B);
+impl_function_args!(A, B, C);
+impl_function_args!(A, B, C, D);
+impl_function_args!(A, B, C, D, E);
+impl_function_args!(A, B, C, D, E, F);
+impl_function_args!(A, B, C, D, E, F, G);
+impl_function_args!(A, B, C, D, E, F, G, H);
+
 /// Result type for runtime operations
 pub type RuntimeResult<T> = Result<T, RuntimeError>;
 
@@ -98,10 +172,20 @@ pub struct RuntimeConfig {
     pub timeout_ms: u64,
     /// Whether to enable inspector for debugging
     pub enable_inspector: bool,
+    /// Port the inspector listens on (0 = any free port)
+    pub inspector_port: u16,
+    /// Whether to pause on the first statement until a debugger attaches
+    pub inspect_brk: bool,
     /// Initial heap size in MB
     pub initial_heap_size: usize,
     /// Maximum heap size in MB (0 = no limit)
     pub max_heap_size: usize,
+    /// Directory for the V8 code cache of modules and scripts (`None` = disabled)
+    pub code_cache_dir: Option<PathBuf>,
+    /// Signal number that writes a heap snapshot to the current directory
+    pub heap_snapshot_signal: Option<i32>,
+    /// Extensions installed in the runtime, in order
+    pub extensions: Vec<Extension>,
 }
 
 impl Default for RuntimeConfig {
@@ -111,8 +195,13 @@ impl Default for RuntimeConfig {
             max_stack_size: 0,
             timeout_ms: 0,
             enable_inspector: false,
+            inspector_port: 9229,
+            inspect_brk: false,
             initial_heap_size: 8,
             max_heap_size: 0,
+            code_cache_dir: None,
+            heap_snapshot_signal: None,
+            extensions: Vec::new(),
         }
     }
 }
@@ -124,26 +213,83 @@ pub struct RuntimeStats {
     pub scripts_executed: usize,
     /// Total execution time in milliseconds
     pub total_execution_time_ms: u64,
+    /// Execution time of the most recent call in milliseconds
+    pub last_execution_time_ms: u64,
     /// Number of errors encountered
     pub error_count: usize,
-    /// Current memory usage in bytes
+    /// Used heap size in bytes after the most recent call
     pub memory_usage_bytes: usize,
 }
 
-/// Shared context passed to V8 callbacks via External
+/// V8 heap statistics
+///
+/// All sizes are in bytes. Per-space figures are not reported because the
+/// `v8` crate does not expose `Isolate::GetHeapSpaceStatistics`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HeapStats {
+    /// Bytes used by live and not yet collected objects
+    pub used_heap_size: usize,
+    /// Bytes committed for the heap
+    pub total_heap_size: usize,
+    /// Bytes committed for executable code
+    pub total_heap_size_executable: usize,
+    /// Bytes of physical memory backing the heap
+    pub total_physical_size: usize,
+    /// Bytes still available before reaching the heap limit
+    pub total_available_size: usize,
+    /// Maximum heap size
+    pub heap_size_limit: usize,
+    /// Bytes allocated outside the heap and reported to V8 (e.g. `ArrayBuffer`s)
+    pub external_memory: usize,
+    /// Bytes currently allocated through `malloc` by V8
+    pub malloced_memory: usize,
+    /// Peak of `malloced_memory`
+    pub peak_malloced_memory: usize,
+    /// Number of native contexts alive
+    pub number_of_native_contexts: usize,
+    /// Number of contexts that were detached but not yet collected
+    pub number_of_detached_contexts: usize,
+}
+
+impl HeapStats {
+    /// Read the heap statistics of an isolate
+    pub fn from_isolate(isolate: &mut v8::Isolate) -> Self {
+        let mut stats = v8::HeapStatistics::default();
+        isolate.get_heap_statistics(&mut stats);
+
+        Self {
+            used_heap_size: stats.used_heap_size(),
+            total_heap_size: stats.total_heap_size(),
+            total_heap_size_executable: stats.total_heap_size_executable(),
+            total_physical_size: stats.total_physical_size(),
+            total_available_size: stats.total_available_size(),
+            heap_size_limit: stats.heap_size_limit(),
+            external_memory: stats.external_memory(),
+            malloced_memory: stats.malloced_memory(),
+            peak_malloced_memory: stats.peak_malloced_memory(),
+            number_of_native_contexts: stats.number_of_native_contexts(),
+            number_of_detached_contexts: stats.number_of_detached_contexts(),
+        }
+    }
+}
+
+/// Shared context of a runtime, read by V8 callbacks
 ///
 /// This struct contains references to the runtime's state that
 /// ops need access to (permissions, registry, etc.).
 ///
 /// # Note
 ///
-/// The context is stored in Arc<Mutex<>> to allow safe sharing across
-/// V8 callbacks which may execute from different threads.
+/// The context is stored as `Arc<RuntimeContext>` in a slot of the
+/// runtime's isolate, so callbacks always see the state of the runtime
+/// they were called from, however many runtimes share a thread.
 pub struct RuntimeContext {
     /// Runtime permissions (checked by ops)
     pub permissions: Arc<Mutex<Permissions>>,
     /// Operation registry (for dispatching native ops)
     pub registry: Arc<Mutex<OpRegistry>>,
+    /// Extensions installed in every context (their ops are dispatched too)
+    pub extensions: Vec<Extension>,
 }
 
 impl RuntimeContext {
@@ -153,8 +299,184 @@ impl RuntimeContext {
         Self {
             permissions: Arc::new(Mutex::new(permissions)),
             registry: Arc::new(Mutex::new(registry)),
+            extensions: Vec::new(),
+        }
+    }
+
+    /// Set the extensions of the runtime
+    #[must_use]
+    pub fn with_extensions(mut self, extensions: Vec<Extension>) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Find an op of the registry, of an extension or a built-in op by its
+    /// qualified name
+    ///
+    /// # Arguments
+    /// * `qualified_name` - Path of the op from the global object (see
+    ///   [`OpRegistry::qualified_name`])
+    pub fn find_op(&self, qualified_name: &str) -> Option<Op> {
+        if let Some(op) = self.registry.lock().unwrap().get_qualified(qualified_name) {
+            return Some(op.clone());
+        }
+        self.extensions
+            .iter()
+            .find_map(|extension| extension.registry().get_qualified(qualified_name))
+            .or_else(|| builtin_ops().get_qualified(qualified_name))
+            .cloned()
+    }
+
+    /// Create the op state a runtime starts with
+    ///
+    /// Holds the shared permissions (read by the built-in typed ops)
+    /// followed by the state of the extensions.
+    pub(crate) fn initial_op_state(&self) -> OpState {
+        let mut op_state = OpState::new();
+        op_state.put(self.permissions.clone());
+        for extension in &self.extensions {
+            extension.init_state(&mut op_state);
+        }
+        op_state
+    }
+
+    /// Find the code of an extension module by its `ext:` specifier
+    pub fn find_module(&self, specifier: &str) -> Option<&str> {
+        self.extensions
+            .iter()
+            .find_map(|extension| extension.module_source(specifier))
+    }
+}
+
+/// State shared with the near-heap-limit callback
+///
+/// Passed to V8 as the callback data pointer, so it is boxed to keep its
+/// address stable for the lifetime of the isolate.
+struct HeapLimit {
+    /// Handle used to terminate execution from the callback
+    handle: v8::IsolateHandle,
+    /// Configured maximum heap size in bytes
+    max_bytes: usize,
+    /// Set once the limit was reached and execution was terminated
+    exceeded: AtomicBool,
+}
+
+/// Near-heap-limit callback for V8
+///
+/// Terminates the running JavaScript instead of letting V8 abort the
+/// process. The heap limit is temporarily raised so the termination can
+/// unwind; `JsRuntime::check_termination` restores it afterwards.
+extern "C" fn near_heap_limit_callback(
+    data: *mut c_void,
+    current_heap_limit: usize,
+    _initial_heap_limit: usize,
+) -> usize {
+    // SAFETY: `data` points to the `HeapLimit` owned by the `JsRuntime`,
+    // which outlives the isolate
+    let heap_limit = unsafe { &*(data as *const HeapLimit) };
+
+    if !heap_limit.exceeded.swap(true, Ordering::SeqCst) {
+        tracing::warn!("JavaScript heap limit reached, terminating execution");
+        heap_limit.handle.terminate_execution();
+    }
+
+    current_heap_limit * 2
+}
+
+/// Per-isolate ES module map
+///
+/// Holds every module compiled by the runtime, keyed by resolved specifier,
+/// together with the reverse mapping used to find the specifier of an
+/// importing module. It is stored in an isolate slot so that the V8 module
+/// resolve callback can link imports without access to the `JsRuntime`.
+pub(crate) struct ModuleMap {
+    /// Module loader used to resolve and load module sources
+    loader: Option<Rc<ModuleLoader>>,
+    /// Tokio runtime used to block on module loading
+    tokio_runtime: Rc<tokio::runtime::Runtime>,
+    /// Compiled modules by resolved specifier
+    modules: HashMap<String, v8::Global<Module>>,
+    /// Resolved specifiers by compiled module
+    specifiers: HashMap<v8::Global<Module>, String>,
+    /// Resolved specifier of the entry module (`import.meta.main`)
+    main: Option<String>,
+    /// Code cache consumed and produced when compiling modules
+    code_cache: Option<Rc<CodeCache>>,
+}
+
+impl ModuleMap {
+    /// Create an empty module map without a loader
+    fn new(tokio_runtime: Rc<tokio::runtime::Runtime>, code_cache: Option<Rc<CodeCache>>) -> Self {
+        Self {
+            loader: None,
+            tokio_runtime,
+            modules: HashMap::new(),
+            specifiers: HashMap::new(),
+            main: None,
+            code_cache,
         }
     }
+
+    /// Get the module map stored in the isolate
+    ///
+    /// # Panics
+    ///
+    /// Panics if the isolate was not created by `JsRuntime`.
+    fn from_isolate(isolate: &v8::Isolate) -> Rc<RefCell<ModuleMap>> {
+        isolate
+            .get_slot::<Rc<RefCell<ModuleMap>>>()
+            .expect("ModuleMap not installed in isolate")
+            .clone()
+    }
+
+    /// Get a compiled module by resolved specifier
+    fn get(&self, specifier: &str) -> Option<&v8::Global<Module>> {
+        self.modules.get(specifier)
+    }
+
+    /// Register a compiled module under its resolved specifier
+    fn insert(&mut self, specifier: String, module: v8::Global<Module>) {
+        self.specifiers.insert(module.clone(), specifier.clone());
+        self.modules.insert(specifier, module);
+    }
+
+    /// Get the resolved specifier of a compiled module
+    fn specifier_of(&self, module: &Module) -> Option<&str> {
+        self.specifiers.get(module).map(String::as_str)
+    }
+
+    /// Forget all compiled modules (the loader is kept)
+    fn clear(&mut self) {
+        self.modules.clear();
+        self.specifiers.clear();
+        self.main = None;
+    }
+}
+
+/// Convert a resolved module specifier to the URL exposed as `import.meta.url`
+///
+/// Remote specifiers are already URLs; local paths become `file://` URLs.
+fn specifier_to_url(specifier: &str) -> String {
+    if is_remote_specifier(specifier) || specifier.starts_with("file://") {
+        return specifier.to_string();
+    }
+    url::Url::from_file_path(specifier)
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| specifier.to_string())
+}
+
+/// Get the resolved specifier of the entry module executed in an isolate
+///
+/// Returns `None` if no module was executed yet.
+pub(crate) fn main_module(isolate: &v8::Isolate) -> Option<String> {
+    isolate
+        .get_slot::<Rc<RefCell<ModuleMap>>>()
+        .and_then(|module_map| module_map.borrow().main.clone())
+}
+
+/// Check if a resolved module specifier refers to a remote module
+fn is_remote_specifier(specifier: &str) -> bool {
+    specifier.starts_with("https://") || specifier.starts_with("http://")
 }
 
 /// Main JavaScript runtime
@@ -162,8 +484,31 @@ impl RuntimeContext {
 /// Each runtime instance has its own V8 isolate and context,
 /// providing complete isolation between instances.
 pub struct JsRuntime {
+    /// Inspector session of the running CPU profile
+    ///
+    /// Declared before `inspector` so it is dropped before the inspector.
+    cpu_profiler: Option<LocalSession>,
+    /// DevTools inspector (created on demand when `enable_inspector` is
+    /// not configured, without a DevTools server)
+    ///
+    /// Declared before `isolate` so it is dropped before the isolate.
+    inspector: Option<Box<JsRuntimeInspector>>,
+    /// Whether to wait for a debugger before the first execution
+    break_on_start: bool,
     /// V8 isolate (owns the JavaScript heap and manages execution)
     isolate: OwnedIsolate,
+    /// Heap limit state (only when `max_heap_size` is configured)
+    ///
+    /// Declared after `isolate` so it is dropped after the isolate.
+    heap_limit: Option<Box<HeapLimit>>,
+    /// Execution timeout watchdog (only when `timeout_ms` is configured)
+    watchdog: Option<Watchdog>,
+    /// Heap snapshot signal listener (only when `heap_snapshot_signal` is configured)
+    heap_snapshot_signal: Option<HeapSnapshotSignal>,
+    /// Persistent V8 context (realm) reused across execute calls
+    context: v8::Global<v8::Context>,
+    /// Whether the isolate was booted from a startup snapshot
+    from_snapshot: bool,
     /// Shared runtime context (passed to V8 callbacks)
     rt_context: Arc<RuntimeContext>,
     /// Runtime permissions
@@ -174,10 +519,12 @@ pub struct JsRuntime {
     stats: Rc<RefCell<RuntimeStats>>,
     /// Unique identifier for this runtime
     id: String,
-    /// Module loader for ES module support
-    module_loader: Option<ModuleLoader>,
-    /// V8 module cache (maps specifier to v8::Module)
-    module_cache: HashMap<String, v8::Global<Module>>,
+    /// ES module map (module loader and compiled modules)
+    module_map: Rc<RefCell<ModuleMap>>,
+    /// Tokio runtime used to poll pending ops in the event loop
+    tokio_runtime: Rc<tokio::runtime::Runtime>,
+    /// Code cache consumed and produced when compiling scripts
+    code_cache: Option<Rc<CodeCache>>,
 }
 
 impl JsRuntime {
@@ -190,31 +537,180 @@ impl JsRuntime {
     /// # Returns
     /// A new runtime instance or an error if initialization fails
     pub fn new(config: RuntimeConfig, permissions: Permissions) -> RuntimeResult<Self> {
+        Self::new_impl(config, permissions, None, OpRegistry::new())
+    }
+
+    /// Create a new JavaScript runtime instance with custom ops
+    ///
+    /// The registered ops are exposed to JavaScript on the registry's
+    /// namespace object (see [`OpRegistry::set_namespace`]).
+    ///
+    /// # Arguments
+    /// * `config` - Runtime configuration options
+    /// * `permissions` - Permission set for this runtime
+    /// * `ops` - Ops to expose to JavaScript
+    ///
+    /// # Returns
+    /// A new runtime instance or an error if initialization fails
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use ferrum::{JsRuntime, OpRegistry, Permissions, RuntimeConfig};
+    /// # ferrum::init_v8();
+    /// let mut ops = OpRegistry::new();
+    /// ops.set_namespace("app");
+    /// ops.register_op("double", |(n,): (i64,)| Ok::<_, String>(n * 2));
+    ///
+    /// let mut runtime = JsRuntime::with_ops(RuntimeConfig::default(), Permissions::default(), ops).unwrap();
+    /// assert_eq!(runtime.execute("app.double(21)", None).unwrap(), "42");
+    /// ```
+    pub fn with_ops(config: RuntimeConfig, permissions: Permissions, ops: OpRegistry) -> RuntimeResult<Self> {
+        Self::new_impl(config, permissions, None, ops)
+    }
+
+    /// Create a new JavaScript runtime instance from a startup snapshot
+    ///
+    /// The global APIs are deserialized from the snapshot instead of being
+    /// bootstrapped, which shortens startup.
+    ///
+    /// # Arguments
+    /// * `config` - Runtime configuration options
+    /// * `permissions` - Permission set for this runtime
+    /// * `snapshot` - Snapshot created by this binary (see [`crate::snapshot`])
+    ///
+    /// # Returns
+    /// A new runtime instance or an error if initialization fails
+    pub fn from_snapshot(
+        config: RuntimeConfig,
+        permissions: Permissions,
+        snapshot: impl Into<Cow<'static, [u8]>>,
+    ) -> RuntimeResult<Self> {
+        Self::new_impl(config, permissions, Some(snapshot.into()), OpRegistry::new())
+    }
+
+    /// Create a new JavaScript runtime instance (implementation)
+    fn new_impl(
+        config: RuntimeConfig,
+        permissions: Permissions,
+        snapshot: Option<Cow<'static, [u8]>>,
+        registry: OpRegistry,
+    ) -> RuntimeResult<Self> {
         // Create V8 isolate with configured parameters
-        let params = CreateParams::default();
+        let mut params = CreateParams::default();
+        let from_snapshot = snapshot.is_some();
+        if let Some(snapshot) = snapshot {
+            params = params
+                .snapshot_blob(snapshot)
+                .external_references(&**external_references());
+        }
+        if config.max_heap_size > 0 {
+            let max_bytes = config.max_heap_size * 1024 * 1024;
+            let initial_bytes = (config.initial_heap_size * 1024 * 1024).min(max_bytes);
+            params = params.heap_limits(initial_bytes, max_bytes);
+        }
 
         // Create isolate using v8 API
-        let isolate = v8::Isolate::new(params);
+        let mut isolate = v8::Isolate::new(params);
         let id = uuid::Uuid::new_v4().to_string();
 
+        // Terminate execution instead of aborting when the heap limit is hit
+        let heap_limit = (config.max_heap_size > 0).then(|| {
+            let heap_limit = Box::new(HeapLimit {
+                handle: isolate.thread_safe_handle(),
+                max_bytes: config.max_heap_size * 1024 * 1024,
+                exceeded: AtomicBool::new(false),
+            });
+            isolate.add_near_heap_limit_callback(
+                near_heap_limit_callback,
+                &*heap_limit as *const HeapLimit as *mut c_void,
+            );
+            heap_limit
+        });
+
+        // Terminate execution that runs longer than the configured timeout
+        let watchdog = (config.timeout_ms > 0).then(|| {
+            Watchdog::new(
+                isolate.thread_safe_handle(),
+                std::time::Duration::from_millis(config.timeout_ms),
+            )
+        });
+
+        // Write a heap snapshot whenever the configured signal arrives
+        let heap_snapshot_signal = config
+            .heap_snapshot_signal
+            .map(|signal| HeapSnapshotSignal::start(&mut isolate, signal))
+            .transpose()
+            .map_err(|e| RuntimeError::InitializationError(format!("Failed to listen for heap snapshot signal: {}", e)))?;
+
+        // Event loop state is reachable from V8 callbacks through an isolate slot
+        isolate.set_slot(Rc::new(RefCell::new(EventLoop::new())));
+
+        // Single-threaded tokio runtime that drives pending ops
+        let tokio_runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| RuntimeError::InitializationError(format!("Failed to create tokio runtime: {}", e)))?;
+        let tokio_runtime = Rc::new(tokio_runtime);
+
+        // Code cache shared by script and module compilation
+        let code_cache = config.code_cache_dir.clone().map(|dir| Rc::new(CodeCache::new(dir)));
+
+        // Module map is shared with the module resolve callback the same way
+        let module_map = Rc::new(RefCell::new(ModuleMap::new(tokio_runtime.clone(), code_cache.clone())));
+        isolate.set_slot(module_map.clone());
+        isolate.set_host_import_module_dynamically_callback(Self::dynamic_import_callback);
+        isolate.set_host_initialize_import_meta_object_callback(Self::import_meta_callback);
+        isolate.set_promise_reject_callback(Self::promise_reject_callback);
+
+        // Workers created from JavaScript are owned by the isolate as well
+        isolate.set_slot(Rc::new(RefCell::new(WorkerHost::new(&config, &registry))));
+
         tracing::debug!("Created new runtime instance: {}", id);
 
-        // Create operation registry for potential future use
-        let registry = OpRegistry::new();
+        // Create shared runtime context (ops dispatch through its registry)
+        let rt_context = Arc::new(
+            RuntimeContext::new(permissions.clone(), registry).with_extensions(config.extensions.clone()),
+        );
+
+        // Op state is shared through an isolate slot as well
+        isolate.set_slot(Rc::new(RefCell::new(rt_context.initial_op_state())));
 
-        // Create shared runtime context
-        // Note: We pass the registry to RuntimeContext for potential future use
-        let rt_context = Arc::new(RuntimeContext::new(permissions.clone(), registry));
+        // Create the realm all scripts and modules run in
+        let context = Self::create_context(&mut isolate, rt_context.clone(), from_snapshot)?;
+
+        // Debugging over the Chrome DevTools Protocol
+        let inspector = if config.enable_inspector {
+            let mut inspector = JsRuntimeInspector::new(&mut isolate, &format!("ferrum[{}]", std::process::id()));
+            let address = SocketAddr::from(([127, 0, 0, 1], config.inspector_port));
+            inspector.start_server(address).map_err(|e| {
+                RuntimeError::InitializationError(format!("Failed to start inspector on {}: {}", address, e))
+            })?;
+
+            let scope = &mut v8::HandleScope::new(&mut isolate);
+            inspector.context_created(v8::Local::new(scope, &context));
+            Some(inspector)
+        } else {
+            None
+        };
 
         Ok(Self {
+            cpu_profiler: None,
+            inspector,
+            break_on_start: config.inspect_brk,
             isolate,
+            heap_limit,
+            watchdog,
+            heap_snapshot_signal,
+            context,
+            from_snapshot,
             rt_context,
             permissions,
             config,
             stats: Rc::new(RefCell::new(RuntimeStats::default())),
             id,
-            module_loader: None,
-            module_cache: HashMap::new(),
+            module_map,
+            tokio_runtime,
+            code_cache,
         })
     }
 
@@ -232,34 +728,9 @@ impl JsRuntime {
         permissions: Permissions,
         module_config: ModuleLoaderConfig,
     ) -> RuntimeResult<Self> {
-        // Create V8 isolate with configured parameters
-        let params = CreateParams::default();
-
-        // Create isolate using v8 API
-        let isolate = v8::Isolate::new(params);
-        let id = uuid::Uuid::new_v4().to_string();
-
-        tracing::debug!("Created new runtime instance with module loader: {}", id);
-
-        // Create operation registry for potential future use
-        let registry = OpRegistry::new();
-
-        // Create shared runtime context
-        let rt_context = Arc::new(RuntimeContext::new(permissions.clone(), registry));
-
-        // Create module loader
-        let module_loader = ModuleLoader::new(permissions.clone(), module_config);
-
-        Ok(Self {
-            isolate,
-            rt_context,
-            permissions,
-            config,
-            stats: Rc::new(RefCell::new(RuntimeStats::default())),
-            id,
-            module_loader: Some(module_loader),
-            module_cache: HashMap::new(),
-        })
+        let mut runtime = Self::new(config, permissions)?;
+        runtime.setup_module_loader(module_config);
+        Ok(runtime)
     }
 
     /// Set up module loader for this runtime
@@ -269,14 +740,84 @@ impl JsRuntime {
     /// # Arguments
     /// * `module_config` - Module loader configuration
     pub fn setup_module_loader(&mut self, module_config: ModuleLoaderConfig) {
+        WorkerHost::from_isolate(&self.isolate)
+            .borrow_mut()
+            .set_module_config(module_config.clone());
         let module_loader = ModuleLoader::new(self.permissions.clone(), module_config);
-        self.module_loader = Some(module_loader);
+        self.module_map.borrow_mut().loader = Some(Rc::new(module_loader));
         tracing::debug!("Module loader set up for runtime: {}", self.id);
     }
 
     /// Check if module loader is available
     pub fn has_module_loader(&self) -> bool {
-        self.module_loader.is_some()
+        self.module_map.borrow().loader.is_some()
+    }
+
+    /// Create a new V8 context with the global APIs bootstrapped
+    ///
+    /// Isolates booted from a snapshot deserialize the globals with the
+    /// snapshot's default context, so only the runtime context is installed.
+    /// Ops registered by the embedder and extensions are installed in both
+    /// cases.
+    fn create_context(
+        isolate: &mut v8::Isolate,
+        rt_context: Arc<RuntimeContext>,
+        from_snapshot: bool,
+    ) -> RuntimeResult<v8::Global<v8::Context>> {
+        let scope = &mut v8::HandleScope::new(isolate);
+        let context = v8::Context::new(scope);
+        let scope = &mut v8::ContextScope::new(scope, context);
+
+        if from_snapshot {
+            scope.set_slot(rt_context.clone());
+        } else if let Err(e) = bootstrap_globals(scope, rt_context.clone()) {
+            // Bootstrap global APIs (console, Deno, etc.)
+            tracing::error!("Failed to bootstrap globals: {}", e);
+            return Err(RuntimeError::InitializationError(format!(
+                "Failed to bootstrap globals: {}",
+                e
+            )));
+        }
+
+        {
+            let registry = rt_context.registry.lock().unwrap();
+            install_registered_ops(scope, &registry).map_err(|e| {
+                RuntimeError::InitializationError(format!("Failed to register ops: {}", e))
+            })?;
+        }
+
+        for extension in &rt_context.extensions {
+            extension.install(scope).map_err(|e| {
+                RuntimeError::InitializationError(format!(
+                    "Failed to install extension '{}': {}",
+                    extension.name(),
+                    e
+                ))
+            })?;
+        }
+
+        Ok(v8::Global::new(scope, context))
+    }
+
+    /// Replace the realm with a clean one
+    ///
+    /// Globals defined by earlier scripts are discarded, pending timers and
+    /// ops are cancelled, and compiled modules are forgotten so they are
+    /// loaded and evaluated again in the new context.
+    pub fn reset_context(&mut self) -> RuntimeResult<()> {
+        // Cleared first: extension modules are evaluated in the new context
+        EventLoop::from_isolate(&self.isolate).borrow_mut().clear();
+        self.module_map.borrow_mut().clear();
+
+        let context = Self::create_context(&mut self.isolate, self.rt_context.clone(), self.from_snapshot)?;
+        if let Some(inspector) = &self.inspector {
+            let scope = &mut v8::HandleScope::new(&mut self.isolate);
+            inspector.context_destroyed(v8::Local::new(scope, &self.context));
+            inspector.context_created(v8::Local::new(scope, &context));
+        }
+        self.context = context;
+        tracing::debug!("Context reset for runtime: {}", self.id);
+        Ok(())
     }
 
     /// Get the runtime ID
@@ -294,11 +835,149 @@ impl JsRuntime {
         &mut self.permissions
     }
 
+    /// Replace the permissions
+    ///
+    /// Unlike changes made through [`JsRuntime::permissions_mut`], the new
+    /// permissions also apply to ops and to the module loader.
+    ///
+    /// # Arguments
+    /// * `permissions` - New permission set
+    pub fn set_permissions(&mut self, permissions: Permissions) {
+        *self.rt_context.permissions.lock().unwrap() = permissions.clone();
+        let mut module_map = self.module_map.borrow_mut();
+        if let Some(loader) = &module_map.loader {
+            let module_loader = ModuleLoader::new(permissions.clone(), loader.config().clone());
+            module_map.loader = Some(Rc::new(module_loader));
+        }
+        drop(module_map);
+        self.permissions = permissions;
+    }
+
+    /// Replace the op state with the state the runtime started with
+    ///
+    /// Values put into the [`OpState`] since the runtime was created are
+    /// dropped and the extensions' state initializers run again. Call it
+    /// before [`JsRuntime::reset_context`] so init hooks see the new state.
+    pub fn reset_op_state(&mut self) {
+        *OpState::from_isolate(&self.isolate).borrow_mut() = self.rt_context.initial_op_state();
+    }
+
     /// Get the runtime configuration
     pub fn config(&self) -> &RuntimeConfig {
         &self.config
     }
 
+    /// Get the DevTools inspector (only when `enable_inspector` is configured)
+    pub fn inspector(&self) -> Option<&JsRuntimeInspector> {
+        self.inspector.as_deref().filter(|inspector| inspector.address().is_some())
+    }
+
+    /// Run a closure with the runtime's context entered
+    ///
+    /// Gives embedders direct access to the V8 API, e.g. to move values
+    /// between runtimes with [`crate::structured_clone`]:
+    ///
+    /// ```no_run
+    /// # use ferrum::{structured_clone, JsRuntime, Permissions, RuntimeConfig};
+    /// # ferrum::init_v8();
+    /// let mut source = JsRuntime::new(RuntimeConfig::default(), Permissions::default()).unwrap();
+    /// let mut target = JsRuntime::new(RuntimeConfig::default(), Permissions::default()).unwrap();
+    ///
+    /// let value = source.with_context_scope(|scope| {
+    ///     let code = v8::String::new(scope, "new Map([[1, { one: 1 }]])").unwrap();
+    ///     let value = v8::Script::compile(scope, code, None)?.run(scope)?;
+    ///     structured_clone::serialize(scope, value, &[])
+    /// });
+    ///
+    /// target.with_context_scope(|scope| {
+    ///     let map = structured_clone::deserialize(scope, value.unwrap()).unwrap();
+    ///     assert!(map.is_map());
+    /// });
+    /// ```
+    ///
+    /// # Arguments
+    /// * `f` - Closure receiving a handle scope with the context entered
+    ///
+    /// # Returns
+    /// The closure's result
+    pub fn with_context_scope<R>(&mut self, f: impl FnOnce(&mut v8::HandleScope) -> R) -> R {
+        let scope = &mut v8::HandleScope::new(&mut self.isolate);
+        let context = v8::Local::new(scope, &self.context);
+        let scope = &mut v8::ContextScope::new(scope, context);
+        f(scope)
+    }
+
+    /// Get a handle that can terminate execution from another thread
+    pub(crate) fn thread_safe_handle(&self) -> v8::IsolateHandle {
+        self.isolate.thread_safe_handle()
+    }
+
+    /// Get the inspector, creating one without a DevTools server if needed
+    fn inspector_or_init(&mut self) -> &JsRuntimeInspector {
+        let inspector = self.inspector.get_or_insert_with(|| {
+            let inspector = JsRuntimeInspector::new(&mut self.isolate, &format!("ferrum[{}]", std::process::id()));
+            let scope = &mut v8::HandleScope::new(&mut self.isolate);
+            inspector.context_created(v8::Local::new(scope, &self.context));
+            inspector
+        });
+        inspector
+    }
+
+    /// Write a snapshot of the JavaScript heap
+    ///
+    /// The file uses the `.heapsnapshot` format loaded by Chrome DevTools.
+    ///
+    /// # Arguments
+    /// * `path` - Output file path
+    pub fn write_heap_snapshot(&mut self, path: impl AsRef<Path>) -> RuntimeResult<()> {
+        crate::heap_snapshot::write_heap_snapshot(&mut self.isolate, path.as_ref())?;
+        Ok(())
+    }
+
+    /// Start sampling the isolate with V8's CPU profiler
+    ///
+    /// # Arguments
+    /// * `sampling_interval_us` - Sampling interval in microseconds
+    pub fn start_cpu_profile(&mut self, sampling_interval_us: u32) -> RuntimeResult<()> {
+        if self.cpu_profiler.is_some() {
+            return Err(RuntimeError::InspectorError("CPU profile already started".to_string()));
+        }
+
+        let mut session = self.inspector_or_init().connect_local();
+        session
+            .post("Profiler.enable", serde_json::json!({}))
+            .and_then(|_| {
+                session.post(
+                    "Profiler.setSamplingInterval",
+                    serde_json::json!({ "interval": sampling_interval_us }),
+                )
+            })
+            .and_then(|_| session.post("Profiler.start", serde_json::json!({})))
+            .map_err(|e| RuntimeError::InspectorError(format!("Failed to start CPU profile: {}", e)))?;
+
+        self.cpu_profiler = Some(session);
+        tracing::debug!("CPU profile started for runtime: {}", self.id);
+        Ok(())
+    }
+
+    /// Stop the CPU profile started with [`JsRuntime::start_cpu_profile`]
+    ///
+    /// # Returns
+    /// The profile in the Chrome `.cpuprofile` JSON format
+    pub fn stop_cpu_profile(&mut self) -> RuntimeResult<String> {
+        let mut session = self
+            .cpu_profiler
+            .take()
+            .ok_or_else(|| RuntimeError::InspectorError("CPU profile not started".to_string()))?;
+
+        let mut result = session
+            .post("Profiler.stop", serde_json::json!({}))
+            .map_err(|e| RuntimeError::InspectorError(format!("Failed to stop CPU profile: {}", e)))?;
+        tracing::debug!("CPU profile stopped for runtime: {}", self.id);
+
+        Ok(result["profile"].take().to_string())
+    }
+
     /// Get execution statistics
     pub fn stats(&self) -> RuntimeStats {
         self.stats.borrow().clone()
@@ -306,176 +985,754 @@ impl JsRuntime {
 
     /// Execute JavaScript code
     ///
+    /// Code runs in the runtime's persistent context, so globals defined by
+    /// earlier calls stay visible until [`JsRuntime::reset_context`].
+    ///
+    /// # Arguments
+    /// * `code` - JavaScript source code to execute
+    /// * `filename` - Optional filename for error reporting
+    ///
+    /// # Returns
+    /// The result of the last expression evaluated
+    pub fn execute(&mut self, code: &str, filename: Option<&str>) -> RuntimeResult<String> {
+        self.execute_with_offset(code, filename, 0, 0)
+    }
+
+    /// Execute a fragment of JavaScript code taken from a larger file
+    ///
+    /// Stack traces and error locations are reported relative to the
+    /// original file, as if `code` started at the given position.
+    ///
     /// # Arguments
     /// * `code` - JavaScript source code to execute
     /// * `filename` - Optional filename for error reporting
+    /// * `line_offset` - Zero-based line in the file where `code` starts
+    /// * `column_offset` - Zero-based column in the file where `code` starts
     ///
     /// # Returns
     /// The result of the last expression evaluated
-    pub fn execute(&mut self, code: &str, _filename: Option<&str>) -> RuntimeResult<String> {
-        // Clone the runtime context before creating the scope
-        // This avoids borrow checker issues with the mutable borrow of self.isolate
-        let rt_context = self.rt_context.clone();
+    pub fn execute_with_offset(
+        &mut self,
+        code: &str,
+        filename: Option<&str>,
+        line_offset: i32,
+        column_offset: i32,
+    ) -> RuntimeResult<String> {
+        self.run_guarded(|runtime| {
+            let result = runtime.execute_impl(code, filename, line_offset, column_offset)?;
+            runtime.result_to_string(result)
+        })
+    }
 
+    /// Execute JavaScript code (implementation)
+    fn execute_impl(
+        &mut self,
+        code: &str,
+        filename: Option<&str>,
+        line_offset: i32,
+        column_offset: i32,
+    ) -> RuntimeResult<v8::Global<v8::Value>> {
         let scope = &mut v8::HandleScope::new(&mut self.isolate);
-        let context = v8::Context::new(scope);
+        let context = v8::Local::new(scope, &self.context);
         let scope = &mut v8::ContextScope::new(scope, context);
 
-        // Bootstrap global APIs (console, Deno, etc.)
-        // This must happen after context creation and before script execution
-        if let Err(e) = bootstrap_globals(scope, rt_context) {
-            tracing::error!("Failed to bootstrap globals: {}", e);
-            return Err(RuntimeError::InitializationError(format!(
-                "Failed to bootstrap globals: {}",
-                e
-            )));
-        }
-
         // Compile the script
         let source = v8::String::new(scope, code)
             .ok_or_else(|| RuntimeError::CompilationError("Failed to create source string".into()))?;
 
-        let script = Script::compile(scope, source, None)
-            .ok_or_else(|| RuntimeError::CompilationError("Script compilation failed".into()))?;
+        let origin = Self::script_origin(scope, filename, line_offset, column_offset, false);
 
-        // Run the script
-        let result = script.run(scope)
-            .ok_or_else(|| RuntimeError::ExecutionError("Script execution failed".into()))?;
+        // Only scripts loaded from a file are worth caching
+        let code_cache = self.code_cache.as_deref()
+            .zip(filename.filter(|name| !name.starts_with('<')));
+        let cached = code_cache.and_then(|(cache, name)| cache.get(name, code));
+        let (mut source, options) = Self::compiler_source(source, &origin, cached.as_deref());
 
-        // Convert result to string
-        let result_str = result.to_rust_string_lossy(scope);
+        let tc_scope = &mut v8::TryCatch::new(scope);
+
+        let Some(script) = v8::script_compiler::compile(tc_scope, &mut source, options, NoCacheReason::NoReason) else {
+            self.stats.borrow_mut().error_count += 1;
+            return Err(RuntimeError::CompilationError(Self::caught_error(tc_scope, "Script compilation failed").into()));
+        };
+
+        if let Some((cache, name)) = code_cache {
+            if Self::needs_code_cache(&source, cached.is_some(), name) {
+                if let Some(data) = script.get_unbound_script(tc_scope).create_code_cache() {
+                    cache.set(name, code, &data);
+                }
+            }
+        }
+
+        // Run the script
+        let Some(result) = script.run(tc_scope) else {
+            self.stats.borrow_mut().error_count += 1;
+            return Err(RuntimeError::ExecutionError(Self::caught_error(tc_scope, "Script execution failed").into()));
+        };
 
         // Update stats
         self.stats.borrow_mut().scripts_executed += 1;
 
-        Ok(result_str)
+        Ok(v8::Global::new(tc_scope, result))
     }
 
-    /// Execute a script from a file
+    /// Execute JavaScript code and run the event loop to completion
+    ///
+    /// This is the blocking counterpart of [`JsRuntime::execute`]: after the
+    /// script itself has run, all work it scheduled (promise continuations,
+    /// timers, async ops) is driven until nothing is left.
     ///
     /// # Arguments
-    /// * `path` - Path to the JavaScript file
+    /// * `code` - JavaScript source code to execute
+    /// * `filename` - Optional filename for error reporting
     ///
     /// # Returns
-    /// The result of the last expression evaluated
-    pub fn execute_file(&mut self, path: &str) -> RuntimeResult<String> {
-        // Check read permission
-        self.permissions
-            .check_read(path)
-            .map_err(|e| RuntimeError::PermissionDenied(e.to_string()))?;
-
-        // Read the file
-        let code = std::fs::read_to_string(path)
-            .map_err(|e| RuntimeError::ExecutionError(format!("Failed to read file: {}", e)))?;
-
-        self.execute(&code, Some(path))
+    /// The result of the last expression evaluated by the script itself
+    pub fn execute_and_wait(&mut self, code: &str, filename: Option<&str>) -> RuntimeResult<String> {
+        let result = self.execute(code, filename)?;
+        self.run_event_loop()?;
+        Ok(result)
     }
 
-    /// Execute an ES module from a file path
+    /// Run the event loop until no pending work remains
     ///
-    /// This method uses V8's Module API to compile and execute ES modules.
-    /// It handles module resolution, dependency loading, and instantiation.
+    /// Each turn performs a V8 microtask checkpoint, then waits on the tokio
+    /// runtime until at least one pending op has completed and runs its
+    /// completion back on the JavaScript thread.
+    ///
+    /// # Returns
+    /// `Ok(())` once the event loop is empty, or an error for the first
+    /// exception that escaped a callback
+    pub fn run_event_loop(&mut self) -> RuntimeResult<()> {
+        let started = Instant::now();
+        let result = self.drive_event_loop(None);
+        self.record_execution(started);
+        self.check_termination(result)
+    }
+
+    /// Run a call into JavaScript under the runtime's execution limits
+    ///
+    /// Dispatches pending inspector messages, arms the watchdog, records
+    /// the execution time and translates a termination into its error.
+    fn run_guarded<T>(&mut self, f: impl FnOnce(&mut Self) -> RuntimeResult<T>) -> RuntimeResult<T> {
+        self.prepare_inspector();
+        let started = Instant::now();
+        self.arm_watchdog();
+        let result = f(self);
+        self.record_execution(started);
+        self.check_termination(result)
+    }
+
+    /// Record the execution time and heap usage of a call
+    fn record_execution(&mut self, started: Instant) {
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+        let used_heap_size = HeapStats::from_isolate(&mut self.isolate).used_heap_size;
+
+        let mut stats = self.stats.borrow_mut();
+        stats.last_execution_time_ms = elapsed_ms;
+        stats.total_execution_time_ms += elapsed_ms;
+        stats.memory_usage_bytes = used_heap_size;
+    }
+
+    /// Dispatch queued inspector messages before running JavaScript
+    ///
+    /// With `inspect_brk`, the first execution waits for a debugger to
+    /// attach and pauses on its first statement.
+    fn prepare_inspector(&mut self) {
+        let Some(inspector) = &self.inspector else {
+            return;
+        };
+        inspector.poll_sessions();
+        if std::mem::take(&mut self.break_on_start) {
+            inspector.wait_for_session_and_break_on_next_statement();
+        }
+    }
+
+    /// Start the execution time budget (no-op without a timeout)
+    fn arm_watchdog(&self) {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.arm();
+        }
+    }
+
+    /// Stop the execution time budget (no-op without a timeout)
+    fn disarm_watchdog(&self) {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.disarm();
+        }
+    }
+
+    /// Report promises rejected without a handler during the last turn
+    ///
+    /// Each rejection that still has no handler is dispatched as a
+    /// cancelable `unhandledrejection` event on `globalThis`. The first one
+    /// no listener called `preventDefault()` on is returned as
+    /// `RuntimeError::UnhandledRejection`.
+    fn check_unhandled_rejections(&mut self) -> RuntimeResult<()> {
+        let event_loop = EventLoop::from_isolate(&self.isolate);
+
+        loop {
+            let rejections = event_loop.borrow_mut().take_unhandled_rejections();
+            if rejections.is_empty() {
+                return Ok(());
+            }
+
+            for (promise, reason) in rejections {
+                let scope = &mut v8::HandleScope::new(&mut self.isolate);
+                let context = v8::Local::new(scope, &self.context);
+                let scope = &mut v8::ContextScope::new(scope, context);
+                let promise = v8::Local::new(scope, promise);
+                let reason = v8::Local::new(scope, reason);
+
+                // A handler may have been attached later in the same turn
+                if promise.has_handler() {
+                    continue;
+                }
+
+                let tc_scope = &mut v8::TryCatch::new(scope);
+                match dispatch_unhandled_rejection(tc_scope, promise, reason) {
+                    Some(true) => {}
+                    Some(false) => {
+                        self.stats.borrow_mut().error_count += 1;
+                        return Err(RuntimeError::UnhandledRejection(
+                            JsError::from_v8_exception(tc_scope, reason).into(),
+                        ));
+                    }
+                    None => {
+                        self.stats.borrow_mut().error_count += 1;
+                        return Err(RuntimeError::ExecutionError(
+                            Self::caught_error(tc_scope, "unhandledrejection listener failed").into(),
+                        ));
+                    }
+                }
+            }
+
+            // Listeners may have queued microtasks that reject more promises
+            self.isolate.perform_microtask_checkpoint();
+        }
+    }
+
+    /// Check if the runtime terminated JavaScript execution
+    fn termination_requested(&self) -> bool {
+        let out_of_memory = self.heap_limit
+            .as_ref()
+            .is_some_and(|heap_limit| heap_limit.exceeded.load(Ordering::SeqCst));
+        let timed_out = self.watchdog
+            .as_ref()
+            .is_some_and(|watchdog| watchdog.has_fired());
+        out_of_memory || timed_out
+    }
+
+    /// Translate a result produced after execution was terminated
+    ///
+    /// Disarms the watchdog first. If execution was terminated because the
+    /// heap limit was reached or the timeout expired, the termination is
+    /// cancelled (and the configured heap limit restored) so the runtime
+    /// can be used again, and the result is replaced with
+    /// `RuntimeError::OutOfMemory` or `RuntimeError::Timeout`.
+    fn check_termination<T>(&mut self, result: RuntimeResult<T>) -> RuntimeResult<T> {
+        self.disarm_watchdog();
+
+        let timed_out = self.watchdog
+            .as_ref()
+            .is_some_and(|watchdog| watchdog.take_fired());
+        let out_of_memory = self.heap_limit
+            .as_ref()
+            .is_some_and(|heap_limit| heap_limit.exceeded.swap(false, Ordering::SeqCst));
+
+        if !timed_out && !out_of_memory {
+            return result;
+        }
+
+        self.isolate.cancel_terminate_execution();
+
+        if let (true, Some(heap_limit)) = (out_of_memory, &self.heap_limit) {
+            let data = &**heap_limit as *const HeapLimit as *mut c_void;
+            self.isolate
+                .remove_near_heap_limit_callback(near_heap_limit_callback, heap_limit.max_bytes);
+            self.isolate.add_near_heap_limit_callback(near_heap_limit_callback, data);
+
+            return Err(RuntimeError::OutOfMemory(format!(
+                "JavaScript heap limit of {} MB exceeded",
+                self.config.max_heap_size
+            )));
+        }
+
+        Err(RuntimeError::Timeout(format!(
+            "Execution exceeded {} ms",
+            self.config.timeout_ms
+        )))
+    }
+
+    /// Drive the event loop, optionally stopping early once a promise settles
     ///
     /// # Arguments
-    /// * `specifier` - Module specifier (file path or URL)
+    /// * `until` - Promise to wait for; `None` runs until no work remains
+    fn drive_event_loop(&mut self, until: Option<&v8::Global<v8::Promise>>) -> RuntimeResult<()> {
+        let event_loop = EventLoop::from_isolate(&self.isolate);
+
+        loop {
+            // Each turn (microtasks, then completions) gets its own time budget
+            self.arm_watchdog();
+            self.isolate.perform_microtask_checkpoint();
+
+            if self.termination_requested() {
+                self.stats.borrow_mut().error_count += 1;
+                return self.check_termination(Err(RuntimeError::ExecutionError("Execution terminated".into())));
+            }
+
+            self.check_unhandled_rejections()?;
+
+            if let Some(promise) = until {
+                let scope = &mut v8::HandleScope::new(&mut self.isolate);
+                let promise = v8::Local::new(scope, promise);
+                if promise.state() != v8::PromiseState::Pending {
+                    return Ok(());
+                }
+            }
+
+            if !event_loop.borrow().has_pending() {
+                return Ok(());
+            }
+
+            // Wait for at least one op or inspector message; JavaScript
+            // never runs inside block_on
+            self.disarm_watchdog();
+            let inspector = self.inspector.as_deref();
+            let heap_snapshot_signal = self.heap_snapshot_signal.as_ref();
+            let completions = self.tokio_runtime.block_on(std::future::poll_fn(|cx| {
+                if inspector.is_some_and(|inspector| inspector.poll_pending(cx))
+                    || heap_snapshot_signal.is_some_and(|signal| signal.poll_requested(cx))
+                {
+                    return Poll::Ready(Vec::new());
+                }
+                event_loop.borrow_mut().poll_ready(cx)
+            }));
+
+            if let Some(inspector) = inspector {
+                inspector.poll_sessions();
+            }
+            if let Some(signal) = &self.heap_snapshot_signal {
+                signal.write_requested(&mut self.isolate);
+            }
+
+            // Completions run JavaScript (timer callbacks, op results), so
+            // they are subject to the time budget as well
+            self.arm_watchdog();
+
+            for (context, completion) in completions {
+                let terminated = {
+                    let scope = &mut v8::HandleScope::new(&mut self.isolate);
+                    let context = v8::Local::new(scope, context);
+                    let scope = &mut v8::ContextScope::new(scope, context);
+                    let tc_scope = &mut v8::TryCatch::new(scope);
+
+                    completion(tc_scope);
+
+                    if !tc_scope.has_terminated() {
+                        if let Some(exception) = tc_scope.exception() {
+                            self.stats.borrow_mut().error_count += 1;
+                            return Err(RuntimeError::ExecutionError(
+                                JsError::from_v8_exception(tc_scope, exception).into(),
+                            ));
+                        }
+                    }
+                    tc_scope.has_terminated()
+                };
+
+                // Reports a timeout or the heap limit as such and cancels
+                // the termination
+                if terminated {
+                    self.stats.borrow_mut().error_count += 1;
+                    return self.check_termination(Err(RuntimeError::ExecutionError("Execution terminated".into())));
+                }
+            }
+        }
+    }
+
+    /// Evaluate JavaScript code and deserialize its result
+    ///
+    /// The result is converted as by `JSON.stringify` (`undefined` becomes
+    /// `null`) and then deserialized into `T`. If it is a promise, the
+    /// event loop is driven until it settles and its value is used.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ferrum::{JsRuntime, Permissions, RuntimeConfig};
+    /// # ferrum::init_v8();
+    /// let mut runtime = JsRuntime::new(RuntimeConfig::default(), Permissions::default()).unwrap();
+    /// let doubled: Vec<u32> = runtime.eval_as("[1, 2, 3].map((n) => n * 2)").unwrap();
+    /// assert_eq!(doubled, [2, 4, 6]);
+    /// ```
+    ///
+    /// # Arguments
+    /// * `code` - JavaScript source code to evaluate
     ///
     /// # Returns
-    /// The result of module evaluation
-    pub fn execute_module(&mut self, specifier: &str) -> RuntimeResult<String> {
-        // Check if module loader is available
-        let module_loader = self.module_loader.as_ref()
-            .ok_or_else(|| RuntimeError::ModuleError("Module loader not initialized".to_string()))?;
+    /// The deserialized result, or a `ConversionError` if it does not fit `T`
+    pub fn eval_as<T: DeserializeOwned>(&mut self, code: &str) -> RuntimeResult<T> {
+        let value = self.run_guarded(|runtime| {
+            let result = runtime.execute_impl(code, None, 0, 0)?;
+            let result = runtime.await_value(result, "Evaluation result promise never resolved")?;
+            runtime.try_in_context(|scope| {
+                let result = v8::Local::new(scope, result);
+                v8_to_json(scope, result)
+            })
+        })?;
+        Self::deserialize_value(value)
+    }
+
+    /// Get a property of the global object
+    ///
+    /// # Arguments
+    /// * `name` - Property name
+    ///
+    /// # Returns
+    /// The deserialized value (a missing property reads as `null`, so use
+    /// `Option<T>` for globals that may not be defined)
+    pub fn get_global<T: DeserializeOwned>(&mut self, name: &str) -> RuntimeResult<T> {
+        let value = self.run_guarded(|runtime| {
+            runtime.try_in_context(|scope| {
+                let global = scope.get_current_context().global(scope);
+                let key = v8::String::new(scope, name)?;
+                let value = global.get(scope, key.into())?;
+                v8_to_json(scope, value)
+            })
+        })?;
+        Self::deserialize_value(value)
+    }
 
-        // Clone the runtime context before creating the scope
-        let rt_context = self.rt_context.clone();
+    /// Set a property of the global object
+    ///
+    /// # Arguments
+    /// * `name` - Property name
+    /// * `value` - Value to store, converted through its JSON representation
+    pub fn set_global<T: Serialize + ?Sized>(&mut self, name: &str, value: &T) -> RuntimeResult<()> {
+        let value = serde_json::to_value(value).map_err(|e| RuntimeError::ConversionError(e.to_string()))?;
+        self.run_guarded(|runtime| {
+            runtime.try_in_context(|scope| {
+                let global = scope.get_current_context().global(scope);
+                let key = v8::String::new(scope, name)?;
+                let value = json_to_v8(scope, value);
+                global.set(scope, key.into(), value).map(|_| ())
+            })
+        })
+    }
 
-        // Get a mutable reference to the module cache
-        let module_cache = &mut self.module_cache;
+    /// Call a JavaScript function and deserialize its result
+    ///
+    /// The function is looked up by its path from the global object, e.g.
+    /// `"hooks.onRequest"`, and called with the object holding it as `this`.
+    /// Arguments are given as a tuple (`()` for none, `(value,)` for one),
+    /// each element becoming one argument, so a sequence in a tuple is
+    /// passed as an array. A returned promise is awaited by driving the
+    /// event loop, so async hooks work as well.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ferrum::{JsRuntime, Permissions, RuntimeConfig};
+    /// # use serde_json::{json, Value};
+    /// # ferrum::init_v8();
+    /// let mut runtime = JsRuntime::new(RuntimeConfig::default(), Permissions::default()).unwrap();
+    /// runtime.execute("globalThis.hooks = { onRequest: async (req) => ({ status: 200, path: req.path }) }", None).unwrap();
+    ///
+    /// let response: Value = runtime.call_function("hooks.onRequest", (json!({ "path": "/" }),)).unwrap();
+    /// assert_eq!(response, json!({ "status": 200, "path": "/" }));
+    /// ```
+    ///
+    /// # Arguments
+    /// * `name` - Path of the function from the global object
+    /// * `args` - Arguments, converted through their JSON representation
+    ///
+    /// # Returns
+    /// The deserialized result, or an `ExecutionError` if the function threw
+    /// (or its promise rejected)
+    pub fn call_function<A: FunctionArgs, R: DeserializeOwned>(&mut self, name: &str, args: A) -> RuntimeResult<R> {
+        let args = args.into_values().map_err(|e| RuntimeError::ConversionError(e.to_string()))?;
+
+        let value = self.run_guarded(|runtime| {
+            let result = runtime.try_in_context(|scope| {
+                let (receiver, function) = Self::resolve_function(scope, name)?;
+                let args: Vec<_> = args.into_iter().map(|arg| json_to_v8(scope, arg)).collect();
+                let result = function.call(scope, receiver, &args)?;
+                Some(v8::Global::new(scope, result))
+            })?;
+            let result = runtime.await_value(result, &format!("Promise returned by {} never resolved", name))?;
+            runtime.try_in_context(|scope| {
+                let result = v8::Local::new(scope, result);
+                v8_to_json(scope, result)
+            })
+        })?;
+        Self::deserialize_value(value)
+    }
 
+    /// Look up a function by its path from the global object
+    ///
+    /// # Returns
+    /// The object holding the function and the function itself, or `None`
+    /// if an exception (a `TypeError` if there is no such function) was
+    /// thrown
+    fn resolve_function<'s>(
+        scope: &mut v8::HandleScope<'s>,
+        path: &str,
+    ) -> Option<(v8::Local<'s, v8::Value>, v8::Local<'s, v8::Function>)> {
+        let mut value: v8::Local<v8::Value> = scope.get_current_context().global(scope).into();
+        let mut receiver = value;
+        for part in path.split('.') {
+            if value.is_null_or_undefined() {
+                break;
+            }
+            let object = value.to_object(scope)?;
+            let key = v8::String::new(scope, part)?;
+            receiver = value;
+            value = object.get(scope, key.into())?;
+        }
+
+        match v8::Local::<v8::Function>::try_from(value) {
+            Ok(function) => Some((receiver, function)),
+            Err(_) => {
+                let message = v8::String::new(scope, &format!("{} is not a function", path))?;
+                let exception = v8::Exception::type_error(scope, message);
+                scope.throw_exception(exception);
+                None
+            }
+        }
+    }
+
+    /// Run a closure in the context, catching exceptions
+    ///
+    /// # Returns
+    /// The closure's result, or an `ExecutionError` for the exception
+    /// caught when it returned `None`
+    fn try_in_context<R>(&mut self, f: impl FnOnce(&mut v8::TryCatch<v8::HandleScope>) -> Option<R>) -> RuntimeResult<R> {
         let scope = &mut v8::HandleScope::new(&mut self.isolate);
-        let context = v8::Context::new(scope);
+        let context = v8::Local::new(scope, &self.context);
         let scope = &mut v8::ContextScope::new(scope, context);
+        let tc_scope = &mut v8::TryCatch::new(scope);
 
-        // Bootstrap global APIs (console, Deno, etc.)
-        if let Err(e) = bootstrap_globals(scope, rt_context) {
-            tracing::error!("Failed to bootstrap globals: {}", e);
-            return Err(RuntimeError::InitializationError(format!(
-                "Failed to bootstrap globals: {}",
-                e
-            )));
+        match f(tc_scope) {
+            Some(result) => Ok(result),
+            None => {
+                self.stats.borrow_mut().error_count += 1;
+                Err(RuntimeError::ExecutionError(Self::caught_error(tc_scope, "Execution failed").into()))
+            }
         }
+    }
 
-        // Load and compile the module
-        let (module, _resolved_specifier) = Self::compile_module_impl(
-            scope,
-            module_loader,
-            module_cache,
-            specifier,
-            None,
-        )?;
+    /// Convert the result of a script or module to a string
+    ///
+    /// Runs JavaScript for objects (their `toString` method), so it must be
+    /// called under the execution limits.
+    ///
+    /// # Returns
+    /// The string, or an `ExecutionError` if the conversion threw
+    fn result_to_string(&mut self, result: v8::Global<v8::Value>) -> RuntimeResult<String> {
+        self.try_in_context(|scope| {
+            let result = v8::Local::new(scope, result);
+            let string = result.to_string(scope)?;
+            Some(string.to_rust_string_lossy(scope))
+        })
+    }
 
-        // Check for compilation errors
-        if module.get_status() == v8::ModuleStatus::Errored {
-            let exception = module.get_exception();
-            let error_msg = exception.to_rust_string_lossy(scope);
-            return Err(RuntimeError::CompilationError(format!("Module compilation error: {}", error_msg)));
-        }
-
-        // Instantiate the module (this resolves all dependencies)
-        // For simple modules without imports, this should succeed
-        // The callback will be called for each import, but we return null for all imports
-        // which means modules with imports will fail during instantiation
-        let instantiate_result = module.instantiate_module(scope, Self::module_resolve_callback);
-
-        // instantiate_module returns Option<bool>
-        // None means success (no imports), Some(true) means success with imports,
-        // Some(false) means failure
-        if instantiate_result == Some(false) {
-            // Check for instantiation errors
+    /// Deserialize a value converted from JavaScript
+    fn deserialize_value<T: DeserializeOwned>(value: serde_json::Value) -> RuntimeResult<T> {
+        serde_json::from_value(value).map_err(|e| RuntimeError::ConversionError(e.to_string()))
+    }
+
+    /// Execute a script from a file
+    ///
+    /// # Arguments
+    /// * `path` - Path to the JavaScript file
+    ///
+    /// # Returns
+    /// The result of the last expression evaluated
+    pub fn execute_file(&mut self, path: &str) -> RuntimeResult<String> {
+        // Check read permission
+        self.permissions
+            .check_read(path)
+            .map_err(|e| RuntimeError::PermissionDenied(e.to_string()))?;
+
+        // Read the file
+        let code = std::fs::read_to_string(path)?;
+
+        self.execute(&code, Some(path))
+    }
+
+    /// Execute an ES module from a file path
+    ///
+    /// This method uses V8's Module API to compile and execute ES modules.
+    /// It handles module resolution, dependency loading, and instantiation.
+    ///
+    /// Module evaluation yields a promise (top-level await). The event loop
+    /// is driven until that promise settles; work that is still pending
+    /// afterwards can be completed with [`JsRuntime::run_event_loop`].
+    ///
+    /// # Arguments
+    /// * `specifier` - Module specifier (file path or URL)
+    ///
+    /// # Returns
+    /// The value the evaluation promise was fulfilled with (normally
+    /// `undefined`), or an `ExecutionError` carrying the rejection reason
+    pub fn execute_module(&mut self, specifier: &str) -> RuntimeResult<String> {
+        self.run_guarded(|runtime| runtime.execute_module_impl(specifier))
+    }
+
+    /// Execute an ES module (implementation)
+    fn execute_module_impl(&mut self, specifier: &str) -> RuntimeResult<String> {
+        // Check if module loader is available
+        if !self.has_module_loader() {
+            return Err(RuntimeError::ModuleError("Module loader not initialized".to_string()));
+        }
+
+        let module_map = self.module_map.clone();
+
+        let result = {
+            let scope = &mut v8::HandleScope::new(&mut self.isolate);
+            let context = v8::Local::new(scope, &self.context);
+            let scope = &mut v8::ContextScope::new(scope, context);
+
+            // Load and compile the module together with its static imports
+            let module = Self::load_module_graph(scope, &mut module_map.borrow_mut(), specifier, None)?;
+
+            // Remember the entry module for `import.meta.main`
+            {
+                let mut module_map = module_map.borrow_mut();
+                module_map.main = module_map.specifier_of(&module).map(str::to_string);
+            }
+
+            // Check for compilation errors
             if module.get_status() == v8::ModuleStatus::Errored {
                 let exception = module.get_exception();
-                let error_msg = exception.to_rust_string_lossy(scope);
-                return Err(RuntimeError::ModuleError(format!("Module instantiation error: {}", error_msg)));
+                return Err(RuntimeError::CompilationError(JsError::from_v8_exception(scope, exception).into()));
             }
-            return Err(RuntimeError::ModuleError("Module instantiation failed - modules with imports are not yet supported".to_string()));
-        }
 
-        // Evaluate the module
-        let result = module.evaluate(scope);
-        let result = match result {
-            Some(r) => r,
-            None => {
-                // Check for evaluation errors
-                if module.get_status() == v8::ModuleStatus::Errored {
-                    let exception = module.get_exception();
-                    let error_msg = exception.to_rust_string_lossy(scope);
-                    return Err(RuntimeError::ExecutionError(format!("Module evaluation error: {}", error_msg)));
+            // Instantiate the module (links every import through the module map)
+            {
+                let tc_scope = &mut v8::TryCatch::new(scope);
+                if module.instantiate_module(tc_scope, Self::module_resolve_callback) != Some(true) {
+                    let error_msg = tc_scope
+                        .exception()
+                        .map(|exception| exception.to_rust_string_lossy(tc_scope))
+                        .unwrap_or_else(|| "unknown error".to_string());
+                    return Err(RuntimeError::ModuleError(format!("Module instantiation error: {}", error_msg)));
                 }
-                return Err(RuntimeError::ExecutionError("Module evaluation failed".to_string()));
             }
+
+            // Evaluate the module
+            let tc_scope = &mut v8::TryCatch::new(scope);
+            let Some(result) = module.evaluate(tc_scope) else {
+                self.stats.borrow_mut().error_count += 1;
+                return Err(RuntimeError::ExecutionError(Self::caught_error(tc_scope, "Module evaluation failed").into()));
+            };
+
+            v8::Global::new(tc_scope, result)
         };
 
-        // Check if the result is a Promise (top-level await)
-        // ES modules with async operations will return a Promise
-        if result.is_promise() {
-            // For now, we'll return the Promise object as a string
-            // In a production system, we'd want to wait for the promise to resolve
-            // and then return the actual result
-            let result_str = result.to_rust_string_lossy(scope);
+        // Module evaluation returns a Promise (top-level await). Drive the
+        // event loop until it settles so awaited work actually completes.
+        let result = self.await_value(result, "Module evaluation error: top-level await promise never resolved")?;
 
-            // Update stats
-            self.stats.borrow_mut().scripts_executed += 1;
+        // Convert result to string
+        let result_str = self.result_to_string(result)?;
 
-            Ok(result_str)
-        } else {
-            // Convert result to string
-            let result_str = result.to_rust_string_lossy(scope);
+        // Update stats
+        self.stats.borrow_mut().scripts_executed += 1;
+
+        Ok(result_str)
+    }
+
+    /// Wait for a value that may be a promise
+    ///
+    /// A promise is marked as handled (its rejection is reported as an
+    /// `ExecutionError`, not as unhandled) and the event loop is driven
+    /// until it settles. Other values are returned as-is.
+    ///
+    /// # Arguments
+    /// * `value` - Value to wait for
+    /// * `pending_error` - Error message if the promise can never settle
+    ///
+    /// # Returns
+    /// The value, or the value the promise was fulfilled with
+    fn await_value(&mut self, value: v8::Global<v8::Value>, pending_error: &str) -> RuntimeResult<v8::Global<v8::Value>> {
+        let promise = self.with_context_scope(|scope| {
+            let value = v8::Local::new(scope, &value);
+            let promise = v8::Local::<v8::Promise>::try_from(value).ok()?;
+            Self::mark_promise_handled(scope, promise);
+            Some(v8::Global::new(scope, promise))
+        });
+        let Some(promise) = promise else {
+            return Ok(value);
+        };
 
-            // Update stats
-            self.stats.borrow_mut().scripts_executed += 1;
+        self.drive_event_loop(Some(&promise))?;
 
-            Ok(result_str)
+        let scope = &mut v8::HandleScope::new(&mut self.isolate);
+        let context = v8::Local::new(scope, &self.context);
+        let scope = &mut v8::ContextScope::new(scope, context);
+
+        let promise = v8::Local::new(scope, promise);
+        match promise.state() {
+            v8::PromiseState::Fulfilled => {
+                let result = promise.result(scope);
+                Ok(v8::Global::new(scope, result))
+            }
+            v8::PromiseState::Rejected => {
+                let reason = promise.result(scope);
+                self.stats.borrow_mut().error_count += 1;
+                Err(RuntimeError::ExecutionError(JsError::from_v8_exception(scope, reason).into()))
+            }
+            v8::PromiseState::Pending => {
+                self.stats.borrow_mut().error_count += 1;
+                Err(RuntimeError::ExecutionError(pending_error.into()))
+            }
+        }
+    }
+
+    /// Compile a module and all of its static dependencies
+    ///
+    /// Walks the import graph, compiling every module that is not already in
+    /// the module map, so that instantiation only has to look modules up.
+    ///
+    /// # Arguments
+    /// * `scope` - V8 handle scope
+    /// * `module_map` - Module map for storing compiled modules
+    /// * `specifier` - Specifier of the root module
+    /// * `referrer` - Resolved specifier of the importing script or module
+    ///
+    /// # Returns
+    /// The compiled root module
+    fn load_module_graph<'s>(
+        scope: &mut v8::HandleScope<'s>,
+        module_map: &mut ModuleMap,
+        specifier: &str,
+        referrer: Option<&str>,
+    ) -> RuntimeResult<v8::Local<'s, Module>> {
+        let (root, root_specifier) = Self::compile_module_impl(scope, module_map, specifier, referrer)?;
+
+        let mut visited = HashSet::from([root_specifier.clone()]);
+        let mut queue = vec![(root, root_specifier)];
+
+        while let Some((module, referrer)) = queue.pop() {
+            let requests = module.get_module_requests();
+            for i in 0..requests.length() {
+                let Some(request) = requests
+                    .get(scope, i)
+                    .and_then(|data| v8::Local::<v8::ModuleRequest>::try_from(data).ok())
+                else {
+                    continue;
+                };
+                let dependency = request.get_specifier().to_rust_string_lossy(scope);
+
+                let (module, resolved) =
+                    Self::compile_module_impl(scope, module_map, &dependency, Some(&referrer))?;
+                if visited.insert(resolved.clone()) {
+                    queue.push((module, resolved));
+                }
+            }
         }
+
+        Ok(root)
     }
 
     /// Compile a V8 module from a specifier (implementation)
@@ -485,115 +1742,510 @@ impl JsRuntime {
     ///
     /// # Arguments
     /// * `scope` - V8 handle scope
-    /// * `module_loader` - Module loader instance
-    /// * `module_cache` - Module cache for storing compiled modules
+    /// * `module_map` - Module map for storing compiled modules
     /// * `specifier` - Module specifier
-    /// * `referrer` - Referrer module (for relative imports)
+    /// * `referrer` - Resolved specifier of the importing module (for relative imports)
     ///
     /// # Returns
     /// Compiled V8 module and its resolved specifier
     fn compile_module_impl<'s>(
-        scope: &mut v8::ContextScope<'s, v8::HandleScope>,
-        module_loader: &ModuleLoader,
-        module_cache: &mut HashMap<String, v8::Global<Module>>,
+        scope: &mut v8::HandleScope<'s>,
+        module_map: &mut ModuleMap,
         specifier: &str,
         referrer: Option<&str>,
     ) -> RuntimeResult<(v8::Local<'s, Module>, String)> {
-        // Resolve the specifier
-        let resolved_specifier = module_loader.resolve(specifier, referrer)
-            .map_err(|e| RuntimeError::ModuleError(format!("Failed to resolve '{}': {}", specifier, e)))?;
+        // Extension modules are resolved without the module loader
+        let (resolved_specifier, module_loader) = if specifier.starts_with(EXTENSION_SCHEME) {
+            (specifier.to_string(), None)
+        } else {
+            let module_loader = module_map.loader.clone()
+                .ok_or_else(|| RuntimeError::ModuleError("Module loader not initialized".to_string()))?;
+
+            // Resolve the specifier
+            let resolved_specifier = module_loader.resolve(specifier, referrer)
+                .map_err(|e| RuntimeError::ModuleError(format!("Failed to resolve '{}': {}", specifier, e)))?;
+            (resolved_specifier, Some(module_loader))
+        };
+
+        // Check if module is already cached
+        if let Some(cached_module) = module_map.get(&resolved_specifier) {
+            let local = v8::Local::new(scope, cached_module);
+            return Ok((local, resolved_specifier));
+        }
+
+        // Load the module source (block on async)
+        let code = match module_loader {
+            Some(module_loader) => module_map
+                .tokio_runtime
+                .block_on(module_loader.load_module(&resolved_specifier, referrer))
+                .map_err(|e| RuntimeError::ModuleError(format!("Failed to load module '{}': {}", specifier, e)))?
+                .source
+                .code,
+            None => scope
+                .get_slot::<Arc<RuntimeContext>>()
+                .and_then(|rt_context| rt_context.find_module(&resolved_specifier).map(str::to_string))
+                .ok_or_else(|| RuntimeError::ModuleError(format!("Unknown extension module '{}'", specifier)))?,
+        };
+
+        // Create V8 source string
+        let source_str = v8::String::new(scope, &code)
+            .ok_or_else(|| RuntimeError::CompilationError(
+                format!("Failed to create source string for '{}'", specifier).into()
+            ))?;
+
+        // Create ScriptOrigin for the module (resource name for error reporting)
+        let origin = Self::script_origin(scope, Some(&resolved_specifier), 0, 0, true);
+
+        // Create ScriptCompiler source, consuming cached code when available
+        let code = &code;
+        let code_cache = module_map.code_cache.clone();
+        let cached = code_cache.as_ref().and_then(|cache| cache.get(&resolved_specifier, code));
+        let (mut source, options) = Self::compiler_source(source_str, &origin, cached.as_deref());
+
+        // Compile the module using ScriptCompiler
+        let tc_scope = &mut v8::TryCatch::new(scope);
+        let Some(module) =
+            v8::script_compiler::compile_module2(tc_scope, &mut source, options, NoCacheReason::NoReason)
+        else {
+            let fallback = format!("Failed to compile module '{}'", specifier);
+            return Err(RuntimeError::CompilationError(Self::caught_error(tc_scope, &fallback).into()));
+        };
+        let scope = tc_scope;
+
+        if let Some(cache) = &code_cache {
+            if Self::needs_code_cache(&source, cached.is_some(), &resolved_specifier) {
+                if let Some(data) = module.get_unbound_module_script(scope).create_code_cache() {
+                    cache.set(&resolved_specifier, code, &data);
+                }
+            }
+        }
+
+        // Cache the compiled module (convert to Global for storage)
+        let global_module = v8::Global::new(scope, module);
+        module_map.insert(resolved_specifier.clone(), global_module);
+
+        Ok((module, resolved_specifier))
+    }
+
+    /// Evaluate an extension module in the entered context
+    ///
+    /// The module and its imports go through the module map, so a module
+    /// that was already imported (e.g. by an earlier extension module) is
+    /// not evaluated again.
+    ///
+    /// # Arguments
+    /// * `scope` - V8 handle scope with the context entered
+    /// * `specifier` - `ext:` specifier of the module
+    ///
+    /// # Returns
+    /// An error message if the module could not be loaded or threw
+    pub(crate) fn evaluate_extension_module(scope: &mut v8::HandleScope, specifier: &str) -> Result<(), String> {
+        let tc_scope = &mut v8::TryCatch::new(scope);
+
+        let module_map = ModuleMap::from_isolate(tc_scope);
+        let module = Self::load_module_graph(tc_scope, &mut module_map.borrow_mut(), specifier, None)
+            .map_err(|e| e.to_string())?;
+
+        if module.get_status() == v8::ModuleStatus::Uninstantiated
+            && module.instantiate_module(tc_scope, Self::module_resolve_callback) != Some(true)
+        {
+            return Err(Self::caught_error(tc_scope, "Module instantiation failed").to_string());
+        }
+
+        let Some(result) = module.evaluate(tc_scope) else {
+            return Err(Self::caught_error(tc_scope, "Module evaluation failed").to_string());
+        };
+        tc_scope.perform_microtask_checkpoint();
+
+        let Ok(promise) = v8::Local::<v8::Promise>::try_from(result) else {
+            return Ok(());
+        };
+        match promise.state() {
+            v8::PromiseState::Fulfilled => Ok(()),
+            v8::PromiseState::Rejected => {
+                let reason = promise.result(tc_scope);
+                Err(JsError::from_v8_exception(tc_scope, reason).to_string())
+            }
+            v8::PromiseState::Pending => Err("Top-level await is not supported in extension modules".to_string()),
+        }
+    }
+
+    /// Create a compiler source, attaching cached code when available
+    ///
+    /// `cached` must outlive the compilation: V8 reads it without copying.
+    fn compiler_source(
+        source: v8::Local<v8::String>,
+        origin: &v8::ScriptOrigin,
+        cached: Option<&[u8]>,
+    ) -> (v8::script_compiler::Source, CompileOptions) {
+        match cached {
+            Some(data) => (
+                v8::script_compiler::Source::new_with_cached_data(
+                    source,
+                    Some(origin),
+                    v8::script_compiler::CachedData::new(data),
+                ),
+                CompileOptions::ConsumeCodeCache,
+            ),
+            None => (
+                v8::script_compiler::Source::new(source, Some(origin)),
+                CompileOptions::NoCompileOptions,
+            ),
+        }
+    }
+
+    /// Check if a fresh code cache should be produced after compiling
+    ///
+    /// True when no cached code was passed in or V8 rejected it.
+    fn needs_code_cache(source: &v8::script_compiler::Source, consumed: bool, specifier: &str) -> bool {
+        let rejected = source.get_cached_data().is_some_and(|data| data.rejected());
+        if rejected {
+            tracing::debug!("Code cache rejected for {}", specifier);
+        }
+        !consumed || rejected
+    }
+
+    /// Create the script origin for a script or module
+    ///
+    /// # Arguments
+    /// * `scope` - V8 handle scope
+    /// * `resource_name` - File name or URL shown in stack traces (`None` for anonymous code)
+    /// * `line_offset` - Zero-based line in the resource where the source starts
+    /// * `column_offset` - Zero-based column in the resource where the source starts
+    /// * `is_module` - Whether the source is an ES module
+    pub(crate) fn script_origin<'s>(
+        scope: &mut v8::HandleScope<'s>,
+        resource_name: Option<&str>,
+        line_offset: i32,
+        column_offset: i32,
+        is_module: bool,
+    ) -> v8::ScriptOrigin<'s> {
+        let resource_name: v8::Local<v8::Value> = match resource_name.and_then(|name| v8::String::new(scope, name)) {
+            Some(name) => name.into(),
+            None => v8::undefined(scope).into(),
+        };
+        let source_map_url = v8::undefined(scope);
+
+        // new(scope, resource_name, resource_line_offset, resource_column_offset,
+        //     resource_is_shared_cross_origin, script_id, source_map_url,
+        //     is_opaque, is_wasm, is_module)
+        v8::ScriptOrigin::new(
+            scope,
+            resource_name,
+            line_offset,
+            column_offset,
+            false,
+            -1,
+            source_map_url.into(),
+            false,
+            false,
+            is_module,
+        )
+    }
+
+    /// Module resolution callback for V8
+    ///
+    /// This callback is invoked by V8 for each import while instantiating a
+    /// module. The specifier is resolved relative to the importing module and
+    /// the compiled module is taken from the module map (it is compiled on
+    /// demand if `load_module_graph` has not seen it yet).
+    ///
+    /// On failure an exception is thrown and `None` is returned, which makes
+    /// instantiation fail with that exception.
+    fn module_resolve_callback<'a>(
+        context: v8::Local<'a, v8::Context>,
+        specifier: v8::Local<'a, v8::String>,
+        _import_attributes: v8::Local<'a, v8::FixedArray>,
+        referrer: v8::Local<'a, Module>,
+    ) -> Option<v8::Local<'a, Module>> {
+        // SAFETY: V8 invokes this callback with a live context on the current thread
+        let scope = &mut unsafe { v8::CallbackScope::new(context) };
+        let specifier = specifier.to_rust_string_lossy(scope);
+
+        let module_map = ModuleMap::from_isolate(scope);
+        let mut module_map = module_map.borrow_mut();
+        let referrer = module_map.specifier_of(&referrer).map(str::to_string);
+
+        match Self::compile_module_impl(scope, &mut module_map, &specifier, referrer.as_deref()) {
+            Ok((module, _)) => Some(module),
+            Err(e) => {
+                let exception = Self::error_to_exception(scope, &e);
+                scope.throw_exception(exception);
+                None
+            }
+        }
+    }
+
+    /// Dynamic `import()` callback for V8
+    ///
+    /// Returns a promise right away and schedules the actual import on the
+    /// event loop, where the module is loaded, linked and evaluated by
+    /// `import_module_dynamically`. The promise settles with the module
+    /// namespace or with the error that occurred.
+    fn dynamic_import_callback<'s>(
+        scope: &mut v8::HandleScope<'s>,
+        _host_defined_options: v8::Local<'s, v8::Data>,
+        resource_name: v8::Local<'s, v8::Value>,
+        specifier: v8::Local<'s, v8::String>,
+        _import_attributes: v8::Local<'s, v8::FixedArray>,
+    ) -> Option<v8::Local<'s, v8::Promise>> {
+        let resolver = v8::PromiseResolver::new(scope)?;
+        let promise = resolver.get_promise(scope);
+
+        let specifier = specifier.to_rust_string_lossy(scope);
+        // Scripts without a file name have no referrer; their imports are
+        // resolved relative to the loader's base directory
+        let referrer = resource_name
+            .is_string()
+            .then(|| resource_name.to_rust_string_lossy(scope));
+
+        let context = scope.get_current_context();
+        let context = v8::Global::new(scope, context);
+        let resolver = v8::Global::new(scope, resolver);
+
+        let completion: OpCompletion = Box::new(move |scope| {
+            let resolver = v8::Local::new(scope, &resolver);
+            match Self::import_module_dynamically(scope, &specifier, referrer.as_deref()) {
+                Ok(value) => resolver.resolve(scope, value),
+                Err(exception) => resolver.reject(scope, exception),
+            };
+        });
+
+        let event_loop = EventLoop::from_isolate(scope);
+        let mut event_loop = event_loop.borrow_mut();
+        let id = event_loop.next_op_id();
+        event_loop.schedule(id, context, Box::pin(async move { completion }));
+
+        Some(promise)
+    }
+
+    /// Load, link and evaluate a dynamically imported module
+    ///
+    /// # Arguments
+    /// * `scope` - V8 handle scope with the importing context entered
+    /// * `specifier` - Module specifier passed to `import()`
+    /// * `referrer` - Resolved specifier of the importing script or module
+    ///
+    /// # Returns
+    /// A value resolving to the module namespace once evaluation (including
+    /// top-level await) finishes, or the exception to reject `import()` with
+    fn import_module_dynamically<'s>(
+        scope: &mut v8::HandleScope<'s>,
+        specifier: &str,
+        referrer: Option<&str>,
+    ) -> Result<v8::Local<'s, v8::Value>, v8::Local<'s, v8::Value>> {
+        let tc_scope = &mut v8::TryCatch::new(scope);
+
+        let module_map = ModuleMap::from_isolate(tc_scope);
+        let module = Self::load_module_graph(tc_scope, &mut module_map.borrow_mut(), specifier, referrer)
+            .map_err(|e| Self::error_to_exception(tc_scope, &e))?;
+
+        if module.get_status() == v8::ModuleStatus::Errored {
+            return Err(v8::Local::new(tc_scope, module.get_exception()));
+        }
 
-        // Check if module is already cached
-        if let Some(cached_module) = module_cache.get(&resolved_specifier) {
-            let local = v8::Local::new(scope, cached_module);
-            return Ok((local, resolved_specifier));
+        if module.get_status() == v8::ModuleStatus::Uninstantiated
+            && module.instantiate_module(tc_scope, Self::module_resolve_callback) != Some(true)
+        {
+            return Err(tc_scope
+                .exception()
+                .unwrap_or_else(|| v8::undefined(tc_scope).into()));
         }
 
-        // Load the module source (block on async)
-        let resolved_module = tokio::runtime::Runtime::new()
-            .map_err(|e| RuntimeError::InitializationError(format!("Failed to create tokio runtime: {}", e)))?
-            .block_on(module_loader.load_module(&resolved_specifier, referrer))
-            .map_err(|e| RuntimeError::ModuleError(format!("Failed to load module '{}': {}", specifier, e)))?;
+        let Some(result) = module.evaluate(tc_scope) else {
+            return Err(tc_scope
+                .exception()
+                .unwrap_or_else(|| v8::undefined(tc_scope).into()));
+        };
 
-        // Create V8 source string
-        let source_str = v8::String::new(scope, &resolved_module.source.code)
-            .ok_or_else(|| RuntimeError::CompilationError(
-                format!("Failed to create source string for '{}'", specifier)
-            ))?;
+        // Evaluation yields a promise (top-level await); settle with the
+        // namespace only after it fulfills
+        let namespace = v8::Local::new(tc_scope, module.get_module_namespace());
+        let Ok(promise) = v8::Local::<v8::Promise>::try_from(result) else {
+            return Ok(namespace);
+        };
+        v8::Function::builder(Self::return_callback_data)
+            .data(namespace)
+            .build(tc_scope)
+            .and_then(|on_fulfilled| promise.then(tc_scope, on_fulfilled))
+            .map(Into::into)
+            .ok_or_else(|| {
+                tc_scope
+                    .exception()
+                    .unwrap_or_else(|| v8::undefined(tc_scope).into())
+            })
+    }
 
-        // Create resource name for error reporting
-        let resource_name = v8::String::new(scope, &resolved_specifier)
-            .ok_or_else(|| RuntimeError::CompilationError(
-                format!("Failed to create resource name for '{}'", specifier)
-            ))?;
+    /// Function callback that returns the data it was created with
+    fn return_callback_data(
+        _scope: &mut v8::HandleScope,
+        args: v8::FunctionCallbackArguments,
+        mut rv: v8::ReturnValue,
+    ) {
+        rv.set(args.data());
+    }
 
-        // Create undefined value before creating ScriptOrigin to avoid borrow issues
-        let undefined_value = v8::undefined(scope);
+    /// `import.meta` initialization callback for V8
+    ///
+    /// Called the first time a module accesses `import.meta`. Fills in:
+    /// - `url` - URL of the module (`file://` for local files)
+    /// - `main` - Whether the module is the entry module
+    /// - `resolve(specifier)` - Resolve a specifier relative to the module
+    /// - `filename` / `dirname` - Path and directory of local modules
+    extern "C" fn import_meta_callback(
+        context: v8::Local<v8::Context>,
+        module: v8::Local<Module>,
+        meta: v8::Local<v8::Object>,
+    ) {
+        // SAFETY: V8 invokes this callback with a live context on the current thread
+        let scope = &mut unsafe { v8::CallbackScope::new(context) };
+
+        let (specifier, is_main) = {
+            let module_map = ModuleMap::from_isolate(scope);
+            let module_map = module_map.borrow();
+            let Some(specifier) = module_map.specifier_of(&module) else {
+                return;
+            };
+            (specifier.to_string(), module_map.main.as_deref() == Some(specifier))
+        };
 
-        // Create ScriptOrigin for the module
-        // The ScriptOrigin constructor has a specific signature:
-        // new(scope, resource_name, resource_line_offset, resource_column_offset,
-        //     resource_is_shared_cross_origin, script_id, source_map_url,
-        //     is_opaque, is_wam, is_module)
-        let origin = v8::ScriptOrigin::new(
-            scope,
-            resource_name.into(),
-            0,
-            0,
-            false,
-            -1,
-            undefined_value.into(),
-            false,
-            false,
-            true,
-        );
+        let set = |scope: &mut v8::HandleScope, name: &str, value: v8::Local<v8::Value>| {
+            if let Some(key) = v8::String::new(scope, name) {
+                meta.create_data_property(scope, key.into(), value);
+            }
+        };
 
-        // Create ScriptCompiler source
-        let source = v8::script_compiler::Source::new(source_str, Some(&origin));
+        if let Some(url) = v8::String::new(scope, &specifier_to_url(&specifier)) {
+            set(scope, "url", url.into());
+        }
 
-        // Compile the module using ScriptCompiler
-        let module = v8::script_compiler::compile_module(scope, source)
-            .ok_or_else(|| RuntimeError::CompilationError(
-                format!("Failed to compile module '{}'", specifier)
-            ))?;
+        let main = v8::Boolean::new(scope, is_main);
+        set(scope, "main", main.into());
 
-        // Cache the compiled module (convert to Global for storage)
-        let global_module = v8::Global::new(scope, module);
-        module_cache.insert(resolved_specifier.clone(), global_module);
+        if let Some(referrer) = v8::String::new(scope, &specifier) {
+            if let Some(resolve) = v8::Function::builder(Self::import_meta_resolve)
+                .data(referrer.into())
+                .build(scope)
+            {
+                set(scope, "resolve", resolve.into());
+            }
+        }
 
-        Ok((module, resolved_specifier))
+        if !is_remote_specifier(&specifier) && !specifier.starts_with(EXTENSION_SCHEME) {
+            let path = std::path::Path::new(&specifier);
+            if let Some(filename) = v8::String::new(scope, &specifier) {
+                set(scope, "filename", filename.into());
+            }
+            if let Some(dirname) = path
+                .parent()
+                .and_then(|dir| v8::String::new(scope, &dir.to_string_lossy()))
+            {
+                set(scope, "dirname", dirname.into());
+            }
+        }
     }
 
-    /// Module resolution callback for V8
+    /// Attach a no-op rejection handler so a rejection is not reported as unhandled
+    fn mark_promise_handled(scope: &mut v8::HandleScope, promise: v8::Local<v8::Promise>) {
+        let ignore = |_: &mut v8::HandleScope, _: v8::FunctionCallbackArguments, _: v8::ReturnValue| {};
+        if let Some(ignore) = v8::Function::new(scope, ignore) {
+            promise.catch(scope, ignore);
+        }
+    }
+
+    /// Promise reject callback tracking rejections without a handler
     ///
-    /// This callback is invoked by V8 when it needs to resolve a module import.
+    /// Rejections are only recorded here; they are reported once the
+    /// current turn of the event loop has run its microtasks, so handlers
+    /// attached in the meantime still count.
+    extern "C" fn promise_reject_callback(message: v8::PromiseRejectMessage) {
+        // SAFETY: V8 invokes this callback on the isolate's thread
+        let scope = &mut unsafe { v8::CallbackScope::new(&message) };
+        let event_loop = EventLoop::from_isolate(scope);
+        let promise = v8::Global::new(scope, message.get_promise());
+
+        match message.get_event() {
+            v8::PromiseRejectEvent::PromiseRejectWithNoHandler => {
+                let reason = message
+                    .get_value()
+                    .unwrap_or_else(|| v8::undefined(scope).into());
+                let reason = v8::Global::new(scope, reason);
+                event_loop.borrow_mut().add_unhandled_rejection(promise, reason);
+            }
+            v8::PromiseRejectEvent::PromiseHandlerAddedAfterReject => {
+                event_loop.borrow_mut().remove_unhandled_rejection(&promise);
+            }
+            _ => {}
+        }
+    }
+
+    /// `import.meta.resolve(specifier)` implementation
     ///
-    /// # Note
-    /// This is a simplified implementation that always returns null.
-    /// A production implementation would:
-    /// 1. Get the isolate from the context
-    /// 2. Convert the specifier to a Rust string
-    /// 3. Look up the module in our cache
-    /// 4. Return the module if found
-    /// 5. Otherwise, compile and cache it
+    /// The function data holds the resolved specifier of the module the
+    /// `import.meta` object belongs to.
+    fn import_meta_resolve(
+        scope: &mut v8::HandleScope,
+        args: v8::FunctionCallbackArguments,
+        mut rv: v8::ReturnValue,
+    ) {
+        let referrer = args.data().to_rust_string_lossy(scope);
+        let specifier = args.get(0).to_rust_string_lossy(scope);
+
+        let module_loader = ModuleMap::from_isolate(scope).borrow().loader.clone();
+        let resolved = match module_loader {
+            Some(module_loader) => module_loader
+                .resolve(&specifier, Some(&referrer))
+                .map_err(|e| e.to_string()),
+            None => Err("Module loader not initialized".to_string()),
+        };
+
+        match resolved {
+            Ok(resolved) => {
+                if let Some(url) = v8::String::new(scope, &specifier_to_url(&resolved)) {
+                    rv.set(url.into());
+                }
+            }
+            Err(e) => {
+                let message = format!("Failed to resolve '{}': {}", specifier, e);
+                let message = v8::String::new(scope, &message).unwrap_or_else(|| v8::String::empty(scope));
+                let exception = v8::Exception::type_error(scope, message);
+                scope.throw_exception(exception);
+            }
+        }
+    }
+
+    /// Capture the exception caught by a `TryCatch` scope
     ///
-    /// For now, modules with imports will fail during instantiation.
-    fn module_resolve_callback<'a>(
-        _context: v8::Local<'a, v8::Context>,
-        _specifier: v8::Local<'a, v8::String>,
-        _import_attributes: v8::Local<'a, v8::FixedArray>,
-        _module_request: v8::Local<'a, Module>,
-    ) -> Option<v8::Local<'a, Module>> {
-        // Return None to indicate module not found
-        // This means modules with imports will fail during instantiation
-        None
+    /// Falls back to `fallback` if nothing was caught, and reports
+    /// terminated execution as such.
+    fn caught_error(tc_scope: &mut v8::TryCatch<v8::HandleScope>, fallback: &str) -> JsError {
+        if tc_scope.has_terminated() {
+            return JsError::from("Execution terminated");
+        }
+        match tc_scope.exception() {
+            Some(exception) => JsError::from_v8_exception(tc_scope, exception),
+            None => JsError::from(fallback),
+        }
+    }
+
+    /// Convert a runtime error into a JavaScript `Error` object
+    fn error_to_exception<'s>(scope: &mut v8::HandleScope<'s>, error: &RuntimeError) -> v8::Local<'s, v8::Value> {
+        let message = v8::String::new(scope, &error.to_string()).unwrap_or_else(|| v8::String::empty(scope));
+        v8::Exception::error(scope, message)
     }
 
     /// Get memory usage information
-    pub fn get_memory_usage(&self) -> RuntimeResult<(usize, usize)> {
-        // V8 API for heap statistics may vary by version
-        // Return a placeholder for now
-        Ok((0, 0))
+    ///
+    /// # Returns
+    /// The used and total heap size in bytes
+    pub fn get_memory_usage(&mut self) -> RuntimeResult<(usize, usize)> {
+        let stats = self.heap_stats();
+        Ok((stats.used_heap_size, stats.total_heap_size))
+    }
+
+    /// Get the V8 heap statistics of this runtime
+    pub fn heap_stats(&mut self) -> HeapStats {
+        HeapStats::from_isolate(&mut self.isolate)
     }
 
     /// Perform garbage collection
@@ -669,6 +2321,13 @@ mod tests {
         assert!(matches!(result, Err(RuntimeError::PermissionDenied(_))));
     }
 
+    #[test]
+    fn test_execute_missing_file() {
+        let mut rt = init_test_runtime();
+        let result = rt.execute_file("/nonexistent/ferrum-missing.js");
+        assert!(matches!(result, Err(RuntimeError::IoError(_))));
+    }
+
     #[test]
     fn test_stats_tracking() {
         let mut rt = init_test_runtime();
@@ -681,5 +2340,590 @@ mod tests {
 
         let stats_after = rt.stats();
         assert_eq!(stats_after.scripts_executed, 2);
+        assert!(stats_after.memory_usage_bytes > 0);
+    }
+
+    #[test]
+    fn test_heap_stats() {
+        let mut rt = init_test_runtime();
+
+        let stats = rt.heap_stats();
+        assert!(stats.used_heap_size > 0);
+        assert!(stats.total_heap_size >= stats.used_heap_size);
+        assert!(stats.heap_size_limit >= stats.total_heap_size);
+
+        let (used, total) = rt.get_memory_usage().unwrap();
+        assert!(used > 0);
+        assert!(total >= used);
+    }
+
+    #[test]
+    fn test_cpu_profile() {
+        let mut rt = init_test_runtime();
+
+        assert!(rt.stop_cpu_profile().is_err());
+        rt.start_cpu_profile(100).unwrap();
+        assert!(rt.start_cpu_profile(100).is_err());
+
+        rt.execute(
+            "function busy() { const end = Date.now() + 20; while (Date.now() < end) {} } busy();",
+            Some("profiled.js"),
+        )
+        .unwrap();
+
+        let profile: serde_json::Value = serde_json::from_str(&rt.stop_cpu_profile().unwrap()).unwrap();
+        assert!(profile["nodes"].as_array().is_some_and(|nodes| !nodes.is_empty()));
+        assert!(profile["startTime"].is_number());
+        assert!(profile["endTime"].is_number());
+        assert!(rt.inspector().is_none());
+    }
+
+    #[test]
+    fn test_execution_time_tracking() {
+        let mut rt = init_test_runtime();
+
+        rt.execute("const end = Date.now() + 20; while (Date.now() < end) {}", None)
+            .unwrap();
+        let stats = rt.stats();
+        assert!(stats.last_execution_time_ms >= 20);
+        assert!(stats.total_execution_time_ms >= stats.last_execution_time_ms);
+
+        rt.execute("1 + 1", None).unwrap();
+        let stats_after = rt.stats();
+        assert!(stats_after.last_execution_time_ms < 20);
+        assert!(stats_after.total_execution_time_ms >= stats.total_execution_time_ms);
+    }
+
+    #[test]
+    fn test_deno_memory_usage() {
+        let mut rt = init_test_runtime();
+        let result = rt
+            .execute(
+                "const m = Deno.memoryUsage(); \
+                 [m.heapUsed > 0, m.heapTotal >= m.heapUsed, m.rss > 0, \
+                  typeof m.external, m.heapLimit >= m.heapTotal].join(',')",
+                None,
+            )
+            .unwrap();
+        assert_eq!(result, "true,true,true,number,true");
+    }
+
+    #[test]
+    fn test_registered_ops() {
+        init_v8_for_tests();
+
+        #[derive(serde::Deserialize)]
+        struct Point {
+            x: f64,
+            y: f64,
+        }
+
+        let mut ops = OpRegistry::new();
+        ops.set_namespace("app.math");
+        ops.register_op("norm", |(p,): (Point,)| Ok::<_, String>((p.x * p.x + p.y * p.y).sqrt()));
+        ops.register_op("split", |(text,): (String,)| {
+            Ok::<_, String>(text.split(',').map(str::to_string).collect::<Vec<_>>())
+        });
+        ops.register_op("fail", |(): ()| Err::<(), _>("op failed"));
+
+        let mut rt = JsRuntime::with_ops(RuntimeConfig::default(), Permissions::default(), ops).unwrap();
+        assert_eq!(rt.execute("app.math.norm({ x: 3, y: 4 })", None).unwrap(), "5");
+        assert_eq!(rt.execute("JSON.stringify(app.math.split('a,b'))", None).unwrap(), r#"["a","b"]"#);
+        assert_eq!(
+            rt.execute("try { app.math.fail() } catch (e) { e.message }", None).unwrap(),
+            "op failed"
+        );
+        assert!(rt
+            .execute("try { app.math.norm('nope') } catch (e) { e.message }", None)
+            .unwrap()
+            .starts_with("Invalid arguments"));
+
+        // Ops are installed again in a reset context
+        rt.reset_context().unwrap();
+        assert_eq!(rt.execute("typeof app.math.norm", None).unwrap(), "function");
+    }
+
+    #[test]
+    fn test_async_registered_ops() {
+        init_v8_for_tests();
+
+        let mut ops = OpRegistry::new();
+        ops.register_async_op("delayedDouble", |(n,): (i64,)| async move {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            Ok::<_, String>(n * 2)
+        });
+        ops.register_async_op("fail", |(): ()| async { Err::<(), _>("op failed") });
+
+        let mut rt = JsRuntime::with_ops(RuntimeConfig::default(), Permissions::default(), ops).unwrap();
+        rt.execute_and_wait(
+            r#"
+            globalThis.pending = ops.delayedDouble(21) instanceof Promise;
+            ops.delayedDouble(21).then((n) => { globalThis.doubled = n; });
+            ops.fail().catch((e) => { globalThis.failed = e.message; });
+            "#,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(rt.execute("pending", None).unwrap(), "true");
+        assert_eq!(rt.execute("doubled", None).unwrap(), "42");
+        assert_eq!(rt.execute("failed", None).unwrap(), "op failed");
+    }
+
+    #[test]
+    fn test_extensions() {
+        init_v8_for_tests();
+
+        struct Greeting(String);
+
+        let mut ops = OpRegistry::new();
+        ops.set_namespace("__greeter");
+        ops.register_op_with_state("greet", |state: &mut OpState, (name,): (String,)| {
+            Ok::<_, String>(format!("{}, {}!", state.get::<Greeting>().0, name))
+        });
+        let greeter = Extension::new("greeter")
+            .ops(ops)
+            .state(|| Greeting("Hello".to_string()))
+            .js("ext:greeter/api.js", "globalThis.greet = (name) => __greeter.greet(name);")
+            .init(|scope| {
+                let key = v8::String::new(scope, "greeterReady").unwrap();
+                let value = v8::Boolean::new(scope, true);
+                let global = scope.get_current_context().global(scope);
+                global.set(scope, key.into(), value.into());
+                Ok(())
+            });
+
+        let config = RuntimeConfig {
+            extensions: vec![greeter],
+            ..RuntimeConfig::default()
+        };
+        let mut rt = JsRuntime::new(config, Permissions::default()).unwrap();
+        assert_eq!(rt.execute("greet('Ferrum')", None).unwrap(), "Hello, Ferrum!");
+        assert_eq!(rt.execute("greeterReady", None).unwrap(), "true");
+
+        // Extensions are installed again in a reset context
+        rt.reset_context().unwrap();
+        assert_eq!(rt.execute("greet('again')", None).unwrap(), "Hello, again!");
+
+        // A failing script fails runtime creation
+        let broken = Extension::new("broken").js("ext:broken/api.js", "throw new Error('boom');");
+        let config = RuntimeConfig {
+            extensions: vec![broken],
+            ..RuntimeConfig::default()
+        };
+        match JsRuntime::new(config, Permissions::default()) {
+            Err(RuntimeError::InitializationError(msg)) => {
+                assert!(msg.contains("broken") && msg.contains("boom"), "Unexpected error: {}", msg)
+            }
+            other => panic!("Expected InitializationError, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_extension_modules() {
+        init_v8_for_tests();
+
+        let base = Extension::new("base").esm(
+            "ext:base/mod.js",
+            "export const prefix = 'Hi'; globalThis.baseLoads = (globalThis.baseLoads ?? 0) + 1;",
+        );
+        // Installed first, so it evaluates the other extension's module
+        let greeter = Extension::new("greeter").esm(
+            "ext:greeter/mod.js",
+            "import { prefix } from 'ext:base/mod.js'; globalThis.hi = (name) => `${prefix}, ${name}!`;",
+        );
+
+        let config = RuntimeConfig {
+            extensions: vec![greeter, base],
+            ..RuntimeConfig::default()
+        };
+        let mut rt = JsRuntime::new(config, Permissions::default()).unwrap();
+        assert_eq!(rt.execute("hi('Ferrum') + ' ' + baseLoads", None).unwrap(), "Hi, Ferrum! 1");
+
+        // Modules are evaluated again in a reset context
+        rt.reset_context().unwrap();
+        assert_eq!(rt.execute("hi('again') + ' ' + baseLoads", None).unwrap(), "Hi, again! 1");
+
+        // Specifiers must use the ext: scheme
+        let broken = Extension::new("broken").esm("broken/mod.js", "export {};");
+        let config = RuntimeConfig {
+            extensions: vec![broken],
+            ..RuntimeConfig::default()
+        };
+        match JsRuntime::new(config, Permissions::default()) {
+            Err(RuntimeError::InitializationError(msg)) => {
+                assert!(msg.contains("must start with 'ext:'"), "Unexpected error: {}", msg)
+            }
+            other => panic!("Expected InitializationError, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_runtimes_on_one_thread_are_isolated() {
+        init_v8_for_tests();
+
+        let mut ops = OpRegistry::new();
+        ops.register_op("tenant", |(): ()| Ok::<_, String>("a"));
+        let mut tenant_a = JsRuntime::with_ops(RuntimeConfig::default(), Permissions::allow_all(), ops).unwrap();
+
+        let mut ops = OpRegistry::new();
+        ops.register_op("tenant", |(): ()| Ok::<_, String>("b"));
+        let mut tenant_b = JsRuntime::with_ops(RuntimeConfig::default(), Permissions::default(), ops).unwrap();
+
+        // Callbacks see the permissions and ops of their own runtime,
+        // including in callbacks run later from the event loop
+        let code = "setTimeout(() => { globalThis.result = [ops.tenant(), Deno.existsSync('/')].join(); }, 0);";
+        tenant_a.execute(code, None).unwrap();
+        tenant_b.execute("setTimeout(() => { globalThis.result = ops.tenant(); }, 0);", None).unwrap();
+        tenant_a.run_event_loop().unwrap();
+        tenant_b.run_event_loop().unwrap();
+
+        assert_eq!(tenant_a.execute("result", None).unwrap(), "a,true");
+        assert_eq!(tenant_b.execute("result", None).unwrap(), "b");
+        assert!(tenant_b.execute("Deno.existsSync('/')", None).is_err());
+        assert_eq!(tenant_a.execute("Deno.existsSync('/')", None).unwrap(), "true");
+    }
+
+    #[test]
+    fn test_write_heap_snapshot() {
+        let mut rt = init_test_runtime();
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("test.heapsnapshot");
+
+        rt.execute("globalThis.retained = { marker: 'leak' };", None).unwrap();
+        rt.write_heap_snapshot(&path).unwrap();
+
+        let snapshot: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert!(snapshot["snapshot"]["meta"]["node_fields"].is_array());
+        assert!(snapshot["nodes"].as_array().is_some_and(|nodes| !nodes.is_empty()));
+        assert!(snapshot["strings"].as_array().unwrap().iter().any(|s| s == "leak"));
+    }
+
+    #[test]
+    fn test_unhandled_rejection_is_reported() {
+        let mut rt = init_test_runtime();
+
+        let err = rt
+            .execute_and_wait("Promise.reject(new Error('boom')); 1", None)
+            .unwrap_err();
+        match err {
+            RuntimeError::UnhandledRejection(js_error) => assert_eq!(js_error.message, "boom"),
+            other => panic!("expected an unhandled rejection, got {:?}", other),
+        }
+
+        // Handlers attached before the end of the turn count
+        rt.execute_and_wait("const p = Promise.reject(1); p.catch(() => {}); 2", None)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_unhandled_rejection_event() {
+        let mut rt = init_test_runtime();
+
+        let result = rt.execute_and_wait(
+            r#"
+            globalThis.seen = [];
+            addEventListener("unhandledrejection", (event) => {
+                seen.push(event.reason, event.cancelable, event.promise instanceof Promise);
+                event.preventDefault();
+            });
+            Promise.reject("first");
+            setTimeout(() => Promise.reject("second"), 1);
+            "#,
+            None,
+        );
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(rt.execute("seen.join(',')", None).unwrap(), "first,true,true,second,true,true");
+
+        // A listener that does not cancel the event leaves it unhandled
+        rt.reset_context().unwrap();
+        let result = rt.execute_and_wait(
+            "globalThis.onunhandledrejection = () => {}; Promise.reject('third');",
+            None,
+        );
+        assert!(matches!(result, Err(RuntimeError::UnhandledRejection(_))));
+    }
+
+    #[test]
+    fn test_event_loop_runs_timers_in_order() {
+        let mut rt = init_test_runtime();
+        let result = rt.execute_and_wait(
+            r#"
+            const order = [];
+            setTimeout(() => order.push("b"), 20);
+            setTimeout(() => order.push("a"), 5);
+            Promise.resolve().then(() => order.push("micro"));
+            setTimeout(() => {
+                if (order.join(",") !== "micro,a,b") {
+                    throw new Error("unexpected order: " + order.join(","));
+                }
+            }, 40);
+            "scheduled"
+            "#,
+            None,
+        );
+        assert_eq!(result.unwrap(), "scheduled");
+    }
+
+    #[test]
+    fn test_event_loop_interval_and_clear() {
+        let mut rt = init_test_runtime();
+        let result = rt.execute_and_wait(
+            r#"
+            let ticks = 0;
+            const id = setInterval(() => {
+                if (++ticks === 3) clearInterval(id);
+            }, 1);
+            const never = setTimeout(() => { throw new Error("cleared timer fired"); }, 1);
+            clearTimeout(never);
+            "#,
+            None,
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_event_loop_uncaught_error() {
+        let mut rt = init_test_runtime();
+        let result = rt.execute_and_wait("setTimeout(() => { throw new Error('boom'); }, 0)", None);
+        match result {
+            Err(RuntimeError::ExecutionError(error)) => assert!(error.message.contains("boom")),
+            other => panic!("Expected ExecutionError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_heap_limit_out_of_memory() {
+        init_v8_for_tests();
+        let config = RuntimeConfig {
+            max_heap_size: 16,
+            ..RuntimeConfig::default()
+        };
+        let mut rt = JsRuntime::new(config, Permissions::allow_all()).unwrap();
+
+        let result = rt.execute(
+            "(() => { const chunks = []; while (true) { chunks.push(new Array(100000).fill(1)); } })()",
+            None,
+        );
+        assert!(matches!(result, Err(RuntimeError::OutOfMemory(_))));
+
+        // The runtime stays usable after the limit was hit
+        assert_eq!(rt.execute("1 + 1", None).unwrap(), "2");
+    }
+
+    #[test]
+    fn test_timeout_terminates_script() {
+        init_v8_for_tests();
+        let config = RuntimeConfig {
+            timeout_ms: 100,
+            ..RuntimeConfig::default()
+        };
+        let mut rt = JsRuntime::new(config, Permissions::allow_all()).unwrap();
+
+        let result = rt.execute("while (true) {}", None);
+        assert!(matches!(result, Err(RuntimeError::Timeout(_))));
+
+        // Converting the result to a string is subject to the timeout too
+        let result = rt.execute("({ toString() { for (;;); } })", None);
+        assert!(matches!(result, Err(RuntimeError::Timeout(_))));
+
+        // A busy timer callback is terminated too
+        let result = rt.execute_and_wait("setTimeout(() => { while (true) {} }, 0);", None);
+        assert!(matches!(result, Err(RuntimeError::Timeout(_))));
+
+        // The runtime stays usable after a timeout
+        assert_eq!(rt.execute("1 + 1", None).unwrap(), "2");
+    }
+
+    #[test]
+    fn test_result_to_string_throws() {
+        let mut rt = init_test_runtime();
+        let result = rt.execute("({ toString() { throw new Error('no string'); } })", None);
+        match result {
+            Err(RuntimeError::ExecutionError(error)) => assert!(error.to_string().contains("no string")),
+            other => panic!("expected an execution error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_exception_details() {
+        let mut rt = init_test_runtime();
+
+        let result = rt.execute("function f() {\n  null.x;\n}\nf();", None);
+        let Err(RuntimeError::ExecutionError(error)) = result else {
+            panic!("Expected ExecutionError, got {:?}", result);
+        };
+        assert_eq!(error.name.as_deref(), Some("TypeError"));
+        assert_eq!(error.line_number, Some(2));
+        assert_eq!(error.source_line.as_deref(), Some("  null.x;"));
+        assert!(error.stack.as_deref().unwrap_or("").contains("at f"));
+
+        let result = rt.execute("let x = ;", None);
+        let Err(RuntimeError::CompilationError(error)) = result else {
+            panic!("Expected CompilationError, got {:?}", result);
+        };
+        assert_eq!(error.name.as_deref(), Some("SyntaxError"));
+        assert_eq!(error.line_number, Some(1));
+        assert_eq!(error.column_number, Some(9));
+    }
+
+    #[test]
+    fn test_execute_with_offset_reports_original_location() {
+        let mut rt = init_test_runtime();
+
+        let result = rt.execute_with_offset("1;\nthrow new Error('in fragment');", Some("big.js"), 10, 4);
+        let Err(RuntimeError::ExecutionError(error)) = result else {
+            panic!("Expected ExecutionError, got {:?}", result);
+        };
+        assert_eq!(error.file_name.as_deref(), Some("big.js"));
+        assert_eq!(error.line_number, Some(12));
+        assert!(error.stack.as_deref().unwrap_or("").contains("big.js:12:"));
+
+        // The column offset applies to the first line of the fragment
+        let result = rt.execute_with_offset("missingVariable;", Some("big.js"), 3, 6);
+        let Err(RuntimeError::ExecutionError(error)) = result else {
+            panic!("Expected ExecutionError, got {:?}", result);
+        };
+        assert_eq!(error.line_number, Some(4));
+        assert_eq!(error.column_number, Some(7));
+    }
+
+    #[test]
+    fn test_context_persists_across_calls() {
+        let mut rt = init_test_runtime();
+
+        rt.execute("var a = 1; let b = 2; globalThis.c = 3;", None).unwrap();
+        assert_eq!(rt.execute("a + b + c", None).unwrap(), "6");
+    }
+
+    #[test]
+    fn test_eval_as_and_globals() {
+        let mut rt = init_test_runtime();
+
+        let doubled: Vec<u32> = rt.eval_as("[1, 2, 3].map((n) => n * 2)").unwrap();
+        assert_eq!(doubled, [2, 4, 6]);
+        let awaited: String = rt.eval_as("Promise.resolve('done')").unwrap();
+        assert_eq!(awaited, "done");
+
+        rt.set_global("config", &serde_json::json!({ "retries": 3 })).unwrap();
+        assert_eq!(rt.execute("config.retries + 1", None).unwrap(), "4");
+        let config: serde_json::Value = rt.get_global("config").unwrap();
+        assert_eq!(config["retries"], 3);
+
+        let missing: Option<String> = rt.get_global("missing").unwrap();
+        assert_eq!(missing, None);
+        assert!(matches!(rt.get_global::<u32>("config"), Err(RuntimeError::ConversionError(_))));
+        assert!(matches!(rt.eval_as::<u32>("1n"), Err(RuntimeError::ExecutionError(_))));
+    }
+
+    #[test]
+    fn test_call_function() {
+        let mut rt = init_test_runtime();
+        rt.execute(
+            r#"
+            function add(a, b) { return a + b; }
+            globalThis.hooks = {
+                prefix: "hook:",
+                name(value) { return this.prefix + value; },
+                length: (list) => list.length,
+                kind: (...args) => args.map((arg) => typeof arg).join(" "),
+                later: async (value) => { await new Promise((r) => setTimeout(r, 1)); return value * 2; },
+                fail: async () => { throw new Error("hook failed"); },
+            };
+            "#,
+            None,
+        )
+        .unwrap();
+
+        let sum: i32 = rt.call_function("add", (2, 3)).unwrap();
+        assert_eq!(sum, 5);
+        let name: String = rt.call_function("hooks.name", ("x",)).unwrap();
+        assert_eq!(name, "hook:x");
+        // Sequences and `None` are single arguments, not spread
+        let length: usize = rt.call_function("hooks.length", (vec![1, 2, 3],)).unwrap();
+        assert_eq!(length, 3);
+        let kind: String = rt.call_function("hooks.kind", (None::<u32>, 1)).unwrap();
+        assert_eq!(kind, "object number");
+        let later: u32 = rt.call_function("hooks.later", (21,)).unwrap();
+        assert_eq!(later, 42);
+
+        let err = rt.call_function::<_, ()>("hooks.fail", ()).unwrap_err();
+        assert!(err.to_string().contains("hook failed"));
+        let err = rt.call_function::<_, ()>("hooks.missing.deeper", ()).unwrap_err();
+        assert!(err.to_string().contains("hooks.missing.deeper is not a function"));
+    }
+
+    #[test]
+    fn test_reset_context() {
+        let mut rt = init_test_runtime();
+
+        rt.execute("var a = 1; setTimeout(() => { throw new Error('stale'); }, 0);", None).unwrap();
+        rt.reset_context().unwrap();
+
+        assert_eq!(rt.execute("typeof a", None).unwrap(), "undefined");
+        assert_eq!(rt.execute("typeof console.log", None).unwrap(), "function");
+        // Timers scheduled before the reset are cancelled
+        assert!(rt.run_event_loop().is_ok());
+    }
+
+    #[test]
+    fn test_script_code_cache() {
+        init_v8_for_tests();
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = RuntimeConfig {
+            code_cache_dir: Some(dir.path().to_path_buf()),
+            ..RuntimeConfig::default()
+        };
+        let code = "function add(a, b) { return a + b; } add(1, 2)";
+
+        // First run produces the cache entry
+        let mut rt = JsRuntime::new(config.clone(), Permissions::default()).unwrap();
+        assert_eq!(rt.execute(code, Some("/app/add.js")).unwrap(), "3");
+        let cache = CodeCache::new(dir.path());
+        let produced = cache.get("/app/add.js", code).unwrap();
+
+        // Second run consumes it and keeps it
+        let mut rt = JsRuntime::new(config.clone(), Permissions::default()).unwrap();
+        assert_eq!(rt.execute(code, Some("/app/add.js")).unwrap(), "3");
+        assert_eq!(cache.get("/app/add.js", code).unwrap(), produced);
+
+        // Changed source invalidates the entry
+        let changed = "function add(a, b) { return a * b; } add(2, 3)";
+        assert!(cache.get("/app/add.js", changed).is_none());
+        assert_eq!(rt.execute(changed, Some("/app/add.js")).unwrap(), "6");
+        assert!(cache.get("/app/add.js", changed).is_some());
+
+        // Anonymous code is never cached
+        rt.execute("1 + 1", Some("<eval>")).unwrap();
+        assert!(cache.get("<eval>", "1 + 1").is_none());
+    }
+
+    #[test]
+    fn test_runtime_from_snapshot() {
+        init_v8_for_tests();
+        let snapshot = crate::snapshot::create_snapshot().unwrap();
+        assert!(!snapshot.is_empty());
+
+        let mut rt =
+            JsRuntime::from_snapshot(RuntimeConfig::default(), Permissions::default(), snapshot).unwrap();
+
+        let types = "[typeof console.log, typeof Deno.readTextFile, typeof setTimeout].join()";
+        assert_eq!(rt.execute(types, None).unwrap(), "function,function,function");
+
+        let result = rt.execute_and_wait(
+            "globalThis.fired = false; setTimeout(() => { globalThis.fired = true; }, 0);",
+            None,
+        );
+        assert!(result.is_ok());
+        assert_eq!(rt.execute("fired", None).unwrap(), "true");
+
+        // Native callbacks use this runtime's permissions
+        let result = rt.execute("Deno.existsSync('/etc/passwd')", None);
+        assert!(result.is_err());
+
+        // A reset context is deserialized from the snapshot as well
+        rt.reset_context().unwrap();
+        assert_eq!(rt.execute("typeof fired", None).unwrap(), "undefined");
+        assert_eq!(rt.execute(types, None).unwrap(), "function,function,function");
     }
 }
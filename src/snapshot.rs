@@ -0,0 +1,149 @@
+//! V8 Startup Snapshot
+//!
+//! This module creates V8 startup snapshots whose default context already
+//! contains the bootstrapped globals (`console`, `Deno`, timers). Booting a
+//! runtime from a snapshot with [`JsRuntime::from_snapshot`] deserializes
+//! that context instead of building every global function by function.
+//!
+//! # Architecture
+//!
+//! Snapshots can be created at build time with [`create_snapshot`] and
+//! embedded with `include_bytes!`, or created on first run and cached on
+//! disk with [`load_or_create_snapshot`]. A snapshot is only valid for the
+//! binary that created it: functions backed by native callbacks are stored
+//! as indices into the external reference table of `ops::bindings`.
+//!
+//! [`JsRuntime::from_snapshot`]: crate::runtime::JsRuntime::from_snapshot
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use v8;
+
+use crate::ops::bindings::{bootstrap_globals, external_references};
+use crate::ops::dispatch::OpRegistry;
+use crate::permissions::Permissions;
+use crate::runtime::{RuntimeContext, RuntimeError, RuntimeResult};
+
+/// Create a startup snapshot with the global APIs bootstrapped
+///
+/// The V8 platform must be initialized first.
+///
+/// # Returns
+/// The serialized snapshot blob
+pub fn create_snapshot() -> RuntimeResult<Vec<u8>> {
+    let mut isolate = v8::Isolate::snapshot_creator(Some(external_references()));
+
+    {
+        let scope = &mut v8::HandleScope::new(&mut isolate);
+        let context = v8::Context::new(scope);
+        let scope = &mut v8::ContextScope::new(scope, context);
+
+        // The globals only capture the callbacks, not this context; runtimes
+        // booted from the snapshot install their own RuntimeContext
+        let rt_context = Arc::new(RuntimeContext::new(Permissions::default(), OpRegistry::new()));
+        bootstrap_globals(scope, rt_context).map_err(|e| {
+            RuntimeError::InitializationError(format!("Failed to bootstrap globals: {}", e))
+        })?;
+
+        scope.set_default_context(context);
+    }
+
+    let blob = isolate
+        .create_blob(v8::FunctionCodeHandling::Keep)
+        .ok_or_else(|| RuntimeError::InitializationError("Failed to create snapshot".to_string()))?;
+
+    tracing::debug!("Created startup snapshot ({} bytes)", blob.len());
+    Ok(blob.to_vec())
+}
+
+/// Load the cached startup snapshot, creating it on first use
+///
+/// The snapshot is stored in `cache_dir` under a name derived from the
+/// Ferrum and V8 versions and the current executable, so a rebuilt binary
+/// never boots from a stale snapshot.
+///
+/// # Arguments
+/// * `cache_dir` - Directory the snapshot is cached in
+///
+/// # Returns
+/// The serialized snapshot blob
+pub fn load_or_create_snapshot(cache_dir: &Path) -> RuntimeResult<Vec<u8>> {
+    let path = cache_dir.join(snapshot_file_name());
+
+    if let Ok(blob) = std::fs::read(&path) {
+        tracing::debug!("Loaded startup snapshot from {}", path.display());
+        return Ok(blob);
+    }
+
+    let blob = create_snapshot()?;
+
+    // Caching is best effort; a failed write only costs the next startup
+    if let Err(e) = write_snapshot(&path, &blob) {
+        tracing::warn!("Failed to cache startup snapshot at {}: {}", path.display(), e);
+    }
+
+    Ok(blob)
+}
+
+/// Get the default Ferrum cache directory
+///
+/// Holds cached snapshots; the CLI keeps the code cache in it as well.
+///
+/// Uses `$FERRUM_DIR`, then `$XDG_CACHE_HOME/ferrum`, then `~/.cache/ferrum`.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("FERRUM_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    if let Some(dir) = std::env::var_os("XDG_CACHE_HOME") {
+        return Some(PathBuf::from(dir).join("ferrum"));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join("ferrum"))
+}
+
+/// Build the cache file name of the snapshot for this binary
+fn snapshot_file_name() -> String {
+    let mut hasher = DefaultHasher::new();
+    if let Some(metadata) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| std::fs::metadata(exe).ok())
+    {
+        metadata.len().hash(&mut hasher);
+        if let Ok(modified) = metadata.modified() {
+            modified.duration_since(UNIX_EPOCH).unwrap_or_default().hash(&mut hasher);
+        }
+    }
+
+    format!(
+        "snapshot-{}-v8-{}-{:016x}.bin",
+        crate::VERSION,
+        v8::V8::get_version(),
+        hasher.finish()
+    )
+}
+
+/// Write a snapshot atomically so concurrent runs never read a partial file
+fn write_snapshot(path: &Path, blob: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+    std::fs::write(&tmp_path, blob)?;
+    std::fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_file_name_is_stable() {
+        let name = snapshot_file_name();
+        assert!(name.starts_with(&format!("snapshot-{}-v8-", crate::VERSION)));
+        assert!(name.ends_with(".bin"));
+        assert_eq!(name, snapshot_file_name());
+    }
+}
@@ -0,0 +1,274 @@
+//! Structured Clone
+//!
+//! This module serializes JavaScript values with V8's `ValueSerializer`
+//! (the HTML structured clone algorithm) so they can be copied into another
+//! context or isolate, e.g. for `postMessage` between workers.
+//!
+//! # Architecture
+//!
+//! [`serialize`] turns a value into a [`SerializedValue`]: the serialized
+//! bytes plus the contents of any transferred `ArrayBuffer`s. It is `Send`
+//! and can cross threads. [`deserialize`] consumes it and rebuilds the
+//! value in whatever context is entered in the given scope. Values that
+//! cannot be cloned (functions, symbols, host objects) make serialization
+//! throw a `DataCloneError`.
+//!
+//! Supported values are those of V8's serializer: primitives, plain
+//! objects and arrays, `Map`, `Set`, `Date`, `RegExp`, boxed primitives,
+//! `ArrayBuffer` and its views, and `Error` objects. Object identity is
+//! preserved within a value, so shared references and cycles survive.
+//!
+//! `ArrayBuffer`s in the transfer list are not copied: their backing
+//! stores move to the clone and the originals are detached.
+
+use v8::{self, ValueDeserializerHelper, ValueSerializerHelper};
+
+/// Backing store of a transferred `ArrayBuffer`
+#[derive(Debug)]
+struct TransferredArrayBuffer(v8::SharedRef<v8::BackingStore>);
+
+// SAFETY: the source `ArrayBuffer` is detached when it is transferred, so
+// the backing store is only reachable through this value until it is
+// handed to exactly one `ArrayBuffer` again (deserialization consumes the
+// `SerializedValue`)
+unsafe impl Send for TransferredArrayBuffer {}
+
+/// A JavaScript value in V8's structured clone wire format
+#[derive(Debug)]
+pub struct SerializedValue {
+    /// Serialized bytes (including the wire format header)
+    data: Vec<u8>,
+    /// Transferred `ArrayBuffer`s, indexed by transfer ID
+    array_buffers: Vec<TransferredArrayBuffer>,
+}
+
+impl SerializedValue {
+    /// Wrap bytes produced by [`serialize`] for a value without transfers
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            array_buffers: Vec::new(),
+        }
+    }
+
+    /// Get the serialized bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Check if the value carries transferred `ArrayBuffer`s
+    ///
+    /// Such values cannot be rebuilt from [`SerializedValue::as_bytes`] alone.
+    pub fn has_transfers(&self) -> bool {
+        !self.array_buffers.is_empty()
+    }
+}
+
+/// Serialize a value with the structured clone algorithm
+///
+/// # Arguments
+/// * `scope` - V8 handle scope with the value's context entered
+/// * `value` - Value to serialize
+/// * `transfer` - `ArrayBuffer`s to transfer instead of copying; they are
+///   detached once the value is serialized
+///
+/// # Returns
+/// The serialized value, or `None` if an exception (usually a
+/// `DataCloneError`) was thrown
+pub fn serialize<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: v8::Local<v8::Value>,
+    transfer: &[v8::Local<'s, v8::Value>],
+) -> Option<SerializedValue> {
+    let transfer = transferable_array_buffers(scope, transfer)?;
+
+    let context = scope.get_current_context();
+    let mut serializer = v8::ValueSerializer::new(scope, Box::new(SerializerDelegate));
+    serializer.write_header();
+    for (id, array_buffer) in transfer.iter().enumerate() {
+        serializer.transfer_array_buffer(id as u32, *array_buffer);
+    }
+    serializer.write_value(context, value)?;
+    let data = serializer.release();
+
+    let array_buffers = transfer
+        .iter()
+        .map(|array_buffer| {
+            let backing_store = array_buffer.get_backing_store();
+            array_buffer.detach(None);
+            TransferredArrayBuffer(backing_store)
+        })
+        .collect();
+
+    Some(SerializedValue { data, array_buffers })
+}
+
+/// Deserialize a value in the current context
+///
+/// # Arguments
+/// * `scope` - V8 handle scope with the target context entered
+/// * `value` - Value produced by [`serialize`]
+///
+/// # Returns
+/// The cloned value, or `None` if an exception was thrown
+pub fn deserialize<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: SerializedValue,
+) -> Option<v8::Local<'s, v8::Value>> {
+    let array_buffers: Vec<_> = value
+        .array_buffers
+        .iter()
+        .map(|array_buffer| v8::ArrayBuffer::with_backing_store(scope, &array_buffer.0))
+        .collect();
+
+    let context = scope.get_current_context();
+    let mut deserializer = v8::ValueDeserializer::new(scope, Box::new(DeserializerDelegate), value.as_bytes());
+    if deserializer.read_header(context) != Some(true) {
+        let message = v8::String::new(scope, "Failed to deserialize value: invalid header").unwrap();
+        let exception = v8::Exception::error(scope, message);
+        scope.throw_exception(exception);
+        return None;
+    }
+    for (id, array_buffer) in array_buffers.into_iter().enumerate() {
+        deserializer.transfer_array_buffer(id as u32, array_buffer);
+    }
+    deserializer.read_value(context)
+}
+
+/// Validate a transfer list
+///
+/// # Returns
+/// The `ArrayBuffer`s to transfer, or `None` if a `DataCloneError` was
+/// thrown for an entry that cannot be transferred
+fn transferable_array_buffers<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    transfer: &[v8::Local<'s, v8::Value>],
+) -> Option<Vec<v8::Local<'s, v8::ArrayBuffer>>> {
+    let mut array_buffers: Vec<v8::Local<v8::ArrayBuffer>> = Vec::with_capacity(transfer.len());
+
+    for value in transfer {
+        let error = match v8::Local::<v8::ArrayBuffer>::try_from(*value) {
+            Err(_) => Some("Value not transferable"),
+            Ok(array_buffer) if array_buffers.contains(&array_buffer) => {
+                Some("ArrayBuffer occurs more than once in the transfer list")
+            }
+            Ok(array_buffer) if array_buffer.was_detached() => Some("ArrayBuffer is detached"),
+            Ok(array_buffer) if !array_buffer.is_detachable() => Some("ArrayBuffer is not detachable"),
+            Ok(array_buffer) => {
+                array_buffers.push(array_buffer);
+                None
+            }
+        };
+        if let Some(error) = error {
+            let message = v8::String::new(scope, error).unwrap();
+            throw_data_clone_error(scope, message);
+            return None;
+        }
+    }
+
+    Some(array_buffers)
+}
+
+/// Read a transfer list (an array, or `undefined` for none)
+///
+/// # Returns
+/// The list entries, or `None` if a `TypeError` was thrown
+pub(crate) fn transfer_list<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: v8::Local<v8::Value>,
+) -> Option<Vec<v8::Local<'s, v8::Value>>> {
+    if value.is_null_or_undefined() {
+        return Some(Vec::new());
+    }
+    let Ok(array) = v8::Local::<v8::Array>::try_from(value) else {
+        let message = v8::String::new(scope, "Transfer list must be an array").unwrap();
+        let exception = v8::Exception::type_error(scope, message);
+        scope.throw_exception(exception);
+        return None;
+    };
+    (0..array.length()).map(|i| array.get_index(scope, i)).collect()
+}
+
+/// Throw a `DataCloneError` (a `DOMException` in browsers)
+fn throw_data_clone_error(scope: &mut v8::HandleScope, message: v8::Local<v8::String>) {
+    let exception = v8::Exception::error(scope, message);
+    if let Ok(error) = v8::Local::<v8::Object>::try_from(exception) {
+        let key = v8::String::new(scope, "name").unwrap();
+        let name = v8::String::new(scope, "DataCloneError").unwrap();
+        error.set(scope, key.into(), name.into());
+    }
+    scope.throw_exception(exception);
+}
+
+/// Serializer delegate (no host objects or shared memory)
+struct SerializerDelegate;
+
+impl v8::ValueSerializerImpl for SerializerDelegate {
+    fn throw_data_clone_error<'s>(&mut self, scope: &mut v8::HandleScope<'s>, message: v8::Local<'s, v8::String>) {
+        throw_data_clone_error(scope, message);
+    }
+}
+
+/// Deserializer delegate (no host objects or shared memory)
+struct DeserializerDelegate;
+
+impl v8::ValueDeserializerImpl for DeserializerDelegate {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permissions::Permissions;
+    use crate::runtime::{init_v8_platform, JsRuntime, RuntimeConfig};
+
+    #[test]
+    fn test_serialized_value_bytes() {
+        let value = SerializedValue::from_bytes(vec![0xFF, 0x0F]);
+        assert_eq!(value.as_bytes(), &[0xFF, 0x0F]);
+    }
+
+    #[test]
+    fn test_clone_between_runtimes() {
+        init_v8_platform();
+        let mut source = JsRuntime::new(RuntimeConfig::default(), Permissions::default()).unwrap();
+        let mut target = JsRuntime::new(RuntimeConfig::default(), Permissions::default()).unwrap();
+
+        let serialized = source
+            .with_context_scope(|scope| {
+                let code = v8::String::new(scope, "({ list: [1, 'two', { three: 3 }], when: new Date(0) })").unwrap();
+                let value = v8::Script::compile(scope, code, None)?.run(scope)?;
+                serialize(scope, value, &[])
+            })
+            .unwrap();
+
+        let json = target.with_context_scope(|scope| {
+            let value = deserialize(scope, serialized).unwrap();
+            v8::json::stringify(scope, value).unwrap().to_rust_string_lossy(scope)
+        });
+        assert_eq!(json, r#"{"list":[1,"two",{"three":3}],"when":"1970-01-01T00:00:00.000Z"}"#);
+    }
+
+    #[test]
+    fn test_transfer_between_runtimes() {
+        init_v8_platform();
+        let mut source = JsRuntime::new(RuntimeConfig::default(), Permissions::default()).unwrap();
+        let mut target = JsRuntime::new(RuntimeConfig::default(), Permissions::default()).unwrap();
+
+        let serialized = source
+            .with_context_scope(|scope| {
+                let code = v8::String::new(scope, "globalThis.buffer = new Uint8Array([1, 2, 3]).buffer").unwrap();
+                let buffer = v8::Script::compile(scope, code, None)?.run(scope)?;
+                serialize(scope, buffer, &[buffer])
+            })
+            .unwrap();
+        assert!(serialized.has_transfers());
+        assert_eq!(source.execute("buffer.byteLength", None).unwrap(), "0");
+
+        let bytes = target.with_context_scope(|scope| {
+            let buffer = deserialize(scope, serialized).unwrap();
+            let buffer = v8::Local::<v8::ArrayBuffer>::try_from(buffer).unwrap();
+            let array = v8::Uint8Array::new(scope, buffer, 0, buffer.byte_length()).unwrap();
+            array.to_rust_string_lossy(scope)
+        });
+        assert_eq!(bytes, "1,2,3");
+    }
+}
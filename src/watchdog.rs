@@ -0,0 +1,150 @@
+//! Execution Watchdog
+//!
+//! This module enforces `RuntimeConfig::timeout_ms`. A background thread
+//! waits for the deadline of the currently armed execution and terminates
+//! the isolate through its thread-safe handle once the deadline passes.
+//!
+//! # Architecture
+//!
+//! The runtime arms the watchdog before running JavaScript (a script, a
+//! module evaluation or an event loop turn) and disarms it afterwards.
+//! Waiting for pending ops is never counted against the budget. After
+//! disarming, the runtime checks [`Watchdog::take_fired`] to find out
+//! whether execution was terminated and reports `RuntimeError::Timeout`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use v8;
+
+/// State shared between the runtime and the watchdog thread
+struct Shared {
+    /// Deadline of the armed execution and shutdown flag
+    state: Mutex<State>,
+    /// Wakes the watchdog thread when the state changes
+    condvar: Condvar,
+    /// Set once the watchdog terminated execution
+    fired: AtomicBool,
+}
+
+/// Mutable watchdog state
+#[derive(Default)]
+struct State {
+    /// Deadline of the armed execution (`None` when disarmed)
+    deadline: Option<Instant>,
+    /// Set when the watchdog is dropped
+    shutdown: bool,
+}
+
+/// Terminates JavaScript execution that exceeds a time budget
+pub struct Watchdog {
+    /// Time budget of each armed execution
+    timeout: Duration,
+    /// State shared with the watchdog thread
+    shared: Arc<Shared>,
+    /// Watchdog thread
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    /// Start a watchdog for an isolate
+    ///
+    /// # Arguments
+    /// * `handle` - Thread-safe handle of the isolate to terminate
+    /// * `timeout` - Time budget of each armed execution
+    pub fn new(handle: v8::IsolateHandle, timeout: Duration) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State::default()),
+            condvar: Condvar::new(),
+            fired: AtomicBool::new(false),
+        });
+
+        let thread_shared = shared.clone();
+        let thread = std::thread::Builder::new()
+            .name("ferrum-watchdog".to_string())
+            .spawn(move || Self::run(thread_shared, handle))
+            .expect("Failed to spawn watchdog thread");
+
+        Self {
+            timeout,
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    /// Watchdog thread main loop
+    fn run(shared: Arc<Shared>, handle: v8::IsolateHandle) {
+        let mut state = shared.state.lock().unwrap();
+
+        loop {
+            if state.shutdown {
+                return;
+            }
+
+            match state.deadline {
+                None => {
+                    state = shared.condvar.wait(state).unwrap();
+                }
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        state.deadline = None;
+                        shared.fired.store(true, Ordering::SeqCst);
+                        tracing::warn!("Execution timeout reached, terminating execution");
+                        handle.terminate_execution();
+                    } else {
+                        state = shared.condvar.wait_timeout(state, deadline - now).unwrap().0;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start the time budget for an execution
+    ///
+    /// Re-arming an armed watchdog restarts the budget.
+    pub fn arm(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.deadline = Some(Instant::now() + self.timeout);
+        self.shared.condvar.notify_one();
+    }
+
+    /// Stop the time budget of the current execution
+    pub fn disarm(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.deadline = None;
+        self.shared.condvar.notify_one();
+    }
+
+    /// Check if the watchdog terminated execution since the last call
+    ///
+    /// Only reliable after [`Watchdog::disarm`].
+    pub fn take_fired(&self) -> bool {
+        self.shared.fired.swap(false, Ordering::SeqCst)
+    }
+
+    /// Check if the watchdog terminated execution
+    pub fn has_fired(&self) -> bool {
+        self.shared.fired.load(Ordering::SeqCst)
+    }
+
+    /// Get the time budget of each armed execution
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            state.shutdown = true;
+            self.shared.condvar.notify_one();
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,585 @@
+//! Web Workers
+//!
+//! This module runs the scripts of `new Worker(url, { type: "module" })`.
+//! Every worker is a separate [`JsRuntime`] (its own isolate, context and
+//! event loop) running on its own thread.
+//!
+//! # Architecture
+//!
+//! The [`WorkerHost`] of a runtime is stored in an isolate slot and owns
+//! the handles of the workers the runtime created. Messages are structured
+//! clones ([`SerializedValue`]) sent over tokio channels in both
+//! directions; each side keeps a pending op on its event loop that
+//! receives the next message and dispatches it as a `message` event.
+//!
+//! Workers get their parent's permissions, optionally narrowed with the
+//! `deno.permissions` option (see [`Permissions::narrow`]), and never more.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+use v8;
+
+use crate::event_loop::{EventLoop, OpCompletion, OpId};
+use crate::module_loader::{ModuleLoader, ModuleLoaderConfig};
+use crate::ops::dispatch::OpRegistry;
+use crate::permissions::{ChildPermission, ChildPermissions, Permissions};
+use crate::runtime::{JsRuntime, RuntimeConfig, RuntimeError, RuntimeResult};
+use crate::structured_clone::{self, SerializedValue};
+
+/// Event sent from a worker to the runtime that created it
+pub(crate) enum WorkerEvent {
+    /// Message posted with `postMessage()` in the worker
+    Message(SerializedValue),
+    /// Uncaught error that stopped the worker
+    Error(String),
+}
+
+/// Receiver of the events of one worker (shared with its pending op)
+pub(crate) type WorkerEvents = Rc<RefCell<UnboundedReceiver<WorkerEvent>>>;
+
+/// Parent side of a running worker
+struct WorkerHandle {
+    /// Messages to the worker (`None` once terminated)
+    messages: Option<UnboundedSender<SerializedValue>>,
+    /// Dropped to stop the worker's event loop (`None` once terminated)
+    shutdown: Option<oneshot::Sender<()>>,
+    /// Handle of the worker's isolate (set once the worker thread created it)
+    isolate: Arc<Mutex<Option<v8::IsolateHandle>>>,
+    /// Set when the parent terminated the worker
+    terminated: Arc<AtomicBool>,
+    /// Worker thread
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WorkerHandle {
+    /// Stop the worker, interrupting any JavaScript it is running
+    ///
+    /// Closing the shutdown channel also wakes a worker that is idle in its
+    /// event loop (even during top-level await), which then discards its
+    /// remaining work.
+    fn terminate(&mut self) {
+        self.terminated.store(true, Ordering::SeqCst);
+        if let Some(isolate) = self.isolate.lock().unwrap().as_ref() {
+            isolate.terminate_execution();
+        }
+        self.messages = None;
+        self.shutdown = None;
+    }
+}
+
+/// Workers created by a runtime
+///
+/// Shared as `Rc<RefCell<WorkerHost>>` through an isolate slot.
+pub(crate) struct WorkerHost {
+    /// Configuration of worker runtimes
+    config: RuntimeConfig,
+    /// Module loader configuration of worker runtimes
+    module_config: Option<ModuleLoaderConfig>,
+    /// Ops exposed to worker runtimes (the parent's)
+    ops: OpRegistry,
+    /// Running workers by ID (the ID of the op receiving their events)
+    workers: HashMap<OpId, WorkerHandle>,
+    /// Threads of terminated workers that may still be shutting down
+    terminated: Vec<JoinHandle<()>>,
+}
+
+impl WorkerHost {
+    /// Create a host for the workers of a runtime
+    ///
+    /// Workers inherit the runtime's configuration and ops, except for
+    /// debugging features that only make sense once per process.
+    pub(crate) fn new(config: &RuntimeConfig, ops: &OpRegistry) -> Self {
+        let config = RuntimeConfig {
+            enable_inspector: false,
+            inspect_brk: false,
+            heap_snapshot_signal: None,
+            ..config.clone()
+        };
+        Self {
+            config,
+            module_config: None,
+            ops: ops.clone(),
+            workers: HashMap::new(),
+            terminated: Vec::new(),
+        }
+    }
+
+    /// Get the worker host stored in the isolate
+    ///
+    /// # Panics
+    ///
+    /// Panics if the isolate was not created by `JsRuntime`.
+    pub(crate) fn from_isolate(isolate: &v8::Isolate) -> Rc<RefCell<WorkerHost>> {
+        isolate
+            .get_slot::<Rc<RefCell<WorkerHost>>>()
+            .expect("WorkerHost not installed in isolate")
+            .clone()
+    }
+
+    /// Set the module loader configuration used by workers
+    pub(crate) fn set_module_config(&mut self, module_config: ModuleLoaderConfig) {
+        self.module_config = Some(module_config);
+    }
+
+    /// Resolve the specifier of a worker script
+    ///
+    /// `file://` URLs are converted to paths; relative specifiers are
+    /// resolved against the main module (or the base directory).
+    ///
+    /// # Arguments
+    /// * `specifier` - Specifier passed to `new Worker()`
+    /// * `main` - Resolved specifier of the parent's main module
+    /// * `permissions` - Parent permissions (used by the module loader)
+    pub(crate) fn resolve(
+        &self,
+        specifier: &str,
+        main: Option<&str>,
+        permissions: &Permissions,
+    ) -> Result<String, String> {
+        if specifier.starts_with("file://") {
+            return url::Url::parse(specifier)
+                .ok()
+                .and_then(|url| url.to_file_path().ok())
+                .map(|path| path.to_string_lossy().to_string())
+                .ok_or_else(|| format!("Invalid worker URL '{}'", specifier));
+        }
+
+        let module_config = self.module_config.clone().unwrap_or_default();
+        ModuleLoader::new(permissions.clone(), module_config)
+            .resolve(specifier, main)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Start a worker on a new thread
+    ///
+    /// # Arguments
+    /// * `id` - Worker ID (also the ID of the op receiving its events)
+    /// * `specifier` - Resolved specifier of the worker module
+    /// * `name` - Worker name (`self.name` in the worker)
+    /// * `permissions` - Permissions of the worker
+    ///
+    /// # Returns
+    /// The receiver of the worker's events
+    pub(crate) fn spawn(
+        &mut self,
+        id: OpId,
+        specifier: String,
+        name: String,
+        permissions: Permissions,
+    ) -> std::io::Result<WorkerEvents> {
+        let (messages_tx, messages_rx) = mpsc::unbounded_channel();
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let isolate = Arc::new(Mutex::new(None));
+        let terminated = Arc::new(AtomicBool::new(false));
+
+        let options = WorkerOptions {
+            specifier,
+            name,
+            config: self.config.clone(),
+            module_config: self.module_config.clone().unwrap_or_default(),
+            ops: self.ops.clone(),
+            permissions,
+            messages: messages_rx,
+            shutdown: shutdown_rx,
+            events: events_tx,
+            isolate: isolate.clone(),
+            terminated: terminated.clone(),
+        };
+        let thread = std::thread::Builder::new()
+            .name(format!("ferrum-worker-{}", id))
+            .spawn(move || run_worker(options))?;
+
+        let events = Rc::new(RefCell::new(events_rx));
+        self.workers.insert(
+            id,
+            WorkerHandle {
+                messages: Some(messages_tx),
+                shutdown: Some(shutdown_tx),
+                isolate,
+                terminated,
+                thread: Some(thread),
+            },
+        );
+        Ok(events)
+    }
+
+    /// Check if a worker is running (created and not terminated)
+    pub(crate) fn contains(&self, id: OpId) -> bool {
+        self.workers.contains_key(&id)
+    }
+
+    /// Send a message to a worker
+    ///
+    /// Messages to terminated or finished workers are dropped.
+    pub(crate) fn post_message(&self, id: OpId, message: SerializedValue) {
+        let sender = self.workers.get(&id).and_then(|worker| worker.messages.as_ref());
+        if let Some(sender) = sender {
+            let _ = sender.send(message);
+        }
+    }
+
+    /// Terminate a worker
+    ///
+    /// The caller is responsible for cancelling the op receiving its events.
+    pub(crate) fn terminate(&mut self, id: OpId) {
+        self.terminated.retain(|thread| !thread.is_finished());
+        if let Some(mut worker) = self.workers.remove(&id) {
+            worker.terminate();
+            self.terminated.extend(worker.thread.take());
+            tracing::debug!("Worker {} terminated", id);
+        }
+    }
+}
+
+impl Drop for WorkerHost {
+    fn drop(&mut self) {
+        for worker in self.workers.values_mut() {
+            worker.terminate();
+        }
+        let threads = self.workers.values_mut().filter_map(|worker| worker.thread.take());
+        for thread in threads.chain(self.terminated.drain(..)).collect::<Vec<_>>() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Parse the `deno.permissions` option of `new Worker()`
+///
+/// Accepts `"inherit"`, `"none"` or an object whose `read`, `write`,
+/// `net`, `env` and `run` fields are `"inherit"`, a boolean or a list of
+/// allowed entries. Fields for permission kinds this runtime does not
+/// have are ignored.
+///
+/// # Arguments
+/// * `json` - The option serialized as JSON
+pub fn parse_child_permissions(json: &str) -> Result<ChildPermissions, String> {
+    let value: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+    match value {
+        serde_json::Value::String(mode) if mode == "inherit" => Ok(ChildPermissions::default()),
+        serde_json::Value::String(mode) if mode == "none" => Ok(ChildPermissions::none()),
+        serde_json::Value::Object(kinds) => {
+            let mut permissions = ChildPermissions::default();
+            for (kind, value) in kinds {
+                let target = match kind.as_str() {
+                    "read" => &mut permissions.read,
+                    "write" => &mut permissions.write,
+                    "net" => &mut permissions.net,
+                    "env" => &mut permissions.env,
+                    "run" => &mut permissions.run,
+                    _ => continue,
+                };
+                *target = parse_child_permission(&kind, value)?;
+            }
+            Ok(permissions)
+        }
+        _ => Err("Worker permissions must be \"inherit\", \"none\" or an object".to_string()),
+    }
+}
+
+/// Parse the value of one permission kind in `deno.permissions`
+fn parse_child_permission(kind: &str, value: serde_json::Value) -> Result<ChildPermission, String> {
+    match value {
+        serde_json::Value::String(mode) if mode == "inherit" => Ok(ChildPermission::Inherit),
+        serde_json::Value::Bool(true) => Ok(ChildPermission::GrantAll),
+        serde_json::Value::Bool(false) => Ok(ChildPermission::Deny),
+        serde_json::Value::Array(entries) => entries
+            .into_iter()
+            .map(|entry| match entry {
+                serde_json::Value::String(entry) => Ok(entry),
+                _ => Err(format!("Worker {} permission entries must be strings", kind)),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(ChildPermission::Grant),
+        _ => Err(format!(
+            "Worker {} permission must be \"inherit\", a boolean or a list of strings",
+            kind
+        )),
+    }
+}
+
+/// Everything a worker thread needs to start
+struct WorkerOptions {
+    specifier: String,
+    name: String,
+    config: RuntimeConfig,
+    module_config: ModuleLoaderConfig,
+    ops: OpRegistry,
+    permissions: Permissions,
+    messages: UnboundedReceiver<SerializedValue>,
+    shutdown: oneshot::Receiver<()>,
+    events: UnboundedSender<WorkerEvent>,
+    isolate: Arc<Mutex<Option<v8::IsolateHandle>>>,
+    terminated: Arc<AtomicBool>,
+}
+
+/// Worker thread entry point
+///
+/// Errors that stop the worker are reported to the parent, unless the
+/// worker was terminated or closed itself.
+fn run_worker(options: WorkerOptions) {
+    let events = options.events.clone();
+    let terminated = options.terminated.clone();
+    let closed = Rc::new(Cell::new(false));
+
+    if let Err(e) = run_worker_impl(options, closed.clone()) {
+        if !terminated.load(Ordering::SeqCst) && !closed.get() {
+            let message = match &e {
+                RuntimeError::ExecutionError(error)
+                | RuntimeError::CompilationError(error)
+                | RuntimeError::UnhandledRejection(error) => error.to_string(),
+                _ => e.to_string(),
+            };
+            tracing::debug!("Worker stopped with error: {}", message);
+            let _ = events.send(WorkerEvent::Error(message));
+        }
+    }
+}
+
+/// Create the worker runtime, run its module and then its event loop
+fn run_worker_impl(options: WorkerOptions, closed: Rc<Cell<bool>>) -> RuntimeResult<()> {
+    let mut runtime = JsRuntime::with_ops(options.config, options.permissions, options.ops)?;
+    runtime.setup_module_loader(options.module_config);
+
+    // Either the parent sees the handle or the worker sees the flag
+    *options.isolate.lock().unwrap() = Some(runtime.thread_safe_handle());
+    if options.terminated.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    let scope_state = WorkerScope {
+        events: options.events,
+        closed: closed.clone(),
+    };
+    let dispatch = runtime
+        .with_context_scope(|scope| bootstrap_worker_scope(scope, &options.name, scope_state))
+        .ok_or_else(|| RuntimeError::InitializationError("Failed to bootstrap worker scope".to_string()))?;
+
+    // Armed first so termination also wakes a module stuck in top-level await
+    runtime.with_context_scope(|scope| arm_worker_shutdown(scope, options.shutdown));
+
+    runtime.execute_module(&options.specifier)?;
+
+    // Messages posted before the module finished evaluating wait in the channel
+    if !closed.get() {
+        let messages = Rc::new(RefCell::new(options.messages));
+        runtime.with_context_scope(|scope| arm_worker_messages(scope, messages, Rc::new(dispatch), closed));
+    }
+    runtime.run_event_loop()
+}
+
+/// State of the worker global scope (stored in the worker's isolate slot)
+struct WorkerScope {
+    /// Events to the parent
+    events: UnboundedSender<WorkerEvent>,
+    /// Set by `close()`
+    closed: Rc<Cell<bool>>,
+}
+
+/// JavaScript installing `self`, `name`, `postMessage` and `close`
+const WORKER_SCOPE_JS: &str = include_str!("js/worker_scope.js");
+
+/// Install the worker global scope
+///
+/// # Returns
+/// The function dispatching `message` events on the worker's global object,
+/// or `None` if an exception was thrown
+fn bootstrap_worker_scope(
+    scope: &mut v8::HandleScope,
+    name: &str,
+    state: WorkerScope,
+) -> Option<v8::Global<v8::Function>> {
+    scope.set_slot(Rc::new(state));
+
+    let ops = v8::Object::new(scope);
+    let methods: [(&str, v8::FunctionCallback); 2] = [
+        ("postMessage", v8::MapFnTo::map_fn_to(op_worker_scope_post_message)),
+        ("close", v8::MapFnTo::map_fn_to(op_worker_scope_close)),
+    ];
+    for (method, callback) in methods {
+        let key = v8::String::new(scope, method).unwrap();
+        let func = v8::Function::builder_raw(callback).build(scope)?;
+        ops.set(scope, key.into(), func.into());
+    }
+
+    let source = v8::String::new(scope, WORKER_SCOPE_JS).unwrap();
+    let install = v8::Script::compile(scope, source, None)?.run(scope)?;
+    let install = v8::Local::<v8::Function>::try_from(install).ok()?;
+
+    let name = v8::String::new(scope, name).unwrap();
+    let recv = v8::undefined(scope).into();
+    let dispatch = install.call(scope, recv, &[ops.into(), name.into()])?;
+    let dispatch = v8::Local::<v8::Function>::try_from(dispatch).ok()?;
+    Some(v8::Global::new(scope, dispatch))
+}
+
+/// Stop the worker's event loop once the parent terminates the worker
+///
+/// The parent never sends on the channel; terminating the worker (or
+/// dropping its handle) closes it.
+fn arm_worker_shutdown(scope: &mut v8::HandleScope, shutdown: oneshot::Receiver<()>) {
+    let context = scope.get_current_context();
+    let context = v8::Global::new(scope, context);
+    let event_loop = EventLoop::from_isolate(scope);
+    let id = event_loop.borrow_mut().next_op_id();
+
+    let future = Box::pin(async move {
+        let _ = shutdown.await;
+        Box::new(|scope: &mut v8::HandleScope| EventLoop::from_isolate(scope).borrow_mut().clear()) as OpCompletion
+    });
+    event_loop.borrow_mut().schedule(id, context, future);
+}
+
+/// Wait for the next message from the parent on the worker's event loop
+fn arm_worker_messages(
+    scope: &mut v8::HandleScope,
+    messages: Rc<RefCell<UnboundedReceiver<SerializedValue>>>,
+    dispatch: Rc<v8::Global<v8::Function>>,
+    closed: Rc<Cell<bool>>,
+) {
+    let context = scope.get_current_context();
+    let context = v8::Global::new(scope, context);
+    let event_loop = EventLoop::from_isolate(scope);
+    let id = event_loop.borrow_mut().next_op_id();
+
+    let future = Box::pin(async move {
+        let message = std::future::poll_fn(|cx| messages.borrow_mut().poll_recv(cx)).await;
+        Box::new(move |scope: &mut v8::HandleScope| {
+            deliver_worker_message(scope, message, messages, dispatch, closed)
+        }) as OpCompletion
+    });
+    event_loop.borrow_mut().schedule(id, context, future);
+}
+
+/// Dispatch a message from the parent and wait for the next one
+fn deliver_worker_message(
+    scope: &mut v8::HandleScope,
+    message: Option<SerializedValue>,
+    messages: Rc<RefCell<UnboundedReceiver<SerializedValue>>>,
+    dispatch: Rc<v8::Global<v8::Function>>,
+    closed: Rc<Cell<bool>>,
+) {
+    // The parent terminated the worker (or went away): drop remaining work
+    let Some(message) = message else {
+        EventLoop::from_isolate(scope).borrow_mut().clear();
+        return;
+    };
+
+    let (kind, data) = deserialize_message(scope, message);
+    let kind = v8::String::new(scope, kind).unwrap();
+    let func = v8::Local::new(scope, &*dispatch);
+    let recv = v8::undefined(scope).into();
+
+    // On exception, stop here and let the event loop report it
+    if func.call(scope, recv, &[kind.into(), data]).is_none() {
+        return;
+    }
+
+    if !closed.get() {
+        arm_worker_messages(scope, messages, dispatch, closed);
+    }
+}
+
+/// Deserialize a posted message
+///
+/// # Returns
+/// `("message", value)`, or `("messageerror", undefined)` if the message
+/// could not be deserialized
+pub(crate) fn deserialize_message<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    message: SerializedValue,
+) -> (&'static str, v8::Local<'s, v8::Value>) {
+    let value = {
+        let tc_scope = &mut v8::TryCatch::new(scope);
+        structured_clone::deserialize(tc_scope, message).map(|value| v8::Global::new(tc_scope, value))
+    };
+    match value {
+        Some(value) => ("message", v8::Local::new(scope, value)),
+        None => ("messageerror", v8::undefined(scope).into()),
+    }
+}
+
+/// Get the worker scope state stored in the isolate
+fn worker_scope(isolate: &v8::Isolate) -> Option<Rc<WorkerScope>> {
+    isolate.get_slot::<Rc<WorkerScope>>().cloned()
+}
+
+/// postMessage() implementation in the worker global scope
+///
+/// Clones the message and sends it to the parent's `Worker` object.
+/// Throws a `DataCloneError` for values that cannot be cloned.
+///
+/// # JavaScript Signature
+/// ```javascript
+/// function postMessage(message: any, transfer: ArrayBuffer[]): void
+/// ```
+fn op_worker_scope_post_message(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(state) = worker_scope(scope) else {
+        return;
+    };
+    let Some(transfer) = structured_clone::transfer_list(scope, args.get(1)) else {
+        return;
+    };
+    let Some(message) = structured_clone::serialize(scope, args.get(0), &transfer) else {
+        return;
+    };
+    if !state.closed.get() {
+        let _ = state.events.send(WorkerEvent::Message(message));
+    }
+    rv.set_undefined();
+}
+
+/// close() implementation in the worker global scope
+///
+/// Stops the worker: pending timers and messages are discarded and the
+/// running script is terminated.
+///
+/// # JavaScript Signature
+/// ```javascript
+/// function close(): void
+/// ```
+fn op_worker_scope_close(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    if let Some(state) = worker_scope(scope) {
+        state.closed.set(true);
+    }
+    EventLoop::from_isolate(scope).borrow_mut().clear();
+    scope.terminate_execution();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_child_permissions() {
+        assert_eq!(parse_child_permissions("\"inherit\"").unwrap(), ChildPermissions::default());
+        assert_eq!(parse_child_permissions("\"none\"").unwrap(), ChildPermissions::none());
+
+        let permissions =
+            parse_child_permissions(r#"{ "read": ["/tmp"], "net": false, "env": true, "hrtime": false }"#).unwrap();
+        assert_eq!(permissions.read, ChildPermission::Grant(vec!["/tmp".to_string()]));
+        assert_eq!(permissions.write, ChildPermission::Inherit);
+        assert_eq!(permissions.net, ChildPermission::Deny);
+        assert_eq!(permissions.env, ChildPermission::GrantAll);
+
+        assert!(parse_child_permissions("\"all\"").is_err());
+        assert!(parse_child_permissions(r#"{ "read": [1] }"#).is_err());
+        assert!(parse_child_permissions(r#"{ "run": "yes" }"#).is_err());
+    }
+}
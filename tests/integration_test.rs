@@ -83,6 +83,30 @@ fn test_runtime_error() {
     assert!(result.is_err());
 }
 
+#[test]
+fn test_execute_and_wait_with_timers() {
+    init_v8_for_tests();
+
+    let mut runtime = create_unsafe_runtime().unwrap();
+    let result = runtime.execute_and_wait(
+        r#"
+        function sleep(ms) {
+            return new Promise(resolve => setTimeout(resolve, ms));
+        }
+        (async () => {
+            await sleep(10);
+            await sleep(10);
+            throw new Error("reached after timers");
+        })().catch(e => setTimeout(() => { throw e; }, 0));
+        "#,
+        None,
+    );
+
+    // The error is only observable if the event loop drove both timers
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("reached after timers"));
+}
+
 #[test]
 fn test_file_execution() {
     init_v8_for_tests();
@@ -186,12 +210,12 @@ fn test_stats_tracking() {
 fn test_memory_usage() {
     init_v8_for_tests();
 
-    let runtime = create_unsafe_runtime().unwrap();
+    let mut runtime = create_unsafe_runtime().unwrap();
     let memory = runtime.get_memory_usage();
 
     assert!(memory.is_ok());
     let (used, total) = memory.unwrap();
-    // Memory usage might be 0 due to placeholder implementation
+    assert!(used > 0);
     assert!(total >= used);
 }
 
@@ -384,8 +408,8 @@ fn test_simple_module_execution() {
     let result = runtime.execute_module(file_path.to_str().unwrap());
 
     assert!(result.is_ok());
-    // ES modules return a Promise object
-    assert_eq!(result.unwrap(), "[object Promise]");
+    // Module evaluation settles with undefined
+    assert_eq!(result.unwrap(), "undefined");
 }
 
 /// Test module execution with Deno API calls
@@ -428,8 +452,134 @@ fn test_module_with_deno_api() {
     let result = runtime.execute_module(file_path.to_str().unwrap());
 
     assert!(result.is_ok());
-    // ES modules return a Promise object
-    assert_eq!(result.unwrap(), "[object Promise]");
+    // Module evaluation settles with undefined
+    assert_eq!(result.unwrap(), "undefined");
+}
+
+/// Test that file system ops return promises settled by the event loop
+#[test]
+fn test_async_file_operations() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("async.txt");
+
+    let config = RuntimeConfig::default();
+    let permissions = Permissions::allow_all();
+    let mut runtime = ferrum::JsRuntime::new(config, permissions).unwrap();
+
+    let code = format!(
+        r#"
+        const path = {:?};
+        const written = Deno.writeTextFile(path, "async");
+        globalThis.isPromise = written instanceof Promise;
+        written
+            .then(() => Deno.readTextFile(path))
+            .then((content) => {{ globalThis.content = content; }});
+        "#,
+        path.to_str().unwrap()
+    );
+    runtime.execute_and_wait(&code, None).unwrap();
+
+    assert_eq!(runtime.execute("isPromise", None).unwrap(), "true");
+    assert_eq!(runtime.execute("content", None).unwrap(), "async");
+
+    // The *Sync variants return their result directly
+    let result = runtime
+        .execute(r#"Deno.writeTextFileSync(path, "sync"); Deno.readTextFileSync(path)"#, None)
+        .unwrap();
+    assert_eq!(result, "sync");
+}
+
+/// Test the typed built-in file system ops and their argument checks
+#[test]
+fn test_builtin_fs_ops() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path().join("nested/dir");
+
+    let config = RuntimeConfig::default();
+    let permissions = Permissions::allow_all();
+    let mut runtime = ferrum::JsRuntime::new(config, permissions).unwrap();
+
+    let code = format!(
+        r#"
+        const dir = {:?};
+        Deno.mkdirSync(dir, {{ recursive: true }});
+        Deno.writeTextFileSync(dir + "/a.txt", "abc");
+        const info = Deno.statSync(dir + "/a.txt");
+        [Deno.statSync(dir).isDirectory, info.isFile, info.size, typeof info.mtime].join()
+        "#,
+        dir.to_str().unwrap()
+    );
+    assert_eq!(runtime.execute(&code, None).unwrap(), "true,true,3,number");
+
+    // Arguments of the wrong type throw, or reject for async ops
+    let result = runtime
+        .execute("try { Deno.readTextFileSync(42); } catch (e) { e.message }", None)
+        .unwrap();
+    assert!(result.contains("Invalid arguments"), "Unexpected error: {}", result);
+    runtime
+        .execute_and_wait("Deno.exists(42).catch((e) => { globalThis.rejected = e.message; });", None)
+        .unwrap();
+    let rejected = runtime.execute("rejected", None).unwrap();
+    assert!(rejected.contains("Invalid arguments"), "Unexpected error: {}", rejected);
+
+    // The options object is optional
+    runtime
+        .execute_and_wait("Deno.remove(dir, { recursive: true }).then(() => Deno.mkdir(dir))", None)
+        .unwrap();
+    assert_eq!(runtime.execute("Deno.existsSync(dir + '/a.txt')", None).unwrap(), "false");
+}
+
+/// Test that clearTimeout cannot cancel an async op that is not a timer
+#[test]
+fn test_clear_timeout_ignores_async_ops() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("in-flight.txt");
+    std::fs::write(&path, "still here").unwrap();
+
+    let config = RuntimeConfig::default();
+    let permissions = Permissions::allow_all();
+    let mut runtime = ferrum::JsRuntime::new(config, permissions).unwrap();
+
+    // Op IDs are sequential, so the read is scheduled right after the timer
+    let code = format!(
+        r#"
+        const timer = setTimeout(() => {{}}, 0);
+        const read = Deno.readTextFile({:?});
+        clearTimeout(timer + 1);
+        clearInterval(timer + 1);
+        read.then((content) => {{ globalThis.content = content; }});
+        "#,
+        path.to_str().unwrap()
+    );
+    runtime.execute_and_wait(&code, None).unwrap();
+
+    assert_eq!(runtime.execute("content", None).unwrap(), "still here");
+}
+
+/// Test that a denied async file op rejects its promise
+#[test]
+fn test_async_file_permission_denied() {
+    init_v8_for_tests();
+
+    let config = RuntimeConfig::default();
+    let permissions = Permissions::default();
+    let mut runtime = ferrum::JsRuntime::new(config, permissions).unwrap();
+
+    runtime
+        .execute_and_wait(
+            r#"Deno.readTextFile("/etc/passwd").catch((e) => { globalThis.error = e.message; });"#,
+            None,
+        )
+        .unwrap();
+
+    let error = runtime.execute("error", None).unwrap();
+    assert!(error.contains("Permission"), "Unexpected error: {}", error);
 }
 
 /// Test module loader setup
@@ -496,8 +646,8 @@ fn test_module_with_import_map() {
     let result = runtime.execute_module(file_path.to_str().unwrap());
 
     assert!(result.is_ok());
-    // ES modules return a Promise object
-    assert_eq!(result.unwrap(), "[object Promise]");
+    // Module evaluation settles with undefined
+    assert_eq!(result.unwrap(), "undefined");
 }
 
 /// Test module with console output
@@ -531,8 +681,69 @@ fn test_module_with_console() {
     let result = runtime.execute_module(file_path.to_str().unwrap());
 
     assert!(result.is_ok());
-    // ES modules return a Promise object
-    assert_eq!(result.unwrap(), "[object Promise]");
+    // Module evaluation settles with undefined
+    assert_eq!(result.unwrap(), "undefined");
+}
+
+/// Test that top-level await waits for timers to settle
+#[test]
+fn test_module_top_level_await() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = temp_dir.path().join("tla.mjs");
+    let out_path = temp_dir.path().join("out.txt");
+
+    std::fs::write(
+        &file_path,
+        format!(
+            r#"
+            await new Promise(resolve => setTimeout(resolve, 10));
+            await Deno.writeTextFile({:?}, "after await");
+            "#,
+            out_path.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+
+    let config = RuntimeConfig::default();
+    let permissions = Permissions::allow_all();
+    let mut runtime = ferrum::JsRuntime::new(config, permissions).unwrap();
+    runtime.setup_module_loader(ModuleLoaderConfig::default());
+
+    let result = runtime.execute_module(file_path.to_str().unwrap());
+
+    assert_eq!(result.unwrap(), "undefined");
+    assert_eq!(std::fs::read_to_string(&out_path).unwrap(), "after await");
+}
+
+/// Test that a rejected top-level await surfaces the rejection reason
+#[test]
+fn test_module_top_level_await_rejection() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = temp_dir.path().join("tla-reject.mjs");
+
+    std::fs::write(
+        &file_path,
+        r#"
+        await new Promise((_, reject) => setTimeout(() => reject(new Error("tla failed")), 5));
+        "#,
+    )
+    .unwrap();
+
+    let config = RuntimeConfig::default();
+    let permissions = Permissions::allow_all();
+    let mut runtime = ferrum::JsRuntime::new(config, permissions).unwrap();
+    runtime.setup_module_loader(ModuleLoaderConfig::default());
+
+    let result = runtime.execute_module(file_path.to_str().unwrap());
+
+    match result {
+        Err(ferrum::RuntimeError::ExecutionError(error)) => assert!(error.message.contains("tla failed")),
+        other => panic!("Expected ExecutionError, got {:?}", other),
+    }
 }
 
 /// Test runtime with module loader
@@ -588,3 +799,601 @@ fn test_module_error_handling() {
     }
 }
 
+
+/// Test a module graph with nested relative imports and a shared dependency
+#[test]
+fn test_module_with_imports() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let lib_dir = temp_dir.path().join("lib");
+    std::fs::create_dir(&lib_dir).unwrap();
+    let main_path = temp_dir.path().join("main.mjs");
+    let out_path = temp_dir.path().join("out.txt");
+
+    std::fs::write(
+        temp_dir.path().join("counter.mjs"),
+        r#"
+        globalThis.loads = (globalThis.loads || 0) + 1;
+        export const base = 40;
+        "#,
+    )
+    .unwrap();
+    std::fs::write(
+        lib_dir.join("math.mjs"),
+        r#"
+        import { base } from "../counter.mjs";
+        export function answer() { return base + 2; }
+        "#,
+    )
+    .unwrap();
+    std::fs::write(
+        &main_path,
+        format!(
+            r#"
+            import {{ answer }} from "./lib/math.mjs";
+            import {{ base }} from "./counter.mjs";
+            await Deno.writeTextFile({:?}, `${{answer()}} ${{base}} ${{globalThis.loads}}`);
+            "#,
+            out_path.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+
+    let config = RuntimeConfig::default();
+    let permissions = Permissions::allow_all();
+    let mut runtime = ferrum::JsRuntime::new(config, permissions).unwrap();
+    runtime.setup_module_loader(ModuleLoaderConfig::default());
+
+    let result = runtime.execute_module(main_path.to_str().unwrap());
+
+    assert!(result.is_ok(), "Module graph failed: {:?}", result.err());
+    assert_eq!(std::fs::read_to_string(&out_path).unwrap(), "42 40 1");
+}
+
+/// Test that an import of a missing module fails with a module error
+#[test]
+fn test_module_with_missing_import() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let main_path = temp_dir.path().join("main.mjs");
+
+    std::fs::write(&main_path, r#"import { x } from "./missing.mjs";"#).unwrap();
+
+    let config = RuntimeConfig::default();
+    let permissions = Permissions::allow_all();
+    let mut runtime = ferrum::JsRuntime::new(config, permissions).unwrap();
+    runtime.setup_module_loader(ModuleLoaderConfig::default());
+
+    let result = runtime.execute_module(main_path.to_str().unwrap());
+
+    match result {
+        Err(ferrum::RuntimeError::ModuleError(msg)) => assert!(msg.contains("missing.mjs")),
+        other => panic!("Expected ModuleError, got {:?}", other),
+    }
+}
+
+/// Test dynamic import() from a module, including a failing import
+#[test]
+fn test_module_dynamic_import() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let main_path = temp_dir.path().join("main.mjs");
+    let out_path = temp_dir.path().join("out.txt");
+
+    std::fs::write(
+        temp_dir.path().join("plugin.mjs"),
+        r#"
+        await new Promise(resolve => setTimeout(resolve, 5));
+        export const name = "plugin";
+        "#,
+    )
+    .unwrap();
+    std::fs::write(
+        &main_path,
+        format!(
+            r#"
+            const plugin = await import("./plugin.mjs");
+            let missing = "resolved";
+            try {{
+                await import("./missing.mjs");
+            }} catch (e) {{
+                missing = "rejected";
+            }}
+            await Deno.writeTextFile({:?}, `${{plugin.name}} ${{missing}}`);
+            "#,
+            out_path.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+
+    let config = RuntimeConfig::default();
+    let permissions = Permissions::allow_all();
+    let mut runtime = ferrum::JsRuntime::new(config, permissions).unwrap();
+    runtime.setup_module_loader(ModuleLoaderConfig::default());
+
+    let result = runtime.execute_module(main_path.to_str().unwrap());
+
+    assert!(result.is_ok(), "Dynamic import failed: {:?}", result.err());
+    assert_eq!(std::fs::read_to_string(&out_path).unwrap(), "plugin rejected");
+}
+
+/// Test dynamic import() from a classic script
+#[test]
+fn test_script_dynamic_import() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let plugin_path = temp_dir.path().join("plugin.mjs");
+    let out_path = temp_dir.path().join("out.txt");
+
+    std::fs::write(
+        &plugin_path,
+        format!(
+            r#"Deno.writeTextFile({:?}, "loaded");"#,
+            out_path.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+
+    let config = RuntimeConfig::default();
+    let permissions = Permissions::allow_all();
+    let mut runtime = ferrum::JsRuntime::new(config, permissions).unwrap();
+    runtime.setup_module_loader(ModuleLoaderConfig::default());
+
+    let code = format!("import({:?});", plugin_path.to_str().unwrap());
+    let result = runtime.execute_and_wait(&code, None);
+
+    assert!(result.is_ok());
+    assert_eq!(std::fs::read_to_string(&out_path).unwrap(), "loaded");
+}
+
+/// Test import.meta properties for the entry module and a dependency
+#[test]
+fn test_module_import_meta() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let main_path = temp_dir.path().join("main.mjs");
+    let dep_path = temp_dir.path().join("dep.mjs");
+    let out_path = temp_dir.path().join("out.txt");
+
+    std::fs::write(&dep_path, "export const depMain = import.meta.main;").unwrap();
+    std::fs::write(
+        &main_path,
+        format!(
+            r#"
+            import {{ depMain }} from "./dep.mjs";
+            await Deno.writeTextFile({:?}, [
+                import.meta.url,
+                import.meta.main,
+                depMain,
+                import.meta.filename,
+                import.meta.dirname,
+                import.meta.resolve("./dep.mjs"),
+            ].join("\n"));
+            "#,
+            out_path.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+
+    let config = RuntimeConfig::default();
+    let permissions = Permissions::allow_all();
+    let mut runtime = ferrum::JsRuntime::new(config, permissions).unwrap();
+    runtime.setup_module_loader(ModuleLoaderConfig::default());
+
+    let result = runtime.execute_module(main_path.to_str().unwrap());
+    assert!(result.is_ok(), "Module failed: {:?}", result.err());
+
+    let expected = [
+        url::Url::from_file_path(&main_path).unwrap().to_string(),
+        "true".to_string(),
+        "false".to_string(),
+        main_path.to_str().unwrap().to_string(),
+        temp_dir.path().to_str().unwrap().to_string(),
+        url::Url::from_file_path(&dep_path).unwrap().to_string(),
+    ]
+    .join("\n");
+    assert_eq!(std::fs::read_to_string(&out_path).unwrap(), expected);
+}
+
+#[test]
+fn test_snapshot_cache() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+
+    let first = ferrum::snapshot::load_or_create_snapshot(temp_dir.path()).unwrap();
+    let cached: Vec<_> = std::fs::read_dir(temp_dir.path()).unwrap().collect();
+    assert_eq!(cached.len(), 1);
+
+    let second = ferrum::snapshot::load_or_create_snapshot(temp_dir.path()).unwrap();
+    assert_eq!(first, second);
+
+    let mut runtime =
+        ferrum::JsRuntime::from_snapshot(RuntimeConfig::default(), Permissions::allow_all(), second).unwrap();
+    let result = runtime.execute("typeof Deno.writeTextFile", None).unwrap();
+    assert_eq!(result, "function");
+}
+
+#[test]
+fn test_module_code_cache() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let cache_dir = temp_dir.path().join("code_cache");
+    let main_path = temp_dir.path().join("main.mjs");
+    let dep_path = temp_dir.path().join("dep.mjs");
+    std::fs::write(&dep_path, "export const value = 21;\n").unwrap();
+    std::fs::write(&main_path, "import { value } from './dep.mjs';\nglobalThis.answer = value * 2;\n").unwrap();
+
+    let config = RuntimeConfig {
+        code_cache_dir: Some(cache_dir.clone()),
+        ..RuntimeConfig::default()
+    };
+
+    for _ in 0..2 {
+        let mut runtime = ferrum::JsRuntime::new(config.clone(), Permissions::allow_all()).unwrap();
+        runtime.setup_module_loader(ModuleLoaderConfig::default());
+        let result = runtime.execute_module(main_path.to_str().unwrap());
+        assert!(result.is_ok(), "Module failed: {:?}", result.err());
+        assert_eq!(runtime.execute("answer", None).unwrap(), "42");
+    }
+
+    // One entry per module
+    assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 2);
+
+    // Corrupted entries are rejected and replaced
+    for entry in std::fs::read_dir(&cache_dir).unwrap() {
+        std::fs::write(entry.unwrap().path(), b"not a code cache").unwrap();
+    }
+    let mut runtime = ferrum::JsRuntime::new(config, Permissions::allow_all()).unwrap();
+    runtime.setup_module_loader(ModuleLoaderConfig::default());
+    assert!(runtime.execute_module(main_path.to_str().unwrap()).is_ok());
+    assert_eq!(runtime.execute("answer", None).unwrap(), "42");
+    for entry in std::fs::read_dir(&cache_dir).unwrap() {
+        assert_ne!(std::fs::read(entry.unwrap().path()).unwrap(), b"not a code cache");
+    }
+}
+
+#[test]
+fn test_inspector_discovery() {
+    use std::io::{Read, Write};
+
+    init_v8_for_tests();
+
+    let config = RuntimeConfig {
+        enable_inspector: true,
+        inspector_port: 0,
+        ..RuntimeConfig::default()
+    };
+    let mut runtime = ferrum::JsRuntime::new(config, Permissions::allow_all()).unwrap();
+    let inspector = runtime.inspector().unwrap();
+    let address = inspector.address().unwrap();
+    let websocket_url = inspector.websocket_url().unwrap();
+
+    let mut stream = std::net::TcpStream::connect(address).unwrap();
+    let request = format!("GET /json/list HTTP/1.1\r\nHost: {}\r\n\r\n", address);
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains(&format!("\"webSocketDebuggerUrl\":\"{}\"", websocket_url)));
+
+    // Console calls still reach the native implementation
+    let result = runtime.execute("console.log('inspected'); 1 + 1", None).unwrap();
+    assert_eq!(result, "2");
+}
+
+#[test]
+fn test_deno_write_heap_snapshot() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("script.heapsnapshot");
+    let code = format!("Deno.writeHeapSnapshot({:?})", path.to_str().unwrap());
+
+    // Writing requires write permission
+    let mut runtime = ferrum::JsRuntime::new(RuntimeConfig::default(), Permissions::default()).unwrap();
+    assert!(runtime.execute(&code, None).is_err());
+    assert!(!path.exists());
+
+    let mut runtime = ferrum::JsRuntime::new(RuntimeConfig::default(), Permissions::allow_all()).unwrap();
+    let result = runtime.execute(&code, None).unwrap();
+    assert_eq!(result, path.to_str().unwrap());
+
+    let snapshot: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert!(snapshot["snapshot"]["node_count"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn test_module_rejection_is_not_reported_twice() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let main_path = temp_dir.path().join("main.mjs");
+    std::fs::write(&main_path, "await null;\nthrow new Error('module failed');\n").unwrap();
+
+    let mut runtime = ferrum::JsRuntime::new(RuntimeConfig::default(), Permissions::allow_all()).unwrap();
+    runtime.setup_module_loader(ModuleLoaderConfig::default());
+
+    match runtime.execute_module(main_path.to_str().unwrap()) {
+        Err(ferrum::RuntimeError::ExecutionError(js_error)) => assert_eq!(js_error.message, "module failed"),
+        other => panic!("expected the module's error, got {:?}", other),
+    }
+    assert!(runtime.run_event_loop().is_ok());
+}
+
+#[test]
+fn test_worker_post_message() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(
+        temp_dir.path().join("worker.mjs"),
+        "self.onmessage = (event) => postMessage({ echo: event.data, name: self.name });\n",
+    )
+    .unwrap();
+    let main_path = temp_dir.path().join("main.mjs");
+    std::fs::write(
+        &main_path,
+        r#"
+        const worker = new Worker("./worker.mjs", { type: "module", name: "echo" });
+        globalThis.reply = await new Promise((resolve) => {
+            worker.onmessage = (event) => {
+                worker.terminate();
+                resolve(event.data);
+            };
+            worker.postMessage({ list: [1, 2], when: new Date(0) });
+        });
+        "#,
+    )
+    .unwrap();
+
+    let mut runtime = ferrum::JsRuntime::new(RuntimeConfig::default(), Permissions::allow_all()).unwrap();
+    runtime.setup_module_loader(ModuleLoaderConfig::default());
+    runtime.execute_module(main_path.to_str().unwrap()).unwrap();
+    runtime.run_event_loop().unwrap();
+
+    let reply = runtime.execute("JSON.stringify(reply)", None).unwrap();
+    assert_eq!(reply, r#"{"echo":{"list":[1,2],"when":"1970-01-01T00:00:00.000Z"},"name":"echo"}"#);
+}
+
+#[test]
+fn test_worker_terminate_during_top_level_await() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let worker_path = temp_dir.path().join("worker.mjs");
+    std::fs::write(&worker_path, "await new Promise((resolve) => setTimeout(resolve, 3_600_000));\n").unwrap();
+
+    let started = std::time::Instant::now();
+    {
+        let mut runtime = ferrum::JsRuntime::new(RuntimeConfig::default(), Permissions::allow_all()).unwrap();
+        runtime.setup_module_loader(ModuleLoaderConfig::default());
+
+        let code = format!(
+            r#"
+            globalThis.terminated = new Worker({path:?}, {{ type: "module" }});
+            // Left running: stopped when the runtime is dropped
+            globalThis.running = new Worker({path:?}, {{ type: "module" }});
+            "#,
+            path = worker_path.to_str().unwrap()
+        );
+        runtime.execute(&code, None).unwrap();
+
+        // Let both workers reach their top-level await
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        runtime.execute("terminated.terminate()", None).unwrap();
+    }
+
+    // Dropping the runtime joins both worker threads
+    assert!(started.elapsed() < std::time::Duration::from_secs(30));
+}
+
+#[test]
+fn test_worker_error_event() {
+    init_v8_for_tests();
+
+    let temp_dir = TempDir::new().unwrap();
+    let worker_path = temp_dir.path().join("worker.mjs");
+    std::fs::write(&worker_path, "throw new Error('worker failed');\n").unwrap();
+
+    let mut runtime = ferrum::JsRuntime::new(RuntimeConfig::default(), Permissions::allow_all()).unwrap();
+    runtime.setup_module_loader(ModuleLoaderConfig::default());
+
+    let code = format!(
+        r#"
+        const worker = new Worker({:?}, {{ type: "module" }});
+        worker.onerror = (event) => {{
+            event.preventDefault();
+            globalThis.failure = event.message;
+            worker.terminate();
+        }};
+        "#,
+        worker_path.to_str().unwrap()
+    );
+    runtime.execute_and_wait(&code, None).unwrap();
+
+    let failure = runtime.execute("failure", None).unwrap();
+    assert!(failure.contains("worker failed"), "unexpected error message: {}", failure);
+}
+
+#[test]
+fn test_worker_permissions_cannot_escalate() {
+    init_v8_for_tests();
+
+    let mut runtime = create_runtime().unwrap();
+    let result = runtime
+        .execute(
+            r#"
+            try {
+                new Worker("./worker.mjs", { type: "module", deno: { permissions: { read: true } } });
+                "created";
+            } catch (e) {
+                e.message;
+            }
+            "#,
+            None,
+        )
+        .unwrap();
+    assert!(result.contains("escalate"), "unexpected result: {}", result);
+
+    let result = runtime.execute("try { new Worker('./worker.mjs'); } catch (e) { e.name }", None).unwrap();
+    assert_eq!(result, "TypeError");
+}
+
+#[test]
+fn test_structured_clone() {
+    init_v8_for_tests();
+
+    let mut runtime = create_runtime().unwrap();
+    let result = runtime
+        .execute(
+            r#"
+            const original = {
+                map: new Map([["key", { nested: true }]]),
+                set: new Set([1, 2]),
+                date: new Date(0),
+                regexp: /ab+c/gi,
+                bytes: new Uint16Array([1, 2, 3]),
+                error: new RangeError("out of range"),
+            };
+            original.self = original;
+            const copy = structuredClone(original);
+            [
+                copy !== original,
+                copy.self === copy,
+                copy.map.get("key").nested,
+                copy.map.get("key") !== original.map.get("key"),
+                copy.set.has(2),
+                copy.date.getTime() === 0,
+                copy.regexp.source === "ab+c" && copy.regexp.flags === "gi",
+                copy.bytes instanceof Uint16Array && copy.bytes[2] === 3,
+                copy.error instanceof RangeError && copy.error.message === "out of range",
+            ].every(Boolean);
+            "#,
+            None,
+        )
+        .unwrap();
+    assert_eq!(result, "true");
+
+    let result = runtime
+        .execute(
+            r#"
+            const buffer = new Uint8Array([1, 2, 3]).buffer;
+            const moved = structuredClone({ buffer }, { transfer: [buffer] });
+            `${buffer.byteLength} ${moved.buffer.byteLength}`;
+            "#,
+            None,
+        )
+        .unwrap();
+    assert_eq!(result, "0 3");
+
+    let result = runtime
+        .execute("try { structuredClone(() => {}); } catch (e) { e.name }", None)
+        .unwrap();
+    assert_eq!(result, "DataCloneError");
+
+    let result = runtime
+        .execute(
+            "const b = new ArrayBuffer(1); try { structuredClone(b, { transfer: [b, b] }); } catch (e) { e.name }",
+            None,
+        )
+        .unwrap();
+    assert_eq!(result, "DataCloneError");
+}
+
+#[test]
+fn test_embedder_values() {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize)]
+    struct Request {
+        method: String,
+        path: String,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Response {
+        status: u16,
+        body: String,
+    }
+
+    init_v8_for_tests();
+
+    let mut runtime = create_runtime().unwrap();
+    runtime
+        .execute(
+            r#"
+            globalThis.onRequest = async (request) => ({
+                status: request.method === "GET" ? 200 : 405,
+                body: `${greeting} ${request.path}`,
+            });
+            "#,
+            None,
+        )
+        .unwrap();
+    runtime.set_global("greeting", "hello").unwrap();
+
+    let request = Request {
+        method: "GET".to_string(),
+        path: "/index".to_string(),
+    };
+    let response: Response = runtime.call_function("onRequest", (&request,)).unwrap();
+    assert_eq!(
+        response,
+        Response {
+            status: 200,
+            body: "hello /index".to_string(),
+        }
+    );
+
+    let greeting: String = runtime.get_global("greeting").unwrap();
+    assert_eq!(greeting, "hello");
+    let count: usize = runtime.eval_as("Object.keys(globalThis.onRequest).length").unwrap();
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn test_runtime_pool_concurrent_requests() {
+    use ferrum::{JsRuntime, JsRuntimePool, PoolConfig};
+    use std::sync::Arc;
+
+    init_v8_for_tests();
+
+    let config = PoolConfig {
+        min_idle: 1,
+        max_size: 2,
+        ..PoolConfig::default()
+    };
+    let pool = Arc::new(
+        JsRuntimePool::new(config, || JsRuntime::new(RuntimeConfig::default(), Permissions::default())).unwrap(),
+    );
+
+    let threads: Vec<_> = (0..8)
+        .map(|i| {
+            let pool = pool.clone();
+            std::thread::spawn(move || {
+                pool.run(move |runtime| {
+                    runtime.set_global("input", &i)?;
+                    runtime.eval_as::<u32>("globalThis.seen = (globalThis.seen ?? 0) + input; seen * 2")
+                })
+            })
+        })
+        .collect();
+
+    // Every request starts from a fresh realm, so `seen` never accumulates
+    let mut results: Vec<u32> = threads.into_iter().map(|thread| thread.join().unwrap().unwrap()).collect();
+    results.sort_unstable();
+    assert_eq!(results, (0..8).map(|i| i * 2).collect::<Vec<_>>());
+
+    let metrics = pool.metrics();
+    assert_eq!(metrics.checkouts, 8);
+    assert!(metrics.created <= 2);
+    assert_eq!(metrics.in_use, 0);
+}